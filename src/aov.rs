@@ -0,0 +1,289 @@
+use crate::exr::{self, Channel};
+use crate::float_image::FloatImage;
+use crate::material::Material;
+use crate::{Color, Point3d, Scene, Vector3d};
+use std::collections::HashMap;
+use std::io::Result;
+
+/// An arbitrary output variable: a per pixel buffer rendered alongside the
+/// image for compositing and denoising.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pass {
+    /// The final image, in linear light, as filtered on the film with any
+    /// splats and before denoising.
+    Beauty,
+    /// Light reaching the camera straight from emitters or the sky.
+    Emission,
+    /// Light scattered once, at the first hit, after leaving an emitter or the sky.
+    Direct,
+    /// Light scattered more than once.
+    Indirect,
+    /// Distance from the camera to the closest hit, infinite where nothing was hit.
+    Depth,
+    /// World space shading normal.
+    Normal,
+    Albedo,
+    /// Index of the scene object hit, counting from 1, with 0 for the sky.
+    ObjectId,
+    /// Number of the material hit, as given by `Scene::material_ids`,
+    /// counting from 1, with 0 for the sky.
+    MaterialId,
+    Uv,
+    /// World space position.
+    Position,
+}
+
+impl Pass {
+    pub const ALL: [Pass; 11] = [
+        Pass::Beauty,
+        Pass::Emission,
+        Pass::Direct,
+        Pass::Indirect,
+        Pass::Depth,
+        Pass::Normal,
+        Pass::Albedo,
+        Pass::ObjectId,
+        Pass::MaterialId,
+        Pass::Uv,
+        Pass::Position,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Pass::Beauty => "beauty",
+            Pass::Emission => "emission",
+            Pass::Direct => "direct",
+            Pass::Indirect => "indirect",
+            Pass::Depth => "depth",
+            Pass::Normal => "normal",
+            Pass::Albedo => "albedo",
+            Pass::ObjectId => "object",
+            Pass::MaterialId => "material",
+            Pass::Uv => "uv",
+            Pass::Position => "position",
+        }
+    }
+
+    /// Channel names for the components the pass uses.
+    fn channels(self) -> &'static [&'static str] {
+        match self {
+            Pass::Depth => &["Z"],
+            Pass::ObjectId | Pass::MaterialId => &["id"],
+            Pass::Uv => &["U", "V"],
+            Pass::Normal | Pass::Position => &["X", "Y", "Z"],
+            _ => &["R", "G", "B"],
+        }
+    }
+}
+
+/// What one camera ray found at the first surface it hit.
+#[derive(Clone, Copy, Debug)]
+pub struct FirstHit {
+    pub depth: f64,
+    pub normal: Vector3d,
+    pub albedo: Color,
+    pub position: Point3d,
+    pub uv: (f64, f64),
+    pub object: usize,
+    /// Number of the material, from `Aovs::material_id`.
+    pub material: usize,
+}
+
+/// Everything one camera ray contributes to the passes.
+#[derive(Clone, Copy, Debug)]
+pub struct AovSample {
+    pub emission: Color,
+    pub direct: Color,
+    pub indirect: Color,
+    pub hit: Option<FirstHit>,
+}
+
+impl AovSample {
+    pub fn beauty(&self) -> Color {
+        self.emission + self.direct + self.indirect
+    }
+}
+
+/// A buffer per pass, top row first like `FloatImage`.
+pub struct Aovs {
+    pub width: usize,
+    pub height: usize,
+    passes: Vec<FloatImage>,
+    /// Material numbers by address, from `Scene::material_ids`.
+    materials: HashMap<usize, usize>,
+}
+
+impl Aovs {
+    pub fn new(width: usize, height: usize) -> Self {
+        Aovs {
+            width,
+            height,
+            passes: Pass::ALL
+                .iter()
+                .map(|_| FloatImage::new(width, height))
+                .collect(),
+            materials: HashMap::new(),
+        }
+    }
+
+    /// Numbers the materials of the scene about to be rendered.
+    pub fn number_materials(&mut self, scene: &Scene) {
+        self.materials = scene.material_ids();
+    }
+
+    /// Number of a material of the scene given to `number_materials`.
+    pub fn material_id(&self, material: &Material) -> usize {
+        self.materials[&(material as *const Material as usize)]
+    }
+
+    pub fn get(&self, pass: Pass) -> &FloatImage {
+        &self.passes[pass as usize]
+    }
+
+    pub fn get_mut(&mut self, pass: Pass) -> &mut FloatImage {
+        &mut self.passes[pass as usize]
+    }
+
+    /// Resolves a pixel from its samples. Lighting and surface attributes
+    /// are averaged, with misses counting as zero; depth keeps the closest
+    /// hit, and IDs, which cannot be blended, come from the first sample.
+    pub fn set_pixel(&mut self, x: usize, y: usize, samples: &[AovSample]) {
+        let n = samples.len() as f64;
+        let mean = |f: &dyn Fn(&AovSample) -> Color| samples.iter().map(f).sum::<Color>() / n;
+        let surface =
+            |f: &dyn Fn(&FirstHit) -> Color| mean(&|s| s.hit.as_ref().map_or(Color::black(), f));
+        let gray = |v: f64| Color::new([v, v, v]);
+
+        let depth = samples
+            .iter()
+            .filter_map(|s| s.hit.map(|hit| hit.depth))
+            .fold(f64::INFINITY, f64::min);
+        let first = samples.first().and_then(|s| s.hit);
+        let values = [
+            (Pass::Beauty, mean(&|s| s.beauty())),
+            (Pass::Emission, mean(&|s| s.emission)),
+            (Pass::Direct, mean(&|s| s.direct)),
+            (Pass::Indirect, mean(&|s| s.indirect)),
+            (Pass::Depth, gray(depth)),
+            (Pass::Normal, surface(&|hit| hit.normal)),
+            (Pass::Albedo, surface(&|hit| hit.albedo)),
+            (
+                Pass::ObjectId,
+                gray(first.map_or(0.0, |hit| (hit.object + 1) as f64)),
+            ),
+            (
+                Pass::MaterialId,
+                gray(first.map_or(0.0, |hit| (hit.material + 1) as f64)),
+            ),
+            (
+                Pass::Uv,
+                surface(&|hit| Color::new([hit.uv.0, hit.uv.1, 0.0])),
+            ),
+            (Pass::Position, surface(&|hit| hit.position)),
+        ];
+        for (pass, value) in values {
+            self.get_mut(pass).set(x, y, value);
+        }
+    }
+
+    /// Writes every pass as a layer of one OpenEXR image, the beauty pass
+    /// as the default `R`, `G`, `B` channels.
+    pub fn write_exr(&self, filename: &str) -> Result<()> {
+        let mut channels = Vec::new();
+        for pass in Pass::ALL {
+            for (i, suffix) in pass.channels().iter().enumerate() {
+                let name = match pass {
+                    Pass::Beauty => suffix.to_string(),
+                    _ => format!("{}.{}", pass.name(), suffix),
+                };
+                let data = self.get(pass).data.iter().map(|c| c[i] as f32).collect();
+                channels.push(Channel { name, data });
+            }
+        }
+        exr::write(filename, self.width, self.height, &channels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::geometry::{Hitable, HitableList, Instance, Sphere};
+    use crate::texture::solid;
+    use crate::transform::Transform;
+    use std::sync::Arc;
+
+    fn sample(depth: f64, material: usize) -> AovSample {
+        AovSample {
+            emission: Color::black(),
+            direct: Color::new([0.5, 0.5, 0.5]),
+            indirect: Color::new([0.25, 0.0, 0.0]),
+            hit: Some(FirstHit {
+                depth,
+                normal: Vector3d::new([0.0, 1.0, 0.0]),
+                albedo: Color::white(),
+                position: Point3d::new([0.0, 0.0, -depth]),
+                uv: (0.5, 0.25),
+                object: 2,
+                material,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_set_pixel() {
+        let mut aovs = Aovs::new(2, 1);
+        let miss = AovSample {
+            hit: None,
+            ..sample(0.0, 0)
+        };
+        aovs.set_pixel(0, 0, &[sample(3.0, 0), sample(2.0, 0), miss, miss]);
+        aovs.set_pixel(1, 0, &[sample(1.0, 1)]);
+        assert_eq!(
+            aovs.get(Pass::Beauty).get(0, 0),
+            Color::new([0.75, 0.5, 0.5])
+        );
+        assert_eq!(aovs.get(Pass::Depth).get(0, 0).x(), 2.0);
+        assert_eq!(
+            aovs.get(Pass::Albedo).get(0, 0),
+            Color::new([0.5, 0.5, 0.5])
+        );
+        assert_eq!(aovs.get(Pass::ObjectId).get(0, 0).x(), 3.0);
+        assert_eq!(aovs.get(Pass::MaterialId).get(0, 0).x(), 1.0);
+        assert_eq!(aovs.get(Pass::MaterialId).get(1, 0).x(), 2.0);
+    }
+
+    #[test]
+    fn test_material_ids() {
+        let red = Material::Lambertian(solid(Color::new([1.0, 0.0, 0.0])));
+        let sphere = |x: f64, material: Material| Sphere {
+            center: Point3d::new([x, 0.0, -2.0]),
+            radius: 0.5,
+            material,
+        };
+        let shared: Arc<dyn Hitable> = Arc::new(sphere(0.0, Material::Lambertian(solid(Color::white()))));
+        let scene = Scene {
+            objects: HitableList {
+                hitables: vec![
+                    Box::new(sphere(-1.0, red.clone())),
+                    Box::new(Instance::new(shared.clone(), Transform::identity())),
+                    Box::new(Instance::new(shared, Transform::translate(Vector3d::new([0.0, 1.0, 0.0])))),
+                    Box::new(sphere(1.0, red)),
+                ],
+            },
+            camera: Camera::new(1.0, 2.0, 1.0),
+            fog: None,
+        };
+        let mut aovs = Aovs::new(1, 1);
+        aovs.number_materials(&scene);
+        // Numbered in the order of the objects, with copies of a material
+        // sharing its number.
+        let ids: Vec<usize> = scene
+            .objects
+            .materials()
+            .into_iter()
+            .map(|material| aovs.material_id(material))
+            .collect();
+        assert_eq!(ids, vec![0, 1, 1, 0]);
+    }
+}
@@ -1,10 +1,12 @@
-use crate::{Point3d, Ray, Vector3d};
+use crate::{random, Point3d, Ray, Vector3d};
 
 pub struct Camera {
     origin: Point3d,
     lower_left_corner: Point3d,
     horizontal: Vector3d,
     vertical: Vector3d,
+    shutter_open: f64,
+    shutter_close: f64,
 }
 
 impl Camera {
@@ -20,12 +22,63 @@ impl Camera {
             horizontal,
             vertical,
             lower_left_corner,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
         }
     }
 
+    /// Rays are spread uniformly over `[open, close]` to produce motion blur.
+    pub fn with_shutter(self, open: f64, close: f64) -> Self {
+        Camera {
+            shutter_open: open,
+            shutter_close: close,
+            ..self
+        }
+    }
+
+    /// Angle subtended by one pixel at the center of an image `width` pixels wide.
+    pub fn pixel_spread(&self, width: usize) -> f64 {
+        let center = self.lower_left_corner + self.horizontal / 2.0 + self.vertical / 2.0;
+        let focal_length = (center - self.origin).length();
+        self.horizontal.length() / width as f64 / focal_length
+    }
+
+    /// Where the ray from the camera toward `point` crosses the viewport, as
+    /// the `(u, v)` that `get_ray` takes, with the cosine between that ray and
+    /// the view axis. `None` for points level with or behind the camera.
+    pub fn project(&self, point: Point3d) -> Option<(f64, f64, f64)> {
+        let center = self.lower_left_corner + self.horizontal / 2.0 + self.vertical / 2.0;
+        let focal_length = (center - self.origin).length();
+        let axis = (center - self.origin) / focal_length;
+        let direction = point - self.origin;
+        let along = direction.dot(&axis);
+        if along <= 0.0 {
+            return None;
+        }
+        let offset = self.origin + direction * (focal_length / along) - self.lower_left_corner;
+        let u = offset.dot(&self.horizontal) / self.horizontal.length_squared();
+        let v = offset.dot(&self.vertical) / self.vertical.length_squared();
+        Some((u, v, along / direction.length()))
+    }
+
+    /// Density over solid angle of rays spread evenly over the viewport, in a
+    /// direction making cosine `cos` with the view axis.
+    pub fn direction_pdf(&self, cos: f64) -> f64 {
+        let center = self.lower_left_corner + self.horizontal / 2.0 + self.vertical / 2.0;
+        let focal_length = (center - self.origin).length();
+        let area = self.horizontal.length() * self.vertical.length();
+        focal_length * focal_length / (area * cos.powi(3))
+    }
+
     pub fn get_ray(&self, u: f64, v: f64) -> Ray {
         let origin = self.origin;
         let direction = self.lower_left_corner + self.horizontal * u + self.vertical * v - origin;
-        Ray { origin, direction }
+        let time = self.shutter_open + (self.shutter_close - self.shutter_open) * random::<f64>();
+        Ray {
+            origin,
+            direction,
+            time,
+            spread: 0.0,
+        }
     }
 }
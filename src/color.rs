@@ -4,12 +4,36 @@ use crate::vector3::Vector3;
 pub type Color = Vector3<f64>;
 
 impl Color {
-    pub fn to_pixel(&self, samples_per_pixel: i32) -> Pixel {
+    pub fn to_pixel(self, samples_per_pixel: i32) -> Pixel {
         let ratio: f64 = 1.0 / samples_per_pixel as f64;
         let f = |a: &f64| (255.999 * (ratio * a).sqrt().clamp(0.0, 0.999)) as i32;
         self.data.iter().map(f).collect()
     }
 
+    /// Like `to_pixel` for a single sample, without the gamma, for images
+    /// holding data rather than light.
+    pub fn to_linear_pixel(self) -> Pixel {
+        let f = |a: &f64| (255.999 * a.clamp(0.0, 0.999)) as i32;
+        self.data.iter().map(f).collect()
+    }
+
+    /// Decodes sRGB-encoded components in `[0, 1]` to linear light.
+    pub fn srgb_to_linear(self) -> Self {
+        let f = |c: &f64| {
+            if *c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        self.data.iter().map(f).collect()
+    }
+
+    /// The brightest of the three channels.
+    pub fn max_component(self) -> f64 {
+        self.data.iter().fold(0.0, |a, &b| a.max(b))
+    }
+
     pub fn black() -> Self {
         Color::new([0.0, 0.0, 0.0])
     }
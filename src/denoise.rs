@@ -0,0 +1,178 @@
+use crate::aov::{Aovs, Pass};
+use crate::float_image::FloatImage;
+use crate::Color;
+
+/// B3 spline weights of the 5×5 à-trous kernel, along one axis.
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+/// Edge-avoiding à-trous wavelet filter (Dammertz et al., 2010) for noisy
+/// renders. Lighting is separated from surface color by dividing out the
+/// albedo pass, smoothed over growing footprints while normal, albedo and
+/// depth differences keep it from bleeding across edges, and multiplied
+/// back, so textures stay sharp.
+#[derive(Clone, Copy, Debug)]
+pub struct Denoiser {
+    /// Number of filter passes, each doubling the reach of the last.
+    pub iterations: usize,
+    /// Tolerated lighting difference, halved every pass as noise falls.
+    pub color_sigma: f64,
+    pub normal_sigma: f64,
+    pub albedo_sigma: f64,
+    /// Tolerated depth difference relative to the depth of the pixel, per
+    /// pixel of distance.
+    pub depth_sigma: f64,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Denoiser {
+            iterations: 5,
+            color_sigma: 2.0,
+            normal_sigma: 0.3,
+            albedo_sigma: 0.1,
+            depth_sigma: 0.01,
+        }
+    }
+}
+
+/// Albedo safe to divide by, leaving black and dark pixels untouched.
+fn divisor(albedo: Color) -> Color {
+    albedo
+        .data
+        .iter()
+        .map(|&a| if a < 1e-3 { 1.0 } else { a })
+        .collect()
+}
+
+fn depth_weight(p: f64, q: f64, tolerance: f64) -> f64 {
+    match (p.is_finite(), q.is_finite()) {
+        (true, true) => (-(p - q).abs() / (tolerance * p).max(1e-9)).exp(),
+        (false, false) => 1.0,
+        _ => 0.0,
+    }
+}
+
+impl Denoiser {
+    /// `beauty`, the resolved image, filtered with the guidance of the
+    /// feature passes in `aovs`.
+    pub fn denoise(&self, beauty: &FloatImage, aovs: &Aovs) -> FloatImage {
+        let (width, height) = (aovs.width, aovs.height);
+        let beauty = &beauty.data;
+        let emission = &aovs.get(Pass::Emission).data;
+        let normal = &aovs.get(Pass::Normal).data;
+        let albedo = &aovs.get(Pass::Albedo).data;
+        let depth = &aovs.get(Pass::Depth).data;
+
+        // Emission seen directly is already noise free and left out.
+        let mut lighting: Vec<Color> = (0..width * height)
+            .map(|i| {
+                let inverse: Color = divisor(albedo[i]).data.iter().map(|a| 1.0 / a).collect();
+                (beauty[i] - emission[i]) * inverse
+            })
+            .collect();
+        for iteration in 0..self.iterations {
+            let step = 1 << iteration;
+            let color_sigma2 = (self.color_sigma / (1 << iteration) as f64).powi(2);
+            lighting = (0..width * height)
+                .map(|p| {
+                    let (px, py) = ((p % width) as isize, (p / width) as isize);
+                    let mut sum = Color::black();
+                    let mut total = 0.0;
+                    for (j, ky) in KERNEL.iter().enumerate() {
+                        let qy = py + (j as isize - 2) * step;
+                        if qy < 0 || qy >= height as isize {
+                            continue;
+                        }
+                        for (i, kx) in KERNEL.iter().enumerate() {
+                            let qx = px + (i as isize - 2) * step;
+                            if qx < 0 || qx >= width as isize {
+                                continue;
+                            }
+                            let q = qy as usize * width + qx as usize;
+                            let distance = (lighting[p] - lighting[q]).length_squared()
+                                / color_sigma2
+                                + (normal[p] - normal[q]).length_squared()
+                                    / self.normal_sigma.powi(2)
+                                + (albedo[p] - albedo[q]).length_squared()
+                                    / self.albedo_sigma.powi(2);
+                            let tolerance = self.depth_sigma * step as f64;
+                            let weight = kx
+                                * ky
+                                * (-distance).exp()
+                                * depth_weight(depth[p].x(), depth[q].x(), tolerance);
+                            sum = sum + lighting[q] * weight;
+                            total += weight;
+                        }
+                    }
+                    sum / total
+                })
+                .collect();
+        }
+        FloatImage {
+            width,
+            height,
+            data: (0..width * height)
+                .map(|i| lighting[i] * divisor(albedo[i]) + emission[i])
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aov::{AovSample, FirstHit};
+    use crate::{Point3d, Vector3d};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_smooths_without_crossing_edges() {
+        let size = 32;
+        let mut aovs = Aovs::new(size, size);
+        // Left and right halves face different ways and are lit differently.
+        let level = |x: usize| if x < size / 2 { 0.2 } else { 0.6 };
+        let mut rng = StdRng::seed_from_u64(1);
+        for y in 0..size {
+            for x in 0..size {
+                let noisy = level(x) * 2.0 * rng.gen::<f64>();
+                let normal = if x < size / 2 {
+                    [1.0, 0.0, 0.0]
+                } else {
+                    [0.0, 1.0, 0.0]
+                };
+                let sample = AovSample {
+                    emission: Color::black(),
+                    direct: Color::new([noisy; 3]) * 0.5,
+                    indirect: Color::black(),
+                    hit: Some(FirstHit {
+                        depth: 1.0,
+                        normal: Vector3d::new(normal),
+                        albedo: Color::new([0.5; 3]),
+                        position: Point3d::new([0.0, 0.0, -1.0]),
+                        uv: (0.0, 0.0),
+                        object: 0,
+                        material: 0,
+                    }),
+                };
+                aovs.set_pixel(x, y, &[sample]);
+            }
+        }
+        let denoised = Denoiser::default().denoise(aovs.get(Pass::Beauty), &aovs);
+        let error = |image: &FloatImage| {
+            let mut sum = 0.0;
+            for y in 0..size {
+                for x in 0..size {
+                    sum += (image.get(x, y).x() - level(x) * 0.5).powi(2);
+                }
+            }
+            sum / (size * size) as f64
+        };
+        assert!(error(&denoised) < error(aovs.get(Pass::Beauty)) / 10.0);
+        // Pixels beside the edge keep their own side's level.
+        for y in 0..size {
+            assert!((denoised.get(size / 2 - 1, y).x() - 0.1).abs() < 0.04);
+            assert!((denoised.get(size / 2, y).x() - 0.3).abs() < 0.08);
+        }
+    }
+}
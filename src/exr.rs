@@ -0,0 +1,121 @@
+use std::fs::File;
+use std::io::{Result, Write};
+
+/// One channel of an image, named as in OpenEXR (`R`, `depth.Z`, ...), with
+/// samples row-major from the top row.
+pub struct Channel {
+    pub name: String,
+    pub data: Vec<f32>,
+}
+
+fn attribute(out: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    for s in [name, kind] {
+        out.extend(s.as_bytes());
+        out.push(0);
+    }
+    out.extend((value.len() as i32).to_le_bytes());
+    out.extend(value);
+}
+
+fn box2i(width: usize, height: usize) -> Vec<u8> {
+    [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect()
+}
+
+/// Encodes a single part scanline OpenEXR image with uncompressed 32-bit
+/// float channels.
+pub fn encode(width: usize, height: usize, channels: &[Channel]) -> Vec<u8> {
+    // Channels must be listed, and stored, in name order.
+    let mut channels: Vec<&Channel> = channels.iter().collect();
+    channels.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut out = vec![0x76, 0x2f, 0x31, 0x01];
+    out.extend(2_i32.to_le_bytes());
+    let mut list = Vec::new();
+    for channel in &channels {
+        list.extend(channel.name.as_bytes());
+        list.push(0);
+        // Float samples, not perceptually linear, sampled every pixel.
+        list.extend(2_i32.to_le_bytes());
+        list.extend([0, 0, 0, 0]);
+        list.extend(1_i32.to_le_bytes());
+        list.extend(1_i32.to_le_bytes());
+    }
+    list.push(0);
+    attribute(&mut out, "channels", "chlist", &list);
+    attribute(&mut out, "compression", "compression", &[0]);
+    attribute(&mut out, "dataWindow", "box2i", &box2i(width, height));
+    attribute(&mut out, "displayWindow", "box2i", &box2i(width, height));
+    attribute(&mut out, "lineOrder", "lineOrder", &[0]);
+    attribute(
+        &mut out,
+        "pixelAspectRatio",
+        "float",
+        &1.0_f32.to_le_bytes(),
+    );
+    attribute(&mut out, "screenWindowCenter", "v2f", &[0; 8]);
+    attribute(
+        &mut out,
+        "screenWindowWidth",
+        "float",
+        &1.0_f32.to_le_bytes(),
+    );
+    out.push(0);
+
+    let line_size = channels.len() * width * 4;
+    let table_end = out.len() + height * 8;
+    for y in 0..height {
+        out.extend(((table_end + y * (line_size + 8)) as u64).to_le_bytes());
+    }
+    for y in 0..height {
+        out.extend((y as i32).to_le_bytes());
+        out.extend((line_size as i32).to_le_bytes());
+        for channel in &channels {
+            for v in &channel.data[y * width..(y + 1) * width] {
+                out.extend(v.to_le_bytes());
+            }
+        }
+    }
+    out
+}
+
+pub fn write(filename: &str, width: usize, height: usize, channels: &[Channel]) -> Result<()> {
+    File::create(filename)?.write_all(&encode(width, height, channels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout() {
+        let channels = [
+            Channel {
+                name: "Z".to_string(),
+                data: vec![1.0, 2.0, 3.0, 4.0],
+            },
+            Channel {
+                name: "A".to_string(),
+                data: vec![5.0, 6.0, 7.0, 8.0],
+            },
+        ];
+        let bytes = encode(2, 2, &channels);
+        assert_eq!(bytes[..4], [0x76, 0x2f, 0x31, 0x01]);
+        // The chlist names channels in sorted order.
+        let header = String::from_utf8_lossy(&bytes);
+        assert!(header.find("A\0").unwrap() < header.find("Z\0").unwrap());
+
+        let read_u64 = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+        let read_f32 = |at: usize| f32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        // Offsets are the last 16 bytes before the first chunk.
+        let first = read_u64(bytes.len() - 2 * 24 - 16) as usize;
+        assert_eq!(first, bytes.len() - 2 * 24);
+        let second = read_u64(bytes.len() - 2 * 24 - 8) as usize;
+        assert_eq!(bytes[second..second + 8], [1, 0, 0, 0, 16, 0, 0, 0]);
+        // Within the second line, channel A precedes Z.
+        assert_eq!(read_f32(second + 8), 7.0);
+        assert_eq!(read_f32(second + 16), 3.0);
+    }
+}
@@ -0,0 +1,264 @@
+use crate::float_image::FloatImage;
+use crate::Color;
+use std::f64::consts::PI;
+
+/// Shape of a pixel reconstruction filter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterKind {
+    Box,
+    Tent,
+    Gaussian,
+    /// Mitchell-Netravali cubic with `B = C = 1/3`.
+    Mitchell,
+    /// Sinc windowed by a sinc stretched to the radius.
+    Lanczos,
+}
+
+/// How much a sample counts toward a pixel, by its offset from the pixel
+/// center. Filters are separable and vanish beyond `radius` pixels.
+#[derive(Clone, Copy, Debug)]
+pub struct PixelFilter {
+    pub kind: FilterKind,
+    pub radius: f64,
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+fn mitchell(x: f64) -> f64 {
+    let (b, c) = (1.0 / 3.0, 1.0 / 3.0);
+    let x = x.abs();
+    if x < 1.0 {
+        ((12.0 - 9.0 * b - 6.0 * c) * x.powi(3)
+            + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+            + (6.0 - 2.0 * b))
+            / 6.0
+    } else if x < 2.0 {
+        ((-b - 6.0 * c) * x.powi(3)
+            + (6.0 * b + 30.0 * c) * x * x
+            + (-12.0 * b - 48.0 * c) * x
+            + (8.0 * b + 24.0 * c))
+            / 6.0
+    } else {
+        0.0
+    }
+}
+
+impl PixelFilter {
+    /// The filter with its customary radius.
+    pub fn new(kind: FilterKind) -> Self {
+        let radius = match kind {
+            FilterKind::Box => 0.5,
+            FilterKind::Tent => 1.0,
+            FilterKind::Gaussian => 1.5,
+            FilterKind::Mitchell => 2.0,
+            FilterKind::Lanczos => 3.0,
+        };
+        PixelFilter { kind, radius }
+    }
+
+    /// Weight along one axis at offset `x` from the pixel center.
+    fn eval_1d(&self, x: f64) -> f64 {
+        let r = self.radius;
+        if x.abs() > r {
+            return 0.0;
+        }
+        match self.kind {
+            FilterKind::Box => 1.0,
+            FilterKind::Tent => r - x.abs(),
+            FilterKind::Gaussian => {
+                // Three standard deviations fit in the radius, and the
+                // curve is lowered to reach zero there.
+                let sigma2 = (r / 3.0).powi(2);
+                (-x * x / (2.0 * sigma2)).exp() - (-r * r / (2.0 * sigma2)).exp()
+            }
+            FilterKind::Mitchell => mitchell(2.0 * x / r),
+            FilterKind::Lanczos => sinc(x) * sinc(x / r),
+        }
+    }
+
+    pub fn eval(&self, dx: f64, dy: f64) -> f64 {
+        self.eval_1d(dx) * self.eval_1d(dy)
+    }
+}
+
+impl Default for PixelFilter {
+    fn default() -> Self {
+        PixelFilter::new(FilterKind::Box)
+    }
+}
+
+/// Accumulates radiance samples into pixels through a reconstruction
+/// filter. Each sample is splatted onto every pixel whose center lies
+/// within the filter radius, so neighbouring pixels share samples.
+///
+/// Light that finds the camera from elsewhere in the scene, as in light
+/// tracing, lands on one pixel unfiltered and is summed separately.
+pub struct Film {
+    pub width: usize,
+    pub height: usize,
+    pub filter: PixelFilter,
+    /// Factor applied to the summed splats when resolving, to turn them
+    /// into pixel values.
+    pub splat_scale: f64,
+    sums: Vec<Color>,
+    weights: Vec<f64>,
+    splats: Vec<Color>,
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize, filter: PixelFilter) -> Self {
+        Film {
+            width,
+            height,
+            filter,
+            splat_scale: 1.0,
+            sums: vec![Color::black(); width * height],
+            weights: vec![0.0; width * height],
+            splats: vec![Color::black(); width * height],
+        }
+    }
+
+    /// Adds a sample taken at continuous image position `(x, y)`, where
+    /// pixel `(i, j)` covers `[i, i + 1) × [j, j + 1)` with the top row first.
+    pub fn add_sample(&mut self, x: f64, y: f64, color: Color) {
+        let r = self.filter.radius;
+        // Pixels with centers `c + 0.5` within the radius, as a half open range.
+        let lo = |c: f64| (c - 0.5 - r).ceil().max(0.0) as usize;
+        let hi = |c: f64, n: usize| ((c - 0.5 + r).floor() + 1.0).clamp(0.0, n as f64) as usize;
+        for j in lo(y)..hi(y, self.height) {
+            for i in lo(x)..hi(x, self.width) {
+                let weight = self.filter.eval(i as f64 + 0.5 - x, j as f64 + 0.5 - y);
+                if weight != 0.0 {
+                    let p = j * self.width + i;
+                    self.sums[p] = self.sums[p] + color * weight;
+                    self.weights[p] += weight;
+                }
+            }
+        }
+    }
+
+    /// Adds light landing at image position `(x, y)` to the pixel there,
+    /// ignoring positions off the film.
+    pub fn add_splat(&mut self, x: f64, y: f64, color: Color) {
+        if x < 0.0 || y < 0.0 || x >= self.width as f64 || y >= self.height as f64 {
+            return;
+        }
+        let p = y as usize * self.width + x as usize;
+        self.splats[p] = self.splats[p] + color;
+    }
+
+    /// The filtered image with the scaled splats added. Negative lobes can
+    /// leave pixels slightly below zero, which are clamped.
+    pub fn resolve(&self) -> FloatImage {
+        let data = (0..self.width * self.height)
+            .map(|p| {
+                let splat = self.splats[p] * self.splat_scale;
+                if self.weights[p].abs() < 1e-12 {
+                    return splat;
+                }
+                let color = self.sums[p] / self.weights[p] + splat;
+                color.data.iter().map(|c| c.max(0.0)).collect()
+            })
+            .collect();
+        FloatImage {
+            width: self.width,
+            height: self.height,
+            data,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::random;
+
+    const KINDS: [FilterKind; 5] = [
+        FilterKind::Box,
+        FilterKind::Tent,
+        FilterKind::Gaussian,
+        FilterKind::Mitchell,
+        FilterKind::Lanczos,
+    ];
+
+    #[test]
+    fn test_filter_shapes() {
+        for kind in KINDS {
+            let filter = PixelFilter::new(kind);
+            assert!(filter.eval(0.0, 0.0) > 0.0, "{:?}", kind);
+            assert_eq!(filter.eval(filter.radius + 0.01, 0.0), 0.0);
+            let n = 1000;
+            let dx = 2.0 * filter.radius / n as f64;
+            let area: f64 = (0..n)
+                .map(|i| filter.eval_1d(-filter.radius + (i as f64 + 0.5) * dx) * dx)
+                .sum();
+            // Box, tent and Mitchell have known areas; the rest must
+            // merely leave some weight.
+            let expected = match kind {
+                FilterKind::Box => Some(2.0 * filter.radius),
+                FilterKind::Tent => Some(filter.radius.powi(2)),
+                FilterKind::Mitchell => Some(filter.radius / 2.0),
+                FilterKind::Gaussian | FilterKind::Lanczos => None,
+            };
+            match expected {
+                Some(expected) => assert!((area - expected).abs() < 1e-4, "{:?}: {}", kind, area),
+                None => assert!(area > 0.0, "{:?}", kind),
+            }
+        }
+        // Mitchell is continuous where its pieces meet.
+        assert!((mitchell(1.0 - 1e-9) - mitchell(1.0 + 1e-9)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_box_matches_pixel_average() {
+        let mut film = Film::new(3, 2, PixelFilter::default());
+        let mut sums = [0.0; 6];
+        for _ in 0..600 {
+            let (x, y) = (3.0 * random::<f64>(), 2.0 * random::<f64>());
+            let value = random::<f64>();
+            film.add_sample(x, y, Color::new([value; 3]));
+            sums[y as usize * 3 + x as usize] += value;
+        }
+        let image = film.resolve();
+        for (p, &sum) in sums.iter().enumerate() {
+            let count = film.weights[p];
+            assert!((image.data[p].x() - sum / count).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_splats_added() {
+        let mut film = Film::new(2, 1, PixelFilter::default());
+        film.add_sample(0.5, 0.5, Color::new([0.5; 3]));
+        film.add_splat(0.2, 0.9, Color::new([1.0; 3]));
+        film.add_splat(1.5, 0.5, Color::new([2.0; 3]));
+        film.add_splat(2.0, 0.5, Color::new([4.0; 3]));
+        film.splat_scale = 0.25;
+        let image = film.resolve();
+        assert_eq!(image.data, vec![Color::new([0.75; 3]), Color::new([0.5; 3])]);
+    }
+
+    #[test]
+    fn test_constant_image_preserved() {
+        for kind in KINDS {
+            let mut film = Film::new(8, 8, PixelFilter::new(kind));
+            for _ in 0..8 * 8 * 64 {
+                let (x, y) = (8.0 * random::<f64>(), 8.0 * random::<f64>());
+                film.add_sample(x, y, Color::new([0.3, 0.5, 0.7]));
+            }
+            for c in film.resolve().data {
+                assert!(
+                    (c - Color::new([0.3, 0.5, 0.7])).length() < 1e-9,
+                    "{:?}",
+                    kind
+                );
+            }
+        }
+    }
+}
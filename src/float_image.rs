@@ -0,0 +1,253 @@
+use crate::image::Netpbm;
+use crate::Color;
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Read, Result};
+
+/// A floating point RGB image, stored row-major with the top row first.
+#[derive(Clone, Debug)]
+pub struct FloatImage {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<Color>,
+}
+
+impl FloatImage {
+    pub fn new(width: usize, height: usize) -> Self {
+        FloatImage {
+            width,
+            height,
+            data: vec![Color::black(); width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.data[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: Color) {
+        self.data[y * self.width + x] = color;
+    }
+
+    /// Reads a PNG, PPM/PGM or Radiance HDR file, picked by extension.
+    /// Low dynamic range formats keep their stored encoding, scaled to `[0, 1]`.
+    pub fn load(filename: &str) -> Result<Self> {
+        let extension = filename
+            .rsplit('.')
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => load_png(filename),
+            "hdr" | "pic" => load_hdr(filename),
+            _ => {
+                // Keep the full precision of 16-bit files.
+                let netpbm = Netpbm::load(filename)?;
+                let max_value = netpbm.max_value as f64;
+                let data = netpbm
+                    .pixels()
+                    .map(|c| c.iter().map(|&s| s as f64 / max_value).collect())
+                    .collect();
+                Ok(FloatImage {
+                    width: netpbm.width,
+                    height: netpbm.height,
+                    data,
+                })
+            }
+        }
+    }
+}
+
+fn invalid(filename: &str, msg: impl std::fmt::Display) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("{}: {}", filename, msg))
+}
+
+fn load_png(filename: &str) -> Result<FloatImage> {
+    let mut decoder = png::Decoder::new(File::open(filename)?);
+    // Expand palettes and sub-byte depths so every sample is 8 or 16 bits.
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info().map_err(|e| invalid(filename, e))?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buf)
+        .map_err(|e| invalid(filename, e))?;
+    let channels = info.color_type.samples();
+    let samples: Vec<f64> = match info.bit_depth {
+        png::BitDepth::Sixteen => buf[..info.buffer_size()]
+            .chunks(2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]) as f64 / 65535.0)
+            .collect(),
+        _ => buf[..info.buffer_size()]
+            .iter()
+            .map(|&b| b as f64 / 255.0)
+            .collect(),
+    };
+    let data = samples
+        .chunks(channels)
+        .map(|c| match channels {
+            1 | 2 => Color::new([c[0], c[0], c[0]]),
+            _ => Color::new([c[0], c[1], c[2]]),
+        })
+        .collect();
+    Ok(FloatImage {
+        width: info.width as usize,
+        height: info.height as usize,
+        data,
+    })
+}
+
+/// Radiance RGBE, flat or run-length encoded, in the standard `-Y h +X w` orientation.
+fn load_hdr(filename: &str) -> Result<FloatImage> {
+    let mut reader = BufReader::new(File::open(filename)?);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !line.starts_with("#?") {
+        return Err(invalid(filename, "missing Radiance signature"));
+    }
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid(filename, "truncated header"));
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(invalid(filename, "unsupported pixel format"));
+            }
+        }
+    }
+    line.clear();
+    reader.read_line(&mut line)?;
+    let resolution: Vec<&str> = line.split_whitespace().collect();
+    let (height, width) = match resolution.as_slice() {
+        ["-Y", h, "+X", w] => (h.parse::<usize>(), w.parse::<usize>()),
+        _ => return Err(invalid(filename, "unsupported orientation")),
+    };
+    let (height, width) = (
+        height.map_err(|e| invalid(filename, e))?,
+        width.map_err(|e| invalid(filename, e))?,
+    );
+
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    let mut pos = 0;
+    let mut next = || -> Result<u8> {
+        let b = *bytes
+            .get(pos)
+            .ok_or_else(|| invalid(filename, "truncated data"))?;
+        pos += 1;
+        Ok(b)
+    };
+
+    let mut data = Vec::with_capacity(width * height);
+    let mut scanline = vec![[0_u8; 4]; width];
+    for _ in 0..height {
+        let head = [next()?, next()?, next()?, next()?];
+        let rle = (8..32768).contains(&width)
+            && head[0] == 2
+            && head[1] == 2
+            && ((head[2] as usize) << 8 | head[3] as usize) == width;
+        if rle {
+            // Each channel is stored separately as runs and literal dumps.
+            for channel in 0..4 {
+                let mut x = 0;
+                while x < width {
+                    let count = next()? as usize;
+                    let (count, run) = if count > 128 {
+                        (count - 128, true)
+                    } else {
+                        (count, false)
+                    };
+                    if count == 0 || x + count > width {
+                        return Err(invalid(filename, "bad run length"));
+                    }
+                    let value = if run { next()? } else { 0 };
+                    for pixel in &mut scanline[x..x + count] {
+                        pixel[channel] = if run { value } else { next()? };
+                    }
+                    x += count;
+                }
+            }
+        } else {
+            scanline[0] = head;
+            for pixel in scanline.iter_mut().skip(1) {
+                *pixel = [next()?, next()?, next()?, next()?];
+            }
+        }
+        data.extend(scanline.iter().map(|&[r, g, b, e]| {
+            if e == 0 {
+                Color::black()
+            } else {
+                let f = 2.0_f64.powi(e as i32 - 136);
+                Color::new([r as f64, g as f64, b as f64]) * f
+            }
+        }));
+    }
+    Ok(FloatImage {
+        width,
+        height,
+        data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(name)
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_load_hdr_flat() {
+        let path = temp_path("float_image_flat.hdr");
+        let mut bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n".to_vec();
+        // Mantissas scale by 2^(exponent - 136).
+        bytes.extend([128, 0, 0, 129, 0, 64, 128, 131]);
+        std::fs::write(&path, bytes).unwrap();
+        let image = FloatImage::load(&path).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert!((image.get(0, 0).x() - 1.0).abs() < 1e-9);
+        assert!((image.get(1, 0).y() - 2.0).abs() < 1e-9);
+        assert!((image.get(1, 0).z() - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_load_hdr_rle() {
+        let path = temp_path("float_image_rle.hdr");
+        let mut bytes = b"#?RADIANCE\n\n-Y 1 +X 8\n".to_vec();
+        bytes.extend([2, 2, 0, 8]);
+        // Red: a run of 8; green: 8 literals; blue: run of 8 zeros; exponent: run of 8.
+        bytes.extend([136, 64]);
+        bytes.extend([8, 0, 16, 32, 48, 64, 80, 96, 112]);
+        bytes.extend([136, 0]);
+        bytes.extend([136, 136]);
+        std::fs::write(&path, bytes).unwrap();
+        let image = FloatImage::load(&path).unwrap();
+        assert_eq!(image.width, 8);
+        assert_eq!(image.get(3, 0), Color::new([64.0, 48.0, 0.0]));
+        assert_eq!(image.get(7, 0), Color::new([64.0, 112.0, 0.0]));
+    }
+
+    #[test]
+    fn test_load_png() {
+        let path = temp_path("float_image.png");
+        let file = File::create(&path).unwrap();
+        let mut encoder = png::Encoder::new(file, 2, 1);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[255, 0, 0, 0, 51, 255]).unwrap();
+        writer.finish().unwrap();
+        let image = FloatImage::load(&path).unwrap();
+        assert_eq!(image.get(0, 0), Color::new([1.0, 0.0, 0.0]));
+        assert_eq!(image.get(1, 0), Color::new([0.0, 0.2, 1.0]));
+    }
+}
@@ -0,0 +1,102 @@
+use crate::material::PhaseFunction;
+use crate::math::sample_exponential;
+use crate::{Color, Ray};
+
+/// Homogeneous fog filling all space below the altitude `height`, so rays
+/// rising above it can still reach the sky.
+#[derive(Clone, Copy, Debug)]
+pub struct Fog {
+    /// Extinction coefficient, the chance of scattering per unit length.
+    pub density: f64,
+    pub albedo: Color,
+    pub phase: PhaseFunction,
+    pub height: f64,
+}
+
+impl Fog {
+    /// The span of `ray` before `t_max` lying below the fog's ceiling.
+    fn span(&self, ray: &Ray, t_max: f64) -> Option<(f64, f64)> {
+        let (origin, direction) = (ray.origin.y(), ray.direction.y());
+        // The span of the ray lying below the fog's ceiling.
+        let (t0, t1) = if direction == 0.0 {
+            if origin > self.height {
+                return None;
+            }
+            (0.0, f64::INFINITY)
+        } else {
+            let crossing = (self.height - origin) / direction;
+            if direction > 0.0 {
+                (0.0, crossing)
+            } else {
+                (crossing.max(0.0), f64::INFINITY)
+            }
+        };
+        let t1 = t1.min(t_max);
+        Some((t0, t1)).filter(|_| t0 < t1)
+    }
+
+    /// Picks where along `ray` it scatters before reaching `t_max`, if at
+    /// all, with probability following the transmittance of the fog.
+    pub fn sample_distance(&self, ray: &Ray, t_max: f64) -> Option<f64> {
+        let (t0, t1) = self.span(ray, t_max)?;
+        let distance = sample_exponential(self.density);
+        let t = t0 + distance / ray.direction.length();
+        Some(t).filter(|&t| t < t1)
+    }
+
+    /// Share of light passing along `ray` up to `t_max` without scattering.
+    pub fn transmittance(&self, ray: &Ray, t_max: f64) -> f64 {
+        match self.span(ray, t_max) {
+            Some((t0, t1)) => (-self.density * (t1 - t0) * ray.direction.length()).exp(),
+            None => 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Point3d, Vector3d};
+
+    fn ray(origin: [f64; 3], direction: [f64; 3]) -> Ray {
+        Ray {
+            origin: Point3d::new(origin),
+            direction: Vector3d::new(direction),
+            time: 0.0,
+            spread: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_sample_distance() {
+        let fog = Fog {
+            density: 1e6,
+            albedo: Color::white(),
+            phase: PhaseFunction::Isotropic,
+            height: 1.0,
+        };
+        // Entering from above scatters right at the ceiling.
+        let t = fog.sample_distance(&ray([0.0, 3.0, 0.0], [0.0, -2.0, 0.0]), 10.0);
+        assert!((t.unwrap() - 1.0).abs() < 1e-3);
+        // A surface in front of the fog, or a ray above it, sees none.
+        assert!(fog.sample_distance(&ray([0.0, 3.0, 0.0], [0.0, -2.0, 0.0]), 0.5).is_none());
+        assert!(fog.sample_distance(&ray([0.0, 3.0, 0.0], [1.0, 0.0, 0.0]), 10.0).is_none());
+        assert!(fog.sample_distance(&ray([0.0, 0.0, 0.0], [1.0, 0.0, 0.0]), 10.0).is_some());
+    }
+
+    #[test]
+    fn test_transmittance() {
+        let fog = Fog {
+            density: 0.5,
+            albedo: Color::white(),
+            phase: PhaseFunction::Isotropic,
+            height: 1.0,
+        };
+        // Two units of the ray lie below the ceiling, one in front of it.
+        let down = ray([0.0, 3.0, 0.0], [0.0, -2.0, 0.0]);
+        assert!((fog.transmittance(&down, 2.0) - (-1.0_f64).exp()).abs() < 1e-12);
+        assert_eq!(fog.transmittance(&down, 0.5), 1.0);
+        let up = ray([0.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+        assert!((fog.transmittance(&up, f64::INFINITY) - (-0.5_f64).exp()).abs() < 1e-12);
+    }
+}
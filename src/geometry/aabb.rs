@@ -0,0 +1,67 @@
+use crate::ray::Ray;
+use crate::vector3::Point3d;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Point3d,
+    pub max: Point3d,
+}
+
+impl Aabb {
+    pub fn new(min: Point3d, max: Point3d) -> Self {
+        Aabb { min, max }
+    }
+
+    /// Smallest box containing both `a` and `b`.
+    pub fn surrounding(a: &Aabb, b: &Aabb) -> Self {
+        let min = (0..3).map(|i| a.min[i].min(b.min[i])).collect();
+        let max = (0..3).map(|i| a.max[i].max(b.max[i])).collect();
+        Aabb { min, max }
+    }
+
+    /// Smallest box containing every point.
+    pub fn from_points(points: &[Point3d]) -> Self {
+        let first = Aabb::new(points[0], points[0]);
+        points
+            .iter()
+            .fold(first, |acc, &p| Aabb::surrounding(&acc, &Aabb::new(p, p)))
+    }
+
+    pub fn corners(&self) -> [Point3d; 8] {
+        let pick = |i: usize, axis: usize| {
+            if i & (1 << axis) == 0 {
+                self.min[axis]
+            } else {
+                self.max[axis]
+            }
+        };
+        let mut corners = [self.min; 8];
+        for (i, corner) in corners.iter_mut().enumerate() {
+            *corner = Point3d::new([pick(i, 0), pick(i, 1), pick(i, 2)]);
+        }
+        corners
+    }
+
+    /// Slab test against the ray segment `[t_min, t_max]`.
+    pub fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.clip(ray, t_min, t_max).is_some()
+    }
+
+    /// The part of `[t_min, t_max]` inside the box, if any.
+    pub fn clip(&self, ray: &Ray, mut t_min: f64, mut t_max: f64) -> Option<(f64, f64)> {
+        for axis in 0..3 {
+            let inv_d = 1.0 / ray.direction[axis];
+            let mut t0 = (self.min[axis] - ray.origin[axis]) * inv_d;
+            let mut t1 = (self.max[axis] - ray.origin[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+            if t_max < t_min {
+                return None;
+            }
+        }
+        Some((t_min, t_max))
+    }
+}
@@ -0,0 +1,148 @@
+use crate::geometry::aabb::Aabb;
+use crate::geometry::hitable::{HitRecord, Hitable};
+use crate::material::{Material, PhaseFunction};
+use crate::math::sample_exponential;
+use crate::ray::Ray;
+use crate::texture::Texture;
+use std::sync::Arc;
+
+/// Fog or smoke of uniform density filling a closed `boundary`. A ray "hits"
+/// the medium where it scatters, at an exponentially distributed distance
+/// into the volume, and passes through unaffected otherwise.
+pub struct ConstantMedium {
+    pub boundary: Box<dyn Hitable>,
+    /// Extinction coefficient, the chance of scattering per unit length.
+    pub density: f64,
+    /// Always a `Material::Medium`.
+    pub material: Material,
+}
+
+impl ConstantMedium {
+    pub fn new(
+        boundary: Box<dyn Hitable>,
+        density: f64,
+        albedo: Arc<dyn Texture>,
+        phase: PhaseFunction,
+    ) -> Self {
+        ConstantMedium {
+            boundary,
+            density,
+            material: Material::Medium { albedo, phase },
+        }
+    }
+}
+
+impl Hitable for ConstantMedium {
+    fn hit(&self, ray: &Ray) -> Option<HitRecord<'_>> {
+        let length = ray.direction.length();
+        // Distance to travel before scattering, spent across the spans of
+        // the ray inside the boundary.
+        let mut distance = sample_exponential(self.density);
+        for interval in self.boundary.intervals(ray) {
+            let enter = interval.enter.t.max(0.001);
+            let exit = interval.exit.t;
+            if exit <= enter {
+                continue;
+            }
+            let span = (exit - enter) * length;
+            if distance < span {
+                let t = enter + distance / length;
+                let point = ray.at(t);
+                // Media have no surface; a normal facing the ray keeps
+                // `front_face` meaningful.
+                return Some(HitRecord::new(
+                    ray,
+                    point,
+                    -ray.direction / length,
+                    t,
+                    (0.0, 0.0),
+                    &self.material,
+                ));
+            }
+            distance -= span;
+        }
+        None
+    }
+
+    /// Exact, from the length of `ray` inside the boundary.
+    fn transmittance(&self, ray: &Ray, t_max: f64) -> f64 {
+        let inside: f64 = self
+            .boundary
+            .intervals(ray)
+            .iter()
+            .map(|interval| (interval.exit.t.min(t_max) - interval.enter.t.max(0.001)).max(0.0))
+            .sum();
+        (-self.density * inside * ray.direction.length()).exp()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.boundary.bounding_box()
+    }
+
+    fn materials(&self) -> Vec<&Material> {
+        vec![&self.material]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Sphere;
+    use crate::texture::solid;
+    use crate::{Color, Point3d, Vector3d};
+
+    fn medium(density: f64) -> ConstantMedium {
+        let boundary = Sphere {
+            center: Point3d::new([0.0, 0.0, 0.0]),
+            radius: 1.0,
+            material: Material::Lambertian(solid(Color::white())),
+        };
+        let albedo = solid(Color::white());
+        ConstantMedium::new(
+            Box::new(boundary),
+            density,
+            albedo,
+            PhaseFunction::Isotropic,
+        )
+    }
+
+    fn ray(origin: [f64; 3], direction: [f64; 3]) -> Ray {
+        Ray {
+            origin: Point3d::new(origin),
+            direction: Vector3d::new(direction),
+            time: 0.0,
+            spread: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_density_extremes() {
+        let thick = medium(1e6);
+        let rec = thick.hit(&ray([-3.0, 0.0, 0.0], [2.0, 0.0, 0.0])).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-3);
+        assert!(rec.front_face);
+        assert!(matches!(rec.material, Material::Medium { .. }));
+        let thin = medium(1e-9);
+        assert!(thin.hit(&ray([-3.0, 0.0, 0.0], [1.0, 0.0, 0.0])).is_none());
+        // Rays passing beside the boundary never scatter.
+        assert!(thick.hit(&ray([-3.0, 2.0, 0.0], [1.0, 0.0, 0.0])).is_none());
+    }
+
+    #[test]
+    fn test_transmittance() {
+        // Starting at the center, a unit radius of medium transmits exp(-density).
+        let density = 0.7;
+        let fog = medium(density);
+        let n = 20000;
+        let passed = (0..n)
+            .filter(|_| fog.hit(&ray([0.0, 0.0, 0.0], [0.0, 0.0, 1.0])).is_none())
+            .count();
+        let expected = (-density).exp();
+        assert!((passed as f64 / n as f64 - expected).abs() < 0.02);
+        let shadow = fog.transmittance(&ray([0.0, 0.0, 0.0], [0.0, 0.0, 2.0]), f64::INFINITY);
+        assert!((shadow - expected).abs() < 1e-3);
+        // Only the part of the ray before `t_max` attenuates.
+        let half = fog.transmittance(&ray([0.0, 0.0, 0.0], [0.0, 0.0, 1.0]), 0.5);
+        assert!((half - (-density / 2.0).exp()).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,252 @@
+use crate::geometry::aabb::Aabb;
+use crate::geometry::hitable::{count_test, HitRecord, Hitable, Interval};
+use crate::material::Material;
+use crate::ray::Ray;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CsgOp {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl CsgOp {
+    fn contains(&self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            CsgOp::Union => in_left || in_right,
+            CsgOp::Intersection => in_left && in_right,
+            CsgOp::Difference => in_left && !in_right,
+        }
+    }
+}
+
+/// A boolean combination of two solids, evaluated on whole interval lists
+/// so that nested combinations and carved cavities resolve correctly.
+pub struct Csg {
+    pub op: CsgOp,
+    pub left: Box<dyn Hitable>,
+    pub right: Box<dyn Hitable>,
+}
+
+impl Csg {
+    pub fn union(left: Box<dyn Hitable>, right: Box<dyn Hitable>) -> Self {
+        Csg { op: CsgOp::Union, left, right }
+    }
+
+    pub fn intersection(left: Box<dyn Hitable>, right: Box<dyn Hitable>) -> Self {
+        Csg { op: CsgOp::Intersection, left, right }
+    }
+
+    pub fn difference(left: Box<dyn Hitable>, right: Box<dyn Hitable>) -> Self {
+        Csg { op: CsgOp::Difference, left, right }
+    }
+}
+
+/// Sweeps the boundaries of both lists in `t` order, emitting a span
+/// whenever the combined inside/outside state changes.
+pub fn combine<'a>(op: CsgOp, left: &[Interval<'a>], right: &[Interval<'a>]) -> Vec<Interval<'a>> {
+    let boundaries = |intervals: &[Interval<'a>], is_left: bool| {
+        intervals
+            .iter()
+            .flat_map(|i| [(i.enter, true), (i.exit, false)])
+            .map(move |(rec, enter)| (rec, is_left, enter))
+            .collect::<Vec<_>>()
+    };
+    let mut events = boundaries(left, true);
+    events.extend(boundaries(right, false));
+    events.sort_by(|a, b| a.0.t.total_cmp(&b.0.t));
+
+    let mut result = Vec::new();
+    let (mut in_left, mut in_right) = (false, false);
+    let mut open: Option<HitRecord<'_>> = None;
+    for (rec, is_left, enter) in events {
+        if is_left {
+            in_left = enter;
+        } else {
+            in_right = enter;
+        }
+        // Stored normals always face the ray, so only `front_face` needs
+        // adjusting when a boundary changes roles (e.g. a carved exit).
+        match (open, op.contains(in_left, in_right)) {
+            (None, true) => {
+                open = Some(HitRecord {
+                    front_face: true,
+                    ..rec
+                })
+            }
+            (Some(enter), false) => {
+                let exit = HitRecord {
+                    front_face: false,
+                    ..rec
+                };
+                if exit.t > enter.t {
+                    result.push(Interval { enter, exit });
+                }
+                open = None;
+            }
+            _ => {}
+        }
+    }
+    result
+}
+
+impl Hitable for Csg {
+    fn hit(&self, ray: &Ray) -> Option<HitRecord<'_>> {
+        self.intervals(ray)
+            .into_iter()
+            .flat_map(|i| [i.enter, i.exit])
+            .find(|rec| rec.t >= 0.001 && rec.t.is_finite())
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        match self.op {
+            CsgOp::Union => Some(Aabb::surrounding(
+                &self.left.bounding_box()?,
+                &self.right.bounding_box()?,
+            )),
+            CsgOp::Intersection => {
+                let (a, b) = (self.left.bounding_box(), self.right.bounding_box());
+                match (a, b) {
+                    (Some(a), Some(b)) => {
+                        let min = (0..3).map(|i| a.min[i].max(b.min[i])).collect();
+                        let max = (0..3).map(|i| a.max[i].min(b.max[i])).collect();
+                        Some(Aabb::new(min, max))
+                    }
+                    _ => a.or(b),
+                }
+            }
+            CsgOp::Difference => self.left.bounding_box(),
+        }
+    }
+
+    fn intervals(&self, ray: &Ray) -> Vec<Interval<'_>> {
+        count_test();
+        let left = self.left.intervals(ray);
+        if left.is_empty() && self.op != CsgOp::Union {
+            return left;
+        }
+        combine(self.op, &left, &self.right.intervals(ray))
+    }
+
+    fn materials(&self) -> Vec<&Material> {
+        let mut materials = self.left.materials();
+        materials.extend(self.right.materials());
+        materials
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{HitableList, Instance, Sphere};
+    use crate::material::Material;
+    use crate::transform::Transform;
+    use crate::vector3::{Point3d, Vector3d};
+    use crate::texture::solid;
+    use crate::Color;
+    use std::sync::Arc;
+
+    fn sphere(x: f64, radius: f64) -> Box<dyn Hitable> {
+        Box::new(Sphere {
+            center: Point3d::new([x, 0.0, 0.0]),
+            radius,
+            material: Material::Lambertian(solid(Color::white())),
+        })
+    }
+
+    fn ray_along_x() -> Ray {
+        Ray {
+            origin: Point3d::new([-10.0, 0.0, 0.0]),
+            direction: Vector3d::new([1.0, 0.0, 0.0]),
+            time: 0.0,
+            spread: 0.0,
+        }
+    }
+
+    fn spans(intervals: &[Interval]) -> Vec<(f64, f64)> {
+        intervals.iter().map(|i| (i.enter.t, i.exit.t)).collect()
+    }
+
+    fn assert_spans(actual: Vec<(f64, f64)>, expected: &[(f64, f64)]) {
+        assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a.0 - e.0).abs() < 1e-6 && (a.1 - e.1).abs() < 1e-6, "{:?}", actual);
+        }
+    }
+
+    #[test]
+    fn test_union() {
+        let csg = Csg::union(sphere(0.0, 2.0), sphere(3.0, 2.0));
+        assert_spans(spans(&csg.intervals(&ray_along_x())), &[(8.0, 15.0)]);
+    }
+
+    #[test]
+    fn test_counts_tests() {
+        use crate::geometry::hitable::{take_tests, Counting};
+        let _counting = Counting::start();
+        take_tests();
+        let csg = Csg::union(sphere(0.0, 2.0), sphere(3.0, 2.0));
+        csg.hit(&ray_along_x());
+        // The node and both spheres, all found through `intervals`.
+        assert_eq!(take_tests(), 3);
+    }
+
+    #[test]
+    fn test_intersection() {
+        let csg = Csg::intersection(sphere(0.0, 2.0), sphere(3.0, 2.0));
+        assert_spans(spans(&csg.intervals(&ray_along_x())), &[(11.0, 12.0)]);
+        let rec = csg.hit(&ray_along_x()).unwrap();
+        assert!(rec.front_face);
+        assert!((rec.normal - Vector3d::new([-1.0, 0.0, 0.0])).length() < 1e-9);
+    }
+
+    #[test]
+    fn test_difference_carves_hole() {
+        let csg = Csg::difference(sphere(0.0, 2.0), sphere(0.0, 1.0));
+        assert_spans(
+            spans(&csg.intervals(&ray_along_x())),
+            &[(8.0, 9.0), (11.0, 12.0)],
+        );
+        // Starting inside the cavity, the first surface is the inner wall
+        // seen from the hollow side, which is an entry into the shell.
+        let ray = Ray {
+            origin: Point3d::new([0.0, 0.0, 0.0]),
+            ..ray_along_x()
+        };
+        let rec = csg.hit(&ray).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-9);
+        assert!(rec.front_face);
+    }
+
+    #[test]
+    fn test_nested() {
+        let shell = Csg::difference(sphere(0.0, 2.0), sphere(0.0, 1.0));
+        let csg = Csg::difference(Box::new(shell), sphere(2.0, 0.5));
+        assert_spans(
+            spans(&csg.intervals(&ray_along_x())),
+            &[(8.0, 9.0), (11.0, 11.5)],
+        );
+    }
+
+    #[test]
+    fn test_default_intervals() {
+        // Lists and instances fall back to marching along `hit`.
+        let list = HitableList {
+            hitables: vec![sphere(0.0, 2.0), sphere(3.0, 2.0)],
+        };
+        assert_spans(spans(&list.intervals(&ray_along_x())), &[(8.0, 15.0)]);
+        let inside = Ray {
+            origin: Point3d::new([0.0, 0.0, 0.0]),
+            ..ray_along_x()
+        };
+        let intervals = list.intervals(&inside);
+        assert_eq!(intervals.len(), 1);
+        assert_eq!(intervals[0].enter.t, f64::NEG_INFINITY);
+        assert!((intervals[0].exit.t - 5.0).abs() < 1e-6);
+
+        let object: Arc<dyn Hitable> = Arc::new(list);
+        let moved = Instance::new(object, Transform::translate(Vector3d::new([1.0, 0.0, 0.0])));
+        let csg = Csg::difference(Box::new(moved), sphere(0.0, 1.0));
+        assert_spans(spans(&csg.intervals(&ray_along_x())), &[(11.0, 16.0)]);
+    }
+}
@@ -0,0 +1,331 @@
+use crate::geometry::aabb::Aabb;
+use crate::geometry::hitable::{count_test, HitRecord, Hitable};
+use crate::float_image::FloatImage;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vector3::{Point3d, Vector3d};
+
+/// Terrain over a regular grid of heights, spanning `[0, size.x]` by
+/// `[0, size.z]` with heights scaled by `size.y`. Place it with an `Instance`.
+/// Each grid cell is split into two triangles. It is not sampled as a
+/// light; an emissive one is only found by hitting it.
+pub struct Heightfield {
+    resolution: (usize, usize),
+    heights: Vec<f64>,
+    size: Vector3d,
+    pub material: Material,
+    normals: Vec<Vector3d>,
+    bounds: Aabb,
+}
+
+impl Heightfield {
+    pub fn new(
+        resolution: (usize, usize),
+        heights: Vec<f64>,
+        size: Vector3d,
+        material: Material,
+    ) -> Self {
+        let (nx, nz) = resolution;
+        assert!(nx >= 2 && nz >= 2, "Heightfield needs at least 2x2 samples");
+        assert_eq!(heights.len(), nx * nz, "Heightfield sample count mismatch");
+        let low = heights.iter().cloned().fold(f64::INFINITY, f64::min);
+        let high = heights.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let bounds = Aabb::new(
+            Point3d::new([0.0, low * size.y(), 0.0]),
+            Point3d::new([size.x(), high * size.y(), size.z()]),
+        );
+        let mut heightfield = Heightfield {
+            resolution,
+            heights,
+            size,
+            material,
+            normals: Vec::new(),
+            bounds,
+        };
+        heightfield.normals = (0..nz)
+            .flat_map(|z| (0..nx).map(move |x| (x, z)))
+            .map(|(x, z)| heightfield.vertex_normal(x, z))
+            .collect();
+        heightfield
+    }
+
+    /// Uses the image luminance in `[0, 1]` as height; image rows run along z.
+    /// 16-bit images keep their full precision.
+    pub fn from_image(filename: &str, size: Vector3d, material: Material) -> std::io::Result<Self> {
+        let image = FloatImage::load(filename)?;
+        let heights = image
+            .data
+            .iter()
+            .map(|p| 0.2126 * p.x() + 0.7152 * p.y() + 0.0722 * p.z())
+            .collect();
+        Ok(Heightfield::new(
+            (image.width, image.height),
+            heights,
+            size,
+            material,
+        ))
+    }
+
+    /// Number of samples along x and z.
+    pub fn resolution(&self) -> (usize, usize) {
+        self.resolution
+    }
+
+    /// Row-major, `resolution().0` samples per row.
+    pub fn heights(&self) -> &[f64] {
+        &self.heights
+    }
+
+    pub fn size(&self) -> Vector3d {
+        self.size
+    }
+
+    fn cell_size(&self) -> (f64, f64) {
+        let (nx, nz) = self.resolution;
+        (
+            self.size.x() / (nx - 1) as f64,
+            self.size.z() / (nz - 1) as f64,
+        )
+    }
+
+    fn vertex(&self, x: usize, z: usize) -> Point3d {
+        let (dx, dz) = self.cell_size();
+        let h = self.heights[z * self.resolution.0 + x];
+        Point3d::new([x as f64 * dx, h * self.size.y(), z as f64 * dz])
+    }
+
+    /// Central differences, one-sided at the borders.
+    fn vertex_normal(&self, x: usize, z: usize) -> Vector3d {
+        let (nx, nz) = self.resolution;
+        let (x0, x1) = (x.saturating_sub(1), (x + 1).min(nx - 1));
+        let (z0, z1) = (z.saturating_sub(1), (z + 1).min(nz - 1));
+        let along_x = self.vertex(x1, z) - self.vertex(x0, z);
+        let along_z = self.vertex(x, z1) - self.vertex(x, z0);
+        along_z.cross(&along_x).unit_vector()
+    }
+
+    /// Tests both triangles of cell `(x, z)`, returning the nearest hit in
+    /// `[t_min, t_max]` with its face normal and interpolated normal.
+    fn hit_cell(
+        &self,
+        ray: &Ray,
+        x: usize,
+        z: usize,
+        t_min: f64,
+        t_max: f64,
+    ) -> Option<(f64, Vector3d, Vector3d)> {
+        count_test();
+        let nx = self.resolution.0;
+        let corners = [(x, z), (x + 1, z), (x + 1, z + 1), (x, z + 1)];
+        let p = corners.map(|(x, z)| self.vertex(x, z));
+        let n = corners.map(|(x, z)| self.normals[z * nx + x]);
+        [(0, 1, 2), (0, 2, 3)]
+            .iter()
+            .filter_map(|&(a, b, c)| {
+                let (t, u, v) = intersect_triangle(ray, p[a], p[b], p[c])?;
+                let face = (p[c] - p[a]).cross(&(p[b] - p[a]));
+                let normal = n[a] * (1.0 - u - v) + n[b] * u + n[c] * v;
+                Some((t, face, normal))
+            })
+            .filter(|(t, _, _)| (t_min..=t_max).contains(t))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+}
+
+/// Möller–Trumbore intersection, returning `t` and the barycentrics of `p1`, `p2`.
+pub(crate) fn intersect_triangle(
+    ray: &Ray,
+    p0: Point3d,
+    p1: Point3d,
+    p2: Point3d,
+) -> Option<(f64, f64, f64)> {
+    let e1 = p1 - p0;
+    let e2 = p2 - p0;
+    let h = ray.direction.cross(&e2);
+    let det = e1.dot(&h);
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let s = ray.origin - p0;
+    let u = s.dot(&h) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(&e1);
+    let v = ray.direction.dot(&q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    Some((e2.dot(&q) * inv_det, u, v))
+}
+
+impl Hitable for Heightfield {
+    /// Walks the cells under the ray with a 2D DDA so only cells the ray
+    /// actually passes over are tested.
+    fn hit(&self, ray: &Ray) -> Option<HitRecord<'_>> {
+        let (t_enter, t_exit) = self.bounds.clip(ray, 0.001, f64::INFINITY)?;
+        let (nx, nz) = self.resolution;
+        let (dx, dz) = self.cell_size();
+        let start = ray.at(t_enter);
+        let cell = |p: f64, d: f64, n: usize| ((p / d).floor().max(0.0) as usize).min(n - 2);
+        let (mut x, mut z) = (cell(start.x(), dx, nx), cell(start.z(), dz, nz));
+
+        // Parametric distance to the next cell boundary on each axis, and
+        // between consecutive boundaries.
+        let axis = |index: usize, size: f64, origin: f64, direction: f64| {
+            if direction > 0.0 {
+                let t = ((index + 1) as f64 * size - origin) / direction;
+                (1_isize, t, size / direction)
+            } else if direction < 0.0 {
+                let t = (index as f64 * size - origin) / direction;
+                (-1_isize, t, -size / direction)
+            } else {
+                (0, f64::INFINITY, f64::INFINITY)
+            }
+        };
+        let (step_x, mut next_x, delta_x) = axis(x, dx, ray.origin.x(), ray.direction.x());
+        let (step_z, mut next_z, delta_z) = axis(z, dz, ray.origin.z(), ray.direction.z());
+
+        let mut t = t_enter;
+        loop {
+            let t_next = next_x.min(next_z).min(t_exit);
+            // Pad the window slightly so hits exactly on a cell edge aren't lost.
+            let pad = 1e-9 * (1.0 + t_next.abs());
+            if let Some((t_hit, face, normal)) =
+                self.hit_cell(ray, x, z, (t - pad).max(0.001), t_next + pad)
+            {
+                let point = ray.at(t_hit);
+                let uv = (point.x() / self.size.x(), point.z() / self.size.z());
+                let rec = HitRecord::new(ray, point, face.unit_vector(), t_hit, uv, &self.material);
+                let rec = HitRecord {
+                    footprint: rec.footprint / (self.size.x() * self.size.z()).sqrt(),
+                    ..rec
+                };
+                let dpdu = Vector3d::new([1.0, 0.0, 0.0]);
+                let dpdv = Vector3d::new([0.0, 0.0, 1.0]);
+                return Some(rec.with_tangents(dpdu, dpdv).with_shading_normal(normal));
+            }
+            if t_next >= t_exit {
+                return None;
+            }
+            if next_x < next_z {
+                let stepped = x as isize + step_x;
+                if stepped < 0 || stepped > nx as isize - 2 {
+                    return None;
+                }
+                x = stepped as usize;
+                t = next_x;
+                next_x += delta_x;
+            } else {
+                let stepped = z as isize + step_z;
+                if stepped < 0 || stepped > nz as isize - 2 {
+                    return None;
+                }
+                z = stepped as usize;
+                t = next_z;
+                next_z += delta_z;
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bounds)
+    }
+
+    fn materials(&self) -> Vec<&Material> {
+        vec![&self.material]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::solid;
+    use crate::Color;
+
+    fn material() -> Material {
+        Material::Lambertian(solid(Color::white()))
+    }
+
+    fn ray(origin: [f64; 3], direction: [f64; 3]) -> Ray {
+        Ray {
+            origin: Point3d::new(origin),
+            direction: Vector3d::new(direction),
+            time: 0.0,
+            spread: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_flat() {
+        let field = Heightfield::new(
+            (3, 3),
+            vec![0.5; 9],
+            Vector3d::new([2.0, 2.0, 2.0]),
+            material(),
+        );
+        let rec = field.hit(&ray([0.3, 5.0, 1.7], [0.0, -1.0, 0.0])).unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-9);
+        assert!((rec.normal - Vector3d::new([0.0, 1.0, 0.0])).length() < 1e-9);
+        assert!(field.hit(&ray([2.5, 5.0, 1.0], [0.0, -1.0, 0.0])).is_none());
+    }
+
+    #[test]
+    fn test_ramp() {
+        // Height rises with x, so y == x over the whole grid.
+        let heights = (0..4)
+            .flat_map(|_| (0..4).map(|x| x as f64 / 3.0))
+            .collect();
+        let field = Heightfield::new((4, 4), heights, Vector3d::new([3.0, 3.0, 3.0]), material());
+        let rec = field.hit(&ray([2.0, 5.0, 1.5], [0.0, -1.0, 0.0])).unwrap();
+        assert!((rec.point.y() - 2.0).abs() < 1e-9);
+        let expected = Vector3d::new([-1.0, 1.0, 0.0]).unit_vector();
+        assert!((rec.normal - expected).length() < 1e-9);
+    }
+
+    #[test]
+    fn test_traverses_to_far_peak() {
+        // A single spike in the far corner, approached at a grazing angle.
+        let n = 64;
+        let mut heights = vec![0.0; n * n];
+        heights[(n - 2) * n + (n - 2)] = 1.0;
+        let size = Vector3d::new([63.0, 10.0, 63.0]);
+        let field = Heightfield::new((n, n), heights, size, material());
+        let rec = field.hit(&ray([-1.0, 5.0, -1.0], [1.0, 0.0, 1.0])).unwrap();
+        assert!(rec.point.x() > 61.0 && rec.point.x() < 62.0);
+        let rec = field.hit(&ray([-1.0, 0.5, 30.0], [1.0, 0.0, 0.0]));
+        assert!(rec.is_none());
+    }
+
+    #[test]
+    fn test_from_image() {
+        let path = std::env::temp_dir().join("heightfield_test.pgm");
+        std::fs::write(&path, "P2\n# ramp\n3 2\n10\n0 5 10\n0 5 10\n").unwrap();
+        let field = Heightfield::from_image(
+            path.to_str().unwrap(),
+            Vector3d::new([2.0, 1.0, 1.0]),
+            material(),
+        )
+        .unwrap();
+        assert_eq!(field.resolution(), (3, 2));
+        let rec = field.hit(&ray([1.5, 5.0, 0.5], [0.0, -1.0, 0.0])).unwrap();
+        assert!((rec.point.y() - 0.75).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_from_image_keeps_16_bit_precision() {
+        let path = std::env::temp_dir().join("heightfield_test_16.pgm");
+        let mut data = b"P5\n2 2\n65535\n".to_vec();
+        data.extend_from_slice(&[0, 0, 0, 100, 255, 255, 0, 0]);
+        std::fs::write(&path, data).unwrap();
+        let field = Heightfield::from_image(
+            path.to_str().unwrap(),
+            Vector3d::new([2.0, 1.0, 1.0]),
+            material(),
+        )
+        .unwrap();
+        assert!((field.heights()[1] - 100.0 / 65535.0).abs() < 1e-12);
+        assert!((field.heights()[2] - 1.0).abs() < 1e-12);
+    }
+}
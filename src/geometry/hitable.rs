@@ -1,49 +1,262 @@
+use crate::geometry::aabb::Aabb;
 use crate::material::Material;
 use crate::ray::Ray;
 use crate::vector3::{Point3d, Vector3d};
+use std::cell::Cell;
 use std::cmp::Ordering;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 
 #[derive(Clone, Copy, Debug)]
-pub struct HitRecord {
+pub struct HitRecord<'a> {
     pub point: Point3d,
+    /// Geometric normal of the surface, facing the ray.
     pub normal: Vector3d,
+    /// Normal used for shading, on the same side as `normal`. It differs from
+    /// it for interpolated normals and bump or normal mapping.
+    pub shading_normal: Vector3d,
+    /// Unit tangent following increasing `u`, perpendicular to `shading_normal`.
+    pub tangent: Vector3d,
+    /// Unit bitangent following increasing `v`, completing the shading frame.
+    pub bitangent: Vector3d,
     pub t: f64,
+    /// Surface coordinates for texture lookup, each in `[0, 1]`.
+    pub u: f64,
+    pub v: f64,
+    /// Width of the ray's footprint at the hit in uv units, for texture
+    /// filtering. `new` measures it in world units; primitives rescale it.
+    pub footprint: f64,
     pub front_face: bool,
-    pub material: Material
+    pub material: &'a Material
 }
 
-pub trait Hitable {
-    fn hit(&self, ray: &Ray) -> Option<HitRecord>;
+/// A span of a ray lying inside a solid. `enter.front_face` is always true and
+/// `exit.front_face` always false; an unbounded side has an infinite `t`.
+#[derive(Clone, Copy, Debug)]
+pub struct Interval<'a> {
+    pub enter: HitRecord<'a>,
+    pub exit: HitRecord<'a>,
 }
 
-impl HitRecord {
-    pub fn new(ray: &Ray, point: Point3d, normal: Vector3d, t: f64, material: Material) -> Self {
+pub trait Hitable: Send + Sync {
+    fn hit(&self, ray: &Ray) -> Option<HitRecord<'_>>;
+    /// `None` for objects without finite extent.
+    fn bounding_box(&self) -> Option<Aabb>;
+
+    /// Every span of `ray` inside the object, sorted by `t`. The default walks
+    /// the surface crossings reported by `hit`, so it cannot see behind the
+    /// origin: a ray starting inside gets a span entered at `-inf`.
+    fn intervals(&self, ray: &Ray) -> Vec<Interval<'_>> {
+        const MAX_CROSSINGS: usize = 64;
+        let step = 1e-4 / ray.direction.length();
+        let mut crossings = Vec::new();
+        let mut offset = 0.0;
+        while crossings.len() < MAX_CROSSINGS {
+            let march = Ray {
+                origin: ray.at(offset),
+                ..*ray
+            };
+            let Some(mut rec) = self.hit(&march) else {
+                break;
+            };
+            rec.t += offset;
+            offset = rec.t + step;
+            crossings.push(rec);
+        }
+
+        // Overlapping parts nest, so count depth; exits without a matching
+        // entry mean the origin was already that many levels inside.
+        let mut depth: i32 = 0;
+        let mut lowest = 0;
+        for rec in &crossings {
+            depth += if rec.front_face { 1 } else { -1 };
+            lowest = lowest.min(depth);
+        }
+        let mut depth = -lowest;
+        let mut enter = crossings.first().filter(|_| depth > 0).map(|&rec| HitRecord {
+            t: f64::NEG_INFINITY,
+            front_face: true,
+            ..rec
+        });
+        let mut intervals = Vec::new();
+        for rec in crossings {
+            if rec.front_face {
+                depth += 1;
+                if depth == 1 {
+                    enter = Some(rec);
+                }
+            } else {
+                depth -= 1;
+                if depth == 0 {
+                    if let Some(enter) = enter.take() {
+                        intervals.push(Interval { enter, exit: rec });
+                    }
+                }
+            }
+        }
+        if let Some(enter) = enter {
+            let exit = HitRecord {
+                t: f64::INFINITY,
+                front_face: false,
+                ..enter
+            };
+            intervals.push(Interval { enter, exit });
+        }
+        intervals
+    }
+
+    /// Fraction of light passing along `ray` up to `t_max` through the
+    /// object, for shadow rays: none behind a surface, and an unbiased
+    /// estimate of the transmittance through media. The default treats
+    /// every hit as a surface.
+    fn transmittance(&self, ray: &Ray, t_max: f64) -> f64 {
+        match self.hit(ray) {
+            Some(rec) if rec.t < t_max => 0.0,
+            _ => 1.0,
+        }
+    }
+
+    /// Whether the object gives off light and can be sampled as a light
+    /// through `sample_surface`.
+    fn is_light(&self) -> bool {
+        false
+    }
+
+    /// A point picked uniformly over the surface where it stands at `time`,
+    /// for objects that support it, recorded as if hit from outside so its
+    /// normal points outward.
+    fn sample_surface(&self, _time: f64) -> Option<HitRecord<'_>> {
+        None
+    }
+
+    /// Surface area, giving the density of `sample_surface`.
+    fn area(&self) -> f64 {
+        0.0
+    }
+
+    /// Every material a hit on the object can carry, in a fixed order.
+    fn materials(&self) -> Vec<&Material>;
+}
+
+impl<'a> HitRecord<'a> {
+    pub fn new(ray: &Ray, point: Point3d, normal: Vector3d, t: f64, (u, v): (f64, f64), material: &'a Material) -> Self {
         let front_face = ray.direction.dot(&normal) < 0.0;
         let normal = if front_face { normal } else { -normal };
+        let (tangent, bitangent) = normal.orthonormal_basis();
         HitRecord {
             point,
             normal,
+            shading_normal: normal,
+            tangent,
+            bitangent,
             t,
+            u,
+            v,
+            footprint: ray.spread * t * ray.direction.length(),
             front_face,
             material
         }
     }
+
+    /// Aligns the shading frame with the surface derivatives `dpdu` and `dpdv`.
+    /// Only their directions matter; a degenerate `dpdu` keeps the current frame.
+    pub fn with_tangents(self, dpdu: Vector3d, dpdv: Vector3d) -> Self {
+        let n = self.shading_normal;
+        let tangent = dpdu - n * n.dot(&dpdu);
+        if tangent.length_squared() < 1e-18 {
+            return self;
+        }
+        let tangent = tangent.unit_vector();
+        let bitangent = n.cross(&tangent);
+        let bitangent = if bitangent.dot(&dpdv) < 0.0 { -bitangent } else { bitangent };
+        HitRecord {
+            tangent,
+            bitangent,
+            ..self
+        }
+    }
+
+    /// Replaces the shading normal, flipped to the side of the geometric normal,
+    /// and bends the tangents to stay perpendicular to it.
+    pub fn with_shading_normal(self, shading_normal: Vector3d) -> Self {
+        let n = shading_normal.unit_vector();
+        let n = if n.dot(&self.normal) < 0.0 { -n } else { n };
+        HitRecord {
+            shading_normal: n,
+            ..self
+        }
+        .with_tangents(self.tangent, self.bitangent)
+    }
+
+    /// Expresses a world space direction in the shading frame `(tangent,
+    /// bitangent, shading_normal)`.
+    pub fn world_to_local(&self, world: &Vector3d) -> Vector3d {
+        Vector3d::new([
+            world.dot(&self.tangent),
+            world.dot(&self.bitangent),
+            world.dot(&self.shading_normal),
+        ])
+    }
+
+    /// Expresses a direction given in the shading frame `(tangent, bitangent,
+    /// shading_normal)` in world space.
+    pub fn local_to_world(&self, local: &Vector3d) -> Vector3d {
+        self.tangent * local.x() + self.bitangent * local.y() + self.shading_normal * local.z()
+    }
 }
-impl PartialEq for HitRecord {
+
+impl PartialEq for HitRecord<'_> {
     fn eq(&self, other: &Self) -> bool {
         self.t.eq(&other.t)
     }
 }
 
-impl Eq for HitRecord {}
+impl Eq for HitRecord<'_> {}
+
+thread_local! {
+    static TESTS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Live `Counting` guards; `count_test` counts only while there are any.
+static COUNTING: AtomicUsize = AtomicUsize::new(0);
+
+/// Keeps intersection tests counted on every thread until dropped, so
+/// renders that do not show traversal cost do not pay for counting.
+pub struct Counting(());
+
+impl Counting {
+    pub fn start() -> Self {
+        COUNTING.fetch_add(1, AtomicOrdering::Relaxed);
+        Counting(())
+    }
+}
+
+impl Drop for Counting {
+    fn drop(&mut self) {
+        COUNTING.fetch_sub(1, AtomicOrdering::Relaxed);
+    }
+}
+
+/// Counts one intersection test, a primitive or a node of a hierarchy, on
+/// this thread, for measuring traversal cost. Does nothing unless a
+/// `Counting` guard is alive.
+pub fn count_test() {
+    if COUNTING.load(AtomicOrdering::Relaxed) > 0 {
+        TESTS.with(|tests| tests.set(tests.get() + 1));
+    }
+}
+
+/// Intersection tests counted on this thread since the last call.
+pub fn take_tests() -> usize {
+    TESTS.with(|tests| tests.replace(0))
+}
 
-impl PartialOrd for HitRecord {
+impl PartialOrd for HitRecord<'_> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.t.total_cmp(&other.t))
+        Some(self.cmp(other))
     }
 }
 
-impl Ord for HitRecord {
+impl Ord for HitRecord<'_> {
     fn cmp(&self, other: &Self) -> Ordering {
         self.t.total_cmp(&other.t)
     }
@@ -52,6 +265,7 @@ impl Ord for HitRecord {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::texture::solid;
     use crate::vector3::{Point3d, Vector3d};
     use crate::Color;
 
@@ -60,16 +274,19 @@ mod tests {
         let ray = Ray {
             origin: Point3d::new([0.0, 0.0, 0.0]),
             direction: Vector3d::new([1.0, 0.0, 0.0]),
+            time: 0.0,
+            spread: 0.0,
         };
         let point = Point3d::new([1.0, 2.0, 3.0]);
         let normal = Vector3d::new([-1.0, 0.0, 0.0]);
         let t = 2.0;
-        let hit_record = HitRecord::new(&ray, point, normal, t, Material::Lambertian(Color::white()));
+        let material = Material::Lambertian(solid(Color::white()));
+        let hit_record = HitRecord::new(&ray, point, normal, t, (0.0, 0.0), &material);
 
         assert_eq!(hit_record.point, point);
         assert_eq!(hit_record.normal, normal);
         assert_eq!(hit_record.t, t);
-        assert_eq!(hit_record.front_face, true);
+        assert!(hit_record.front_face);
     }
 
     #[test]
@@ -77,17 +294,19 @@ mod tests {
         let ray = Ray {
             origin: Point3d::new([0.0, 0.0, 0.0]),
             direction: Vector3d::new([1.0, 0.0, 0.0]),
+            time: 0.0,
+            spread: 0.0,
         };
         let point = Point3d::new([1.0, 2.0, 3.0]);
         let normal = Vector3d::new([1.0, 1.0, 0.0]);
         let t = 2.0;
-        let material = Material::Lambertian(Color::white());
-        let hit_record = HitRecord::new(&ray, point, normal, t, material);
+        let material = Material::Lambertian(solid(Color::white()));
+        let hit_record = HitRecord::new(&ray, point, normal, t, (0.0, 0.0), &material);
 
         assert_eq!(hit_record.point, point);
         assert_eq!(hit_record.normal, -normal);
         assert_eq!(hit_record.t, t);
-        assert_eq!(hit_record.front_face, false);
+        assert!(!hit_record.front_face);
     }
 
     #[test]
@@ -95,16 +314,28 @@ mod tests {
         let hit_record1 = HitRecord {
             point: Point3d::new([1.0, 5.0, 3.0]),
             normal: Vector3d::new([3.0, 1.0, 0.0]),
+            shading_normal: Vector3d::new([0.0, 1.0, 0.0]),
+            tangent: Vector3d::new([1.0, 0.0, 0.0]),
+            bitangent: Vector3d::new([0.0, 0.0, 1.0]),
             t: 2.0,
+            u: 0.0,
+            v: 0.0,
+            footprint: 0.0,
             front_face: true,
-            material: Material::Lambertian(Color::black())
+            material: &Material::Lambertian(solid(Color::black()))
         };
         let hit_record2 = HitRecord {
             point: Point3d::new([0.0, 2.0, 6.0]),
             normal: Vector3d::new([1.0, 4.0, 3.0]),
+            shading_normal: Vector3d::new([0.0, 1.0, 0.0]),
+            tangent: Vector3d::new([1.0, 0.0, 0.0]),
+            bitangent: Vector3d::new([0.0, 0.0, 1.0]),
             t: 2.0,
+            u: 0.0,
+            v: 0.0,
+            footprint: 0.0,
             front_face: true,
-            material: Material::Lambertian(Color::white())
+            material: &Material::Lambertian(solid(Color::white()))
         };
 
         assert_eq!(hit_record1, hit_record2);
@@ -115,19 +346,57 @@ mod tests {
         let hit_record1 = HitRecord {
             point: Point3d::new([1.0, 2.0, 3.0]),
             normal: Vector3d::new([0.0, 1.0, 0.0]),
+            shading_normal: Vector3d::new([0.0, 1.0, 0.0]),
+            tangent: Vector3d::new([1.0, 0.0, 0.0]),
+            bitangent: Vector3d::new([0.0, 0.0, 1.0]),
             t: 2.0,
+            u: 0.0,
+            v: 0.0,
+            footprint: 0.0,
             front_face: true,
-            material: Material::Lambertian(Color::white())
+            material: &Material::Lambertian(solid(Color::white()))
         };
         let hit_record2 = HitRecord {
             point: Point3d::new([1.0, 2.0, 3.0]),
             normal: Vector3d::new([0.0, 1.0, 0.0]),
+            shading_normal: Vector3d::new([0.0, 1.0, 0.0]),
+            tangent: Vector3d::new([1.0, 0.0, 0.0]),
+            bitangent: Vector3d::new([0.0, 0.0, 1.0]),
             t: 3.0,
+            u: 0.0,
+            v: 0.0,
+            footprint: 0.0,
             front_face: true,
-            material: Material::Lambertian(Color::white())
+            material: &Material::Lambertian(solid(Color::white()))
         };
 
         assert!(hit_record1 < hit_record2);
         assert!(hit_record2 > hit_record1);
     }
+
+    #[test]
+    fn test_shading_frame() {
+        let ray = Ray {
+            origin: Point3d::new([0.0, 5.0, 0.0]),
+            direction: Vector3d::new([0.0, -1.0, 0.0]),
+            time: 0.0,
+            spread: 0.0,
+        };
+        let material = Material::Lambertian(solid(Color::white()));
+        let up = Vector3d::new([0.0, 1.0, 0.0]);
+        let rec = HitRecord::new(&ray, Point3d::new([0.0, 0.0, 0.0]), up, 5.0, (0.0, 0.0), &material);
+        let (t, b) = (rec.tangent, rec.bitangent);
+        assert!(t.dot(&b).abs() < 1e-12 && t.dot(&up).abs() < 1e-12 && b.dot(&up).abs() < 1e-12);
+
+        // v runs along +z, so the bitangent must too, whichever way the frame turns.
+        let rec = rec.with_tangents(Vector3d::new([2.0, 0.5, 0.0]), Vector3d::new([0.0, 0.0, 3.0]));
+        assert!((rec.tangent - Vector3d::new([1.0, 0.0, 0.0])).length() < 1e-12);
+        assert!((rec.bitangent - Vector3d::new([0.0, 0.0, 1.0])).length() < 1e-12);
+
+        let tilted = rec.with_shading_normal(Vector3d::new([-1.0, -1.0, 0.0]));
+        assert!((tilted.shading_normal - Vector3d::new([1.0, 1.0, 0.0]).unit_vector()).length() < 1e-12);
+        assert!(tilted.tangent.dot(&tilted.shading_normal).abs() < 1e-12);
+        assert!(tilted.bitangent.z() > 0.99);
+        assert_eq!(tilted.normal, up);
+    }
 }
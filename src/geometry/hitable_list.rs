@@ -1,17 +1,49 @@
-use crate::geometry::hitable::{HitRecord, Hitable};
+use crate::geometry::aabb::Aabb;
+use crate::geometry::hitable::{count_test, HitRecord, Hitable};
+use crate::material::Material;
 use crate::ray::Ray;
 
 pub struct HitableList {
-    pub hitables: std::vec::Vec<Box<dyn Hitable + Sync>>,
+    pub hitables: std::vec::Vec<Box<dyn Hitable>>,
 }
 
-impl Hitable for HitableList {
-    fn hit(&self, r: &Ray) -> Option<HitRecord> {
+impl HitableList {
+    /// The closest hit along with the index of the object it belongs to.
+    pub fn hit_object(&self, r: &Ray) -> Option<(usize, HitRecord<'_>)> {
         self.hitables
             .iter()
-            .map(|a| a.hit(r))
-            .filter(|x| x.is_some())
-            .min()
-            .flatten()
+            .enumerate()
+            .filter_map(|(i, a)| {
+                count_test();
+                Some((i, a.hit(r)?))
+            })
+            .min_by(|(_, a), (_, b)| a.cmp(b))
+    }
+}
+
+impl Hitable for HitableList {
+    fn hit(&self, r: &Ray) -> Option<HitRecord<'_>> {
+        self.hit_object(r).map(|(_, rec)| rec)
+    }
+
+    fn transmittance(&self, r: &Ray, t_max: f64) -> f64 {
+        let mut transmittance = 1.0;
+        for a in &self.hitables {
+            transmittance *= a.transmittance(r, t_max);
+            if transmittance == 0.0 {
+                break;
+            }
+        }
+        transmittance
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let mut boxes = self.hitables.iter().map(|a| a.bounding_box());
+        let first = boxes.next()??;
+        boxes.try_fold(first, |acc, b| Some(Aabb::surrounding(&acc, &b?)))
+    }
+
+    fn materials(&self) -> Vec<&Material> {
+        self.hitables.iter().flat_map(|a| a.materials()).collect()
     }
 }
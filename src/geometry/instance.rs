@@ -0,0 +1,232 @@
+use crate::geometry::aabb::Aabb;
+use crate::geometry::hitable::{count_test, HitRecord, Hitable, Interval};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::transform::{AnimatedTransform, Transform};
+use std::sync::Arc;
+
+/// A transformed reference to a shared object, so many copies cost one allocation.
+/// When `animation` is set it replaces `transform`, evaluated at each ray's time.
+///
+/// An instance of a light is a light too, unless it is animated or stretched:
+/// its area could then change over time or its points be unevenly spread,
+/// so it is only found by hitting it.
+pub struct Instance {
+    pub object: Arc<dyn Hitable>,
+    pub transform: Transform,
+    pub animation: Option<AnimatedTransform>,
+}
+
+impl Instance {
+    pub fn new(object: Arc<dyn Hitable>, transform: Transform) -> Self {
+        Instance {
+            object,
+            transform,
+            animation: None,
+        }
+    }
+
+    pub fn animated(object: Arc<dyn Hitable>, animation: AnimatedTransform) -> Self {
+        Instance {
+            object,
+            transform: Transform::identity(),
+            animation: Some(animation),
+        }
+    }
+
+    pub fn transform_at(&self, time: f64) -> Transform {
+        match &self.animation {
+            Some(animation) => animation.at(time),
+            None => self.transform,
+        }
+    }
+}
+
+// The normal matrix preserves the sign of dot(direction, normal),
+// so `front_face` computed in object space is still valid.
+fn to_world<'a>(transform: &Transform, rec: HitRecord<'a>) -> HitRecord<'a> {
+    HitRecord {
+        point: transform.apply_point(&rec.point),
+        normal: transform.apply_normal(&rec.normal).unit_vector(),
+        shading_normal: transform.apply_normal(&rec.shading_normal).unit_vector(),
+        ..rec
+    }
+    .with_tangents(transform.apply_vector(&rec.tangent), transform.apply_vector(&rec.bitangent))
+}
+
+impl Hitable for Instance {
+    fn hit(&self, ray: &Ray) -> Option<HitRecord<'_>> {
+        count_test();
+        let transform = self.transform_at(ray.time);
+        let local_ray = transform.inverse_ray(ray);
+        let rec = self.object.hit(&local_ray)?;
+        Some(to_world(&transform, rec))
+    }
+
+    fn intervals(&self, ray: &Ray) -> Vec<Interval<'_>> {
+        count_test();
+        let transform = self.transform_at(ray.time);
+        let local_ray = transform.inverse_ray(ray);
+        self.object
+            .intervals(&local_ray)
+            .into_iter()
+            .map(|i| Interval {
+                enter: to_world(&transform, i.enter),
+                exit: to_world(&transform, i.exit),
+            })
+            .collect()
+    }
+
+    fn transmittance(&self, ray: &Ray, t_max: f64) -> f64 {
+        let local_ray = self.transform_at(ray.time).inverse_ray(ray);
+        self.object.transmittance(&local_ray, t_max)
+    }
+
+    fn is_light(&self) -> bool {
+        self.animation.is_none()
+            && self.transform.uniform_scale().is_some()
+            && self.object.is_light()
+    }
+
+    fn sample_surface(&self, time: f64) -> Option<HitRecord<'_>> {
+        if !self.is_light() {
+            return None;
+        }
+        Some(to_world(&self.transform, self.object.sample_surface(time)?))
+    }
+
+    fn area(&self) -> f64 {
+        let scale = self.transform.uniform_scale().unwrap_or(0.0);
+        self.object.area() * scale * scale
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let aabb = self.object.bounding_box()?;
+        match &self.animation {
+            Some(animation) => Some(animation.apply_aabb(&aabb)),
+            None => Some(self.transform.apply_aabb(&aabb)),
+        }
+    }
+
+    fn materials(&self) -> Vec<&Material> {
+        self.object.materials()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Sphere;
+    use crate::material::Material;
+    use crate::quaternion::Quaternion;
+    use crate::transform::Keyframe;
+    use crate::vector3::{Point3d, Vector3d};
+    use crate::texture::solid;
+    use crate::Color;
+
+    fn unit_sphere() -> Arc<dyn Hitable> {
+        Arc::new(Sphere {
+            center: Point3d::new([0.0, 0.0, 0.0]),
+            radius: 1.0,
+            material: Material::Lambertian(solid(Color::white())),
+        })
+    }
+
+    #[test]
+    fn test_translated_hit() {
+        let transform = Transform::translate(Vector3d::new([0.0, 0.0, -5.0]));
+        let instance = Instance::new(unit_sphere(), transform);
+        let ray = Ray {
+            origin: Point3d::new([0.0, 0.0, 0.0]),
+            direction: Vector3d::new([0.0, 0.0, -1.0]),
+            time: 0.0,
+            spread: 0.0,
+        };
+        let rec = instance.hit(&ray).unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-9);
+        assert!((rec.point - Point3d::new([0.0, 0.0, -4.0])).length() < 1e-9);
+        assert!((rec.normal - Vector3d::new([0.0, 0.0, 1.0])).length() < 1e-9);
+        assert!(rec.front_face);
+    }
+
+    #[test]
+    fn test_scaled_normal() {
+        let transform = Transform::scale(Vector3d::new([2.0, 1.0, 1.0]));
+        let instance = Instance::new(unit_sphere(), transform);
+        let ray = Ray {
+            origin: Point3d::new([5.0, 0.0, 0.0]),
+            direction: Vector3d::new([-1.0, 0.0, 0.0]),
+            time: 0.0,
+            spread: 0.0,
+        };
+        let rec = instance.hit(&ray).unwrap();
+        assert!((rec.t - 3.0).abs() < 1e-9);
+        assert!((rec.normal.length() - 1.0).abs() < 1e-9);
+        assert!((rec.normal - Vector3d::new([1.0, 0.0, 0.0])).length() < 1e-9);
+    }
+
+    #[test]
+    fn test_animated_hit() {
+        let one = Vector3d::new([1.0, 1.0, 1.0]);
+        let animation = AnimatedTransform::new(vec![
+            Keyframe::new(0.0, Vector3d::new([0.0, 0.0, -5.0]), Quaternion::identity(), one),
+            Keyframe::new(1.0, Vector3d::new([4.0, 0.0, -5.0]), Quaternion::identity(), one),
+        ]);
+        let instance = Instance::animated(unit_sphere(), animation);
+        let ray = |time| Ray {
+            origin: Point3d::new([4.0, 0.0, 0.0]),
+            direction: Vector3d::new([0.0, 0.0, -1.0]),
+            time,
+            spread: 0.0,
+        };
+        assert!(instance.hit(&ray(0.0)).is_none());
+        assert!((instance.hit(&ray(1.0)).unwrap().t - 4.0).abs() < 1e-9);
+        let aabb = instance.bounding_box().unwrap();
+        assert!((aabb.min - Point3d::new([-1.0, -1.0, -6.0])).length() < 1e-9);
+        assert!((aabb.max - Point3d::new([5.0, 1.0, -4.0])).length() < 1e-9);
+    }
+
+    #[test]
+    fn test_shared_object() {
+        let object = unit_sphere();
+        let instances: Vec<Instance> = (0..1000)
+            .map(|i| Vector3d::new([i as f64 * 3.0, 0.0, 0.0]))
+            .map(|offset| Instance::new(object.clone(), Transform::translate(offset)))
+            .collect();
+        assert_eq!(Arc::strong_count(&object), 1001);
+        let aabb = instances[10].bounding_box().unwrap();
+        assert_eq!(aabb.min, Point3d::new([29.0, -1.0, -1.0]));
+        assert_eq!(aabb.max, Point3d::new([31.0, 1.0, 1.0]));
+    }
+
+    #[test]
+    fn test_light_sampling() {
+        // A light under a blend is still a light.
+        let glow = Material::Mix {
+            a: Box::new(Material::DiffuseLight(solid(Color::white()))),
+            b: Box::new(Material::Lambertian(solid(Color::white()))),
+            factor: solid(Color::new([0.5; 3])),
+        };
+        let light: Arc<dyn Hitable> = Arc::new(Sphere {
+            center: Point3d::new([0.0, 0.0, 0.0]),
+            radius: 1.0,
+            material: glow,
+        });
+        let center = Point3d::new([0.0, 0.0, -5.0]);
+        let transform = Transform::translate(center - Point3d::new([0.0; 3]))
+            * Transform::scale(Vector3d::new([2.0, 2.0, 2.0]));
+        let instance = Instance::new(light.clone(), transform);
+        assert!(instance.is_light());
+        assert!((instance.area() - 16.0 * std::f64::consts::PI).abs() < 1e-9);
+        for _ in 0..100 {
+            let rec = instance.sample_surface(0.0).unwrap();
+            let outward = (rec.point - center) / 2.0;
+            assert!((outward.length() - 1.0).abs() < 1e-9);
+            assert!((rec.normal - outward).length() < 1e-9);
+        }
+        // Stretched, its points would no longer be spread evenly.
+        let stretched = Instance::new(light, Transform::scale(Vector3d::new([2.0, 1.0, 1.0])));
+        assert!(!stretched.is_light());
+        assert!(stretched.sample_surface(0.0).is_none());
+    }
+}
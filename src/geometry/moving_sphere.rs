@@ -0,0 +1,129 @@
+use crate::geometry::aabb::Aabb;
+use crate::geometry::hitable::{HitRecord, Hitable, Interval};
+use crate::geometry::sphere::{hit_sphere, sample_sphere, sphere_box, sphere_intervals};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vector3::Point3d;
+use std::f64::consts::PI;
+
+/// A sphere moving linearly from `center0` at `time0` to `center1` at `time1`.
+pub struct MovingSphere {
+    pub center0: Point3d,
+    pub center1: Point3d,
+    pub time0: f64,
+    pub time1: f64,
+    pub radius: f64,
+    pub material: Material,
+}
+
+impl MovingSphere {
+    /// Center at `time`; a sphere whose interval is empty stays at `center0`.
+    pub fn center(&self, time: f64) -> Point3d {
+        let span = self.time1 - self.time0;
+        if span == 0.0 {
+            return self.center0;
+        }
+        self.center0 + (self.center1 - self.center0) * ((time - self.time0) / span)
+    }
+}
+
+impl Hitable for MovingSphere {
+    fn hit(&self, ray: &Ray) -> Option<HitRecord<'_>> {
+        hit_sphere(self.center(ray.time), self.radius, &self.material, ray)
+    }
+
+    /// Encloses the sphere over the whole `[time0, time1]` interval.
+    fn bounding_box(&self) -> Option<Aabb> {
+        let box0 = sphere_box(self.center0, self.radius);
+        let box1 = sphere_box(self.center1, self.radius);
+        Some(Aabb::surrounding(&box0, &box1))
+    }
+
+    fn intervals(&self, ray: &Ray) -> Vec<Interval<'_>> {
+        sphere_intervals(self.center(ray.time), self.radius, &self.material, ray)
+    }
+
+    fn is_light(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn sample_surface(&self, time: f64) -> Option<HitRecord<'_>> {
+        Some(sample_sphere(self.center(time), self.radius, &self.material, time))
+    }
+
+    fn area(&self) -> f64 {
+        4.0 * PI * self.radius * self.radius
+    }
+
+    fn materials(&self) -> Vec<&Material> {
+        vec![&self.material]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector3::Vector3d;
+    use crate::texture::solid;
+    use crate::Color;
+
+    fn moving_sphere() -> MovingSphere {
+        MovingSphere {
+            center0: Point3d::new([0.0, 0.0, -5.0]),
+            center1: Point3d::new([2.0, 0.0, -5.0]),
+            time0: 0.0,
+            time1: 1.0,
+            radius: 1.0,
+            material: Material::Lambertian(solid(Color::white())),
+        }
+    }
+
+    #[test]
+    fn test_center() {
+        let sphere = moving_sphere();
+        assert_eq!(sphere.center(0.5), Point3d::new([1.0, 0.0, -5.0]));
+    }
+
+    #[test]
+    fn test_static_interval() {
+        let sphere = MovingSphere {
+            time1: 0.0,
+            ..moving_sphere()
+        };
+        assert_eq!(sphere.center(0.5), sphere.center0);
+    }
+
+    #[test]
+    fn test_hit_depends_on_time() {
+        let sphere = moving_sphere();
+        let ray = |time| Ray {
+            origin: Point3d::new([2.0, 0.0, 0.0]),
+            direction: Vector3d::new([0.0, 0.0, -1.0]),
+            time,
+            spread: 0.0,
+        };
+        assert!(sphere.hit(&ray(0.0)).is_none());
+        let rec = sphere.hit(&ray(1.0)).unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let aabb = moving_sphere().bounding_box().unwrap();
+        assert_eq!(aabb.min, Point3d::new([-1.0, -1.0, -6.0]));
+        assert_eq!(aabb.max, Point3d::new([3.0, 1.0, -4.0]));
+    }
+
+    #[test]
+    fn test_light_sampled_where_it_is() {
+        let light = MovingSphere {
+            material: Material::DiffuseLight(solid(Color::white())),
+            ..moving_sphere()
+        };
+        assert!(light.is_light());
+        assert!(!moving_sphere().is_light());
+        let rec = light.sample_surface(1.0).unwrap();
+        assert!(((rec.point - light.center1).length() - 1.0).abs() < 1e-9);
+        assert!(rec.front_face);
+    }
+}
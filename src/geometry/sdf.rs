@@ -0,0 +1,348 @@
+use crate::geometry::aabb::Aabb;
+use crate::geometry::hitable::{count_test, HitRecord, Hitable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vector3::{Point3d, Vector3d};
+
+/// A signed distance expression. Primitives are centered on the origin;
+/// use `Translate` to place them.
+pub enum SdfNode {
+    Sphere {
+        radius: f64,
+    },
+    Box {
+        half_extents: Vector3d,
+    },
+    /// Lies in the xz plane around the y axis.
+    Torus {
+        major: f64,
+        minor: f64,
+    },
+    Capsule {
+        a: Point3d,
+        b: Point3d,
+        radius: f64,
+    },
+    Translate {
+        offset: Vector3d,
+        node: Box<SdfNode>,
+    },
+    Union(Box<SdfNode>, Box<SdfNode>),
+    Intersection(Box<SdfNode>, Box<SdfNode>),
+    Difference(Box<SdfNode>, Box<SdfNode>),
+    /// Blends the two surfaces over a region of width `k`.
+    SmoothUnion {
+        a: Box<SdfNode>,
+        b: Box<SdfNode>,
+        k: f64,
+    },
+    /// Tiles space with cells of size `period`; a zero component disables
+    /// repetition along that axis.
+    Repeat {
+        period: Vector3d,
+        node: Box<SdfNode>,
+    },
+    /// Rotates each slice around the y axis by `rate` radians per unit height.
+    Twist {
+        rate: f64,
+        node: Box<SdfNode>,
+    },
+}
+
+impl SdfNode {
+    pub fn distance(&self, p: Point3d) -> f64 {
+        match self {
+            SdfNode::Sphere { radius } => p.length() - radius,
+            SdfNode::Box { half_extents } => {
+                let q: Vector3d = (0..3).map(|i| p[i].abs() - half_extents[i]).collect();
+                let outside: Vector3d = q.data.iter().map(|a| a.max(0.0)).collect();
+                outside.length() + q.x().max(q.y()).max(q.z()).min(0.0)
+            }
+            SdfNode::Torus { major, minor } => {
+                let ring = (p.x().powi(2) + p.z().powi(2)).sqrt() - major;
+                (ring.powi(2) + p.y().powi(2)).sqrt() - minor
+            }
+            SdfNode::Capsule { a, b, radius } => {
+                let pa = p - *a;
+                let ba = *b - *a;
+                let h = (pa.dot(&ba) / ba.length_squared()).clamp(0.0, 1.0);
+                (pa - ba * h).length() - radius
+            }
+            SdfNode::Translate { offset, node } => node.distance(p - *offset),
+            SdfNode::Union(a, b) => a.distance(p).min(b.distance(p)),
+            SdfNode::Intersection(a, b) => a.distance(p).max(b.distance(p)),
+            SdfNode::Difference(a, b) => a.distance(p).max(-b.distance(p)),
+            SdfNode::SmoothUnion { a, b, k } => {
+                let (d1, d2) = (a.distance(p), b.distance(p));
+                let h = (0.5 + 0.5 * (d2 - d1) / k).clamp(0.0, 1.0);
+                d2 + (d1 - d2) * h - k * h * (1.0 - h)
+            }
+            SdfNode::Repeat { period, node } => {
+                let q = (0..3)
+                    .map(|i| {
+                        if period[i] == 0.0 {
+                            p[i]
+                        } else {
+                            p[i] - period[i] * (p[i] / period[i]).round()
+                        }
+                    })
+                    .collect();
+                node.distance(q)
+            }
+            SdfNode::Twist { rate, node } => {
+                let (s, c) = (rate * p.y()).sin_cos();
+                let q = Point3d::new([c * p.x() - s * p.z(), p.y(), s * p.x() + c * p.z()]);
+                // Twisting stretches space by up to this factor, so scale the
+                // distance down to keep it a conservative bound.
+                let r = (p.x().powi(2) + p.z().powi(2)).sqrt();
+                node.distance(q) / (1.0 + (rate * r).powi(2)).sqrt()
+            }
+        }
+    }
+
+    /// `None` when the expression is unbounded, e.g. under `Repeat`.
+    pub fn bounding_box(&self) -> Option<Aabb> {
+        let cube = |h: Vector3d| Aabb::new(-h, h);
+        match self {
+            SdfNode::Sphere { radius } => Some(cube(Vector3d::new([*radius; 3]))),
+            SdfNode::Box { half_extents } => Some(cube(*half_extents)),
+            SdfNode::Torus { major, minor } => {
+                let r = major + minor;
+                Some(cube(Vector3d::new([r, *minor, r])))
+            }
+            SdfNode::Capsule { a, b, radius } => {
+                let r = Vector3d::new([*radius; 3]);
+                Some(Aabb::from_points(&[*a - r, *a + r, *b - r, *b + r]))
+            }
+            SdfNode::Translate { offset, node } => {
+                let aabb = node.bounding_box()?;
+                Some(Aabb::new(aabb.min + *offset, aabb.max + *offset))
+            }
+            SdfNode::Union(a, b) => Some(Aabb::surrounding(&a.bounding_box()?, &b.bounding_box()?)),
+            SdfNode::Intersection(a, b) => a.bounding_box().or_else(|| b.bounding_box()),
+            SdfNode::Difference(a, _) => a.bounding_box(),
+            SdfNode::SmoothUnion { a, b, k } => {
+                let aabb = Aabb::surrounding(&a.bounding_box()?, &b.bounding_box()?);
+                let pad = Vector3d::new([k / 4.0; 3]);
+                Some(Aabb::new(aabb.min - pad, aabb.max + pad))
+            }
+            SdfNode::Repeat { .. } => None,
+            SdfNode::Twist { node, .. } => {
+                let aabb = node.bounding_box()?;
+                let r = aabb
+                    .corners()
+                    .iter()
+                    .map(|c| (c.x().powi(2) + c.z().powi(2)).sqrt())
+                    .fold(0.0, f64::max);
+                Some(Aabb::new(
+                    Point3d::new([-r, aabb.min.y(), -r]),
+                    Point3d::new([r, aabb.max.y(), r]),
+                ))
+            }
+        }
+    }
+}
+
+/// A surface rendered by sphere tracing an `SdfNode`. Its area is not
+/// known, so it cannot be sampled as a light; emissive ones are only found
+/// by hitting them.
+pub struct Sdf {
+    /// Private so it cannot change under the cached `bounds`.
+    root: SdfNode,
+    pub material: Material,
+    /// Distance below which the march counts as a hit.
+    pub epsilon: f64,
+    pub max_steps: usize,
+    /// How far to march when the expression is unbounded.
+    pub max_distance: f64,
+    bounds: Option<Aabb>,
+}
+
+impl Sdf {
+    pub fn new(root: SdfNode, material: Material) -> Self {
+        let bounds = root.bounding_box();
+        Sdf {
+            root,
+            material,
+            epsilon: 1e-4,
+            max_steps: 256,
+            max_distance: 100.0,
+            bounds,
+        }
+    }
+
+    pub fn root(&self) -> &SdfNode {
+        &self.root
+    }
+
+    /// Central-difference gradient using the tetrahedron technique.
+    pub fn normal(&self, p: Point3d) -> Vector3d {
+        let h = self.epsilon;
+        [[1.0, -1.0, -1.0], [-1.0, -1.0, 1.0], [-1.0, 1.0, -1.0], [1.0, 1.0, 1.0]]
+            .iter()
+            .map(|&k| {
+                let k = Vector3d::new(k);
+                k * self.root.distance(p + k * h)
+            })
+            .sum::<Vector3d>()
+            .unit_vector()
+    }
+}
+
+impl Hitable for Sdf {
+    fn hit(&self, ray: &Ray) -> Option<HitRecord<'_>> {
+        // March in world units along the normalized direction.
+        let length = ray.direction.length();
+        let direction = ray.direction / length;
+        let (start, end) = match &self.bounds {
+            Some(aabb) => {
+                let (t0, t1) = aabb.clip(ray, 0.001, f64::INFINITY)?;
+                (t0 * length, t1 * length)
+            }
+            None => (0.001 * length, self.max_distance),
+        };
+        // Step off the surface first if the ray starts on it.
+        let mut s = 0.001 * length;
+        while self.root.distance(ray.origin + direction * s).abs() < self.epsilon && s < end {
+            s += self.epsilon * 2.0;
+        }
+        s = s.max(start);
+        for _ in 0..self.max_steps {
+            if s > end {
+                return None;
+            }
+            count_test();
+            let point = ray.origin + direction * s;
+            let d = self.root.distance(point);
+            if d.abs() < self.epsilon {
+                let normal = self.normal(point);
+                // Distance fields have no natural parameterization; use solid textures.
+                let uv = (0.0, 0.0);
+                return Some(HitRecord::new(ray, point, normal, s / length, uv, &self.material));
+            }
+            s += d.abs();
+        }
+        None
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bounds
+    }
+
+    fn materials(&self) -> Vec<&Material> {
+        vec![&self.material]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::solid;
+    use crate::Color;
+
+    fn ray(origin: [f64; 3], direction: [f64; 3]) -> Ray {
+        Ray {
+            origin: Point3d::new(origin),
+            direction: Vector3d::new(direction),
+            time: 0.0,
+            spread: 0.0,
+        }
+    }
+
+    fn sdf(root: SdfNode) -> Sdf {
+        Sdf::new(root, Material::Lambertian(solid(Color::white())))
+    }
+
+    #[test]
+    fn test_sphere_matches_analytic() {
+        let sphere = sdf(SdfNode::Sphere { radius: 1.0 });
+        let rec = sphere.hit(&ray([0.0, 0.0, 5.0], [0.0, 0.0, -2.0])).unwrap();
+        assert!((rec.t - 2.0).abs() < 1e-3);
+        assert!((rec.normal - Vector3d::new([0.0, 0.0, 1.0])).length() < 1e-3);
+        assert!(rec.front_face);
+    }
+
+    #[test]
+    fn test_hit_from_inside() {
+        let sphere = sdf(SdfNode::Sphere { radius: 1.0 });
+        let rec = sphere.hit(&ray([0.0, 0.0, 0.0], [1.0, 0.0, 0.0])).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-3);
+        assert!(!rec.front_face);
+    }
+
+    #[test]
+    fn test_box_and_translate() {
+        let node = SdfNode::Translate {
+            offset: Vector3d::new([0.0, 0.0, -3.0]),
+            node: Box::new(SdfNode::Box {
+                half_extents: Vector3d::new([1.0, 2.0, 0.5]),
+            }),
+        };
+        assert!((node.distance(Point3d::new([0.0, 0.0, 0.0])) - 2.5).abs() < 1e-9);
+        assert!((node.distance(Point3d::new([0.0, 0.0, -3.0])) + 0.5).abs() < 1e-9);
+        let sdf = sdf(node);
+        let rec = sdf.hit(&ray([0.5, 1.5, 0.0], [0.0, 0.0, -1.0])).unwrap();
+        assert!((rec.t - 2.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_smooth_union_blends() {
+        let a = SdfNode::Sphere { radius: 1.0 };
+        let b = SdfNode::Translate {
+            offset: Vector3d::new([1.5, 0.0, 0.0]),
+            node: Box::new(SdfNode::Sphere { radius: 1.0 }),
+        };
+        let p = Point3d::new([0.75, 1.0, 0.0]);
+        let hard = a.distance(p).min(b.distance(p));
+        let smooth = SdfNode::SmoothUnion {
+            a: Box::new(a),
+            b: Box::new(b),
+            k: 0.5,
+        };
+        assert!(smooth.distance(p) < hard);
+    }
+
+    #[test]
+    fn test_repeat_is_periodic() {
+        let node = SdfNode::Repeat {
+            period: Vector3d::new([2.0, 0.0, 2.0]),
+            node: Box::new(SdfNode::Sphere { radius: 0.5 }),
+        };
+        let p = Point3d::new([0.3, 0.2, 0.1]);
+        let shifted = p + Vector3d::new([4.0, 0.0, -6.0]);
+        assert!((node.distance(p) - node.distance(shifted)).abs() < 1e-9);
+        assert!(node.bounding_box().is_none());
+        let sdf = sdf(node);
+        let rec = sdf.hit(&ray([6.0, 5.0, -4.0], [0.0, -1.0, 0.0])).unwrap();
+        assert!((rec.t - 4.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_twist_and_torus() {
+        let torus = SdfNode::Torus {
+            major: 1.0,
+            minor: 0.25,
+        };
+        let p = Point3d::new([1.0, 0.0, 0.0]);
+        assert!((torus.distance(p) + 0.25).abs() < 1e-9);
+        let sdf_torus = sdf(torus);
+        let rec = sdf_torus.hit(&ray([1.0, 3.0, 0.0], [0.0, -1.0, 0.0])).unwrap();
+        assert!((rec.t - 2.75).abs() < 1e-3);
+
+        // A thin slab along x, turned a quarter turn by height 1.
+        let slab = SdfNode::Box {
+            half_extents: Vector3d::new([1.0, 2.0, 0.1]),
+        };
+        let p = Point3d::new([0.0, 1.0, 0.9]);
+        assert!(slab.distance(p) > 0.0);
+        let twisted = SdfNode::Twist {
+            rate: std::f64::consts::FRAC_PI_2,
+            node: Box::new(slab),
+        };
+        assert!(twisted.distance(p) < 0.0);
+        assert!(twisted.distance(Point3d::new([0.9, 1.0, 0.0])) > 0.0);
+        let sdf = sdf(twisted);
+        let rec = sdf.hit(&ray([0.0, 1.0, 5.0], [0.0, 0.0, -1.0])).unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-3);
+    }
+}
@@ -1,7 +1,9 @@
-use crate::geometry::hitable::{HitRecord, Hitable};
+use crate::geometry::aabb::Aabb;
+use crate::geometry::hitable::{count_test, HitRecord, Hitable, Interval};
 use crate::material::Material;
 use crate::ray::Ray;
-use crate::vector3::Point3d;
+use crate::vector3::{Point3d, Vector3d};
+use std::f64::consts::PI;
 
 pub struct Sphere {
     pub center: Point3d,
@@ -9,29 +11,114 @@ pub struct Sphere {
     pub material: Material
 }
 
-impl Hitable for Sphere {
-    fn hit(&self, ray: &Ray) -> Option<HitRecord> {
-        let oc = ray.origin - self.center;
-        let a = ray.direction.length_squared();
-        let half_b = oc.dot(&ray.direction);
-        let c = oc.length_squared() - self.radius.powi(2);
-        let discriminant = half_b.powi(2) - a * c;
-        if discriminant < 0.0 {
+/// Spherical coordinates of a point on the unit sphere: `u` runs around the
+/// y axis starting from -x, `v` from the bottom pole to the top.
+pub fn sphere_uv(p: &Point3d) -> (f64, f64) {
+    let theta = (-p.y()).acos();
+    let phi = (-p.z()).atan2(p.x()) + PI;
+    (phi / (2.0 * PI), theta / PI)
+}
+
+pub(crate) fn hit_sphere<'a>(center: Point3d, radius: f64, material: &'a Material, ray: &Ray) -> Option<HitRecord<'a>> {
+    count_test();
+    let oc = ray.origin - center;
+    let a = ray.direction.length_squared();
+    let half_b = oc.dot(&ray.direction);
+    let c = oc.length_squared() - radius.powi(2);
+    let discriminant = half_b.powi(2) - a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let sqrtd = discriminant.sqrt();
+
+    // Find the nearest root that lies in the acceptable range.
+    let mut t = (-half_b - sqrtd) / a;
+    if !(0.001..=1e10).contains(&t) {
+        t = (-half_b + sqrtd) / a;
+        if !(0.001..=1e10).contains(&t) {
             return None;
         }
-        let sqrtd = discriminant.sqrt();
-
-        // Find the nearest root that lies in the acceptable range.
-        let mut t = (-half_b - sqrtd) / a;
-        if t < 0.001 || 1e10 < t {
-            t = (-half_b + sqrtd) / a;
-            if t < 0.0 || 1e10 < t {
-                return None;
-            }
-        }
-        let point = ray.at(t);
-        let normal = (point - self.center) / self.radius;
-        let material = self.material;
-        return Some(HitRecord::new(ray, point, normal, t, material));
+    }
+    Some(sphere_record(center, radius, material, ray, t))
+}
+
+fn sphere_record<'a>(center: Point3d, radius: f64, material: &'a Material, ray: &Ray, t: f64) -> HitRecord<'a> {
+    let point = ray.at(t);
+    let normal = (point - center) / radius;
+    let rec = HitRecord::new(ray, point, normal, t, sphere_uv(&normal), material);
+    // u winds counterclockwise around +y seen from above, v climbs towards +y.
+    let dpdu = Vector3d::new([normal.z(), 0.0, -normal.x()]);
+    let dpdv = Vector3d::new([0.0, 1.0, 0.0]);
+    // The uv square covers the sphere's area of 4 pi r^2.
+    HitRecord {
+        footprint: rec.footprint / (2.0 * PI.sqrt() * radius),
+        ..rec
+    }
+    .with_tangents(dpdu, dpdv)
+}
+
+/// Both crossings of the full line, including those behind the origin.
+pub(crate) fn sphere_intervals<'a>(center: Point3d, radius: f64, material: &'a Material, ray: &Ray) -> Vec<Interval<'a>> {
+    count_test();
+    let oc = ray.origin - center;
+    let a = ray.direction.length_squared();
+    let half_b = oc.dot(&ray.direction);
+    let c = oc.length_squared() - radius.powi(2);
+    let discriminant = half_b.powi(2) - a * c;
+    if discriminant <= 0.0 {
+        return Vec::new();
+    }
+    let sqrtd = discriminant.sqrt();
+    vec![Interval {
+        enter: sphere_record(center, radius, material, ray, (-half_b - sqrtd) / a),
+        exit: sphere_record(center, radius, material, ray, (-half_b + sqrtd) / a),
+    }]
+}
+
+/// A point picked uniformly over the sphere, seen from outside.
+pub(crate) fn sample_sphere(center: Point3d, radius: f64, material: &Material, time: f64) -> HitRecord<'_> {
+    let normal = Vector3d::random_in_unit_vector();
+    let point = center + normal * radius;
+    let ray = Ray {
+        origin: point + normal,
+        direction: -normal,
+        time,
+        spread: 0.0,
+    };
+    sphere_record(center, radius, material, &ray, 1.0)
+}
+
+pub(crate) fn sphere_box(center: Point3d, radius: f64) -> Aabb {
+    let r = Vector3d::new([radius, radius, radius]);
+    Aabb::new(center - r, center + r)
+}
+
+impl Hitable for Sphere {
+    fn hit(&self, ray: &Ray) -> Option<HitRecord<'_>> {
+        hit_sphere(self.center, self.radius, &self.material, ray)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(sphere_box(self.center, self.radius))
+    }
+
+    fn intervals(&self, ray: &Ray) -> Vec<Interval<'_>> {
+        sphere_intervals(self.center, self.radius, &self.material, ray)
+    }
+
+    fn is_light(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn sample_surface(&self, time: f64) -> Option<HitRecord<'_>> {
+        Some(sample_sphere(self.center, self.radius, &self.material, time))
+    }
+
+    fn area(&self) -> f64 {
+        4.0 * PI * self.radius * self.radius
+    }
+
+    fn materials(&self) -> Vec<&Material> {
+        vec![&self.material]
     }
 }
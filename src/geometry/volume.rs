@@ -0,0 +1,240 @@
+use crate::geometry::aabb::Aabb;
+use crate::geometry::hitable::{HitRecord, Hitable};
+use crate::material::{Material, PhaseFunction};
+use crate::math::{random, sample_exponential};
+use crate::ray::Ray;
+use crate::texture::{solid, Texture};
+use crate::voxel_grid::VoxelGrid;
+use crate::{Color, Point3d};
+use std::sync::Arc;
+
+/// Heterogeneous medium such as smoke or fire, with a `VoxelGrid` stretched
+/// over `bounds`. Like `ConstantMedium`, a ray hits the volume where it
+/// scatters, found by delta tracking against the grid's largest extinction.
+/// Where it is absorbed instead, the hit carries an emissive material
+/// glowing with the grid's emission there, ending the path.
+pub struct VoxelVolume {
+    /// Private, like the scale, so neither can change under the cached
+    /// majorant and emission lookup.
+    grid: Arc<VoxelGrid>,
+    bounds: Aabb,
+    /// Multiplies the grid's density and absorption.
+    density_scale: f64,
+    majorant: f64,
+    scatter_material: Material,
+    emission_material: Material,
+}
+
+/// The emission channel of a volume, looked up at world positions.
+#[derive(Debug)]
+struct GridEmission {
+    grid: Arc<VoxelGrid>,
+    bounds: Aabb,
+}
+
+impl Texture for GridEmission {
+    fn value(&self, _u: f64, _v: f64, point: &Point3d) -> Color {
+        self.grid.emission(&to_grid(&self.bounds, point))
+    }
+}
+
+/// Position of `point` in the unit cube spanned by `bounds`.
+fn to_grid(bounds: &Aabb, point: &Point3d) -> Point3d {
+    (0..3)
+        .map(|i| (point[i] - bounds.min[i]) / (bounds.max[i] - bounds.min[i]))
+        .collect()
+}
+
+impl VoxelVolume {
+    pub fn new(
+        grid: Arc<VoxelGrid>,
+        bounds: Aabb,
+        density_scale: f64,
+        phase: PhaseFunction,
+    ) -> Self {
+        let voxels = grid.density.len();
+        assert!(
+            grid.absorption.is_empty() || grid.absorption.len() == voxels,
+            "VoxelVolume absorption size mismatch"
+        );
+        assert!(
+            grid.emission.is_empty() || grid.emission.len() == voxels,
+            "VoxelVolume emission size mismatch"
+        );
+        let emission = GridEmission {
+            grid: grid.clone(),
+            bounds,
+        };
+        VoxelVolume {
+            majorant: grid.max_extinction() * density_scale,
+            grid,
+            bounds,
+            density_scale,
+            // Absorption is decided by tracking, so scattering is lossless.
+            scatter_material: Material::Medium {
+                albedo: solid(Color::white()),
+                phase,
+            },
+            emission_material: Material::DiffuseLight(Arc::new(emission)),
+        }
+    }
+
+    pub fn grid(&self) -> &VoxelGrid {
+        &self.grid
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+
+    pub fn density_scale(&self) -> f64 {
+        self.density_scale
+    }
+
+    /// Scattering and absorption coefficients at a world position.
+    fn coefficients(&self, point: &Point3d) -> (f64, f64) {
+        let p = to_grid(&self.bounds, point);
+        (
+            self.grid.density(&p) * self.density_scale,
+            self.grid.absorption(&p) * self.density_scale,
+        )
+    }
+
+    /// The span of `ray` inside the bounds, starting no earlier than `0.001`.
+    fn span(&self, ray: &Ray, t_max: f64) -> Option<(f64, f64)> {
+        if self.majorant <= 0.0 {
+            return None;
+        }
+        self.bounds.clip(ray, 0.001, t_max)
+    }
+}
+
+impl Hitable for VoxelVolume {
+    fn hit(&self, ray: &Ray) -> Option<HitRecord<'_>> {
+        let (mut t, t1) = self.span(ray, f64::INFINITY)?;
+        let length = ray.direction.length();
+        let step = self.majorant * length;
+        loop {
+            t += sample_exponential(step);
+            if t >= t1 {
+                return None;
+            }
+            let point = ray.at(t);
+            let (scattering, absorption) = self.coefficients(&point);
+            // Tentative collisions are real in proportion to the local
+            // extinction, and null collisions continue tracking.
+            let xi = random::<f64>() * self.majorant;
+            let material = if xi < absorption {
+                &self.emission_material
+            } else if xi < absorption + scattering {
+                &self.scatter_material
+            } else {
+                continue;
+            };
+            return Some(HitRecord::new(
+                ray,
+                point,
+                -ray.direction / length,
+                t,
+                (0.0, 0.0),
+                material,
+            ));
+        }
+    }
+
+    /// Ratio tracking: every tentative collision keeps the share of light
+    /// the local extinction leaves.
+    fn transmittance(&self, ray: &Ray, t_max: f64) -> f64 {
+        let Some((mut t, t1)) = self.span(ray, t_max) else {
+            return 1.0;
+        };
+        let step = self.majorant * ray.direction.length();
+        let mut transmittance = 1.0;
+        loop {
+            t += sample_exponential(step);
+            if t >= t1 {
+                return transmittance;
+            }
+            let (scattering, absorption) = self.coefficients(&ray.at(t));
+            transmittance *= 1.0 - (scattering + absorption) / self.majorant;
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bounds)
+    }
+
+    fn materials(&self) -> Vec<&Material> {
+        vec![&self.scatter_material, &self.emission_material]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::with_stream;
+    use crate::Vector3d;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn slab(density: f64) -> VoxelVolume {
+        // Density rising linearly from 0 to 2 along x.
+        let grid = VoxelGrid::new([2, 1, 1], vec![0.0, 2.0]);
+        let bounds = Aabb::new(
+            Point3d::new([-0.5, 0.0, 0.0]),
+            Point3d::new([1.5, 1.0, 1.0]),
+        );
+        VoxelVolume::new(Arc::new(grid), bounds, density, PhaseFunction::Isotropic)
+    }
+
+    fn ray_along_x() -> Ray {
+        Ray {
+            origin: Point3d::new([-1.0, 0.5, 0.5]),
+            direction: Vector3d::new([1.0, 0.0, 0.0]),
+            time: 0.0,
+            spread: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_tracking_matches_optical_depth() {
+        // Between the voxel centers at x = 0 and x = 1 density ramps from 0
+        // to 2, and it is clamped outside, so the optical depth is 0 + 1 + 1.
+        let volume = slab(0.5);
+        let expected = (-1.0_f64).exp();
+        let n = 20000;
+        let ray = ray_along_x();
+        let (_, (passed, ratio)) = with_stream(StdRng::seed_from_u64(2), || {
+            let passed = (0..n).filter(|_| volume.hit(&ray).is_none()).count();
+            let ratio: f64 = (0..n)
+                .map(|_| volume.transmittance(&ray, f64::INFINITY))
+                .sum();
+            (passed, ratio)
+        });
+        assert!((passed as f64 / n as f64 - expected).abs() < 0.02);
+        assert!((ratio / n as f64 - expected).abs() < 0.01);
+        // Only the part of the ray before `t_max` attenuates.
+        assert_eq!(volume.transmittance(&ray, 1.0), 1.0);
+    }
+
+    #[test]
+    fn test_absorption_emits() {
+        let mut grid = VoxelGrid::new([1, 1, 1], vec![0.0]);
+        grid.absorption = vec![1e3];
+        grid.emission = vec![[2.0, 1.0, 0.5]];
+        let bounds = Aabb::new(Point3d::new([0.0, 0.0, 0.0]), Point3d::new([1.0, 1.0, 1.0]));
+        let volume = VoxelVolume::new(Arc::new(grid), bounds, 1.0, PhaseFunction::Isotropic);
+        let rec = volume.hit(&ray_along_x()).unwrap();
+        assert!(rec.material.scatter(&ray_along_x(), &rec).is_none());
+        assert_eq!(rec.material.emitted(&rec), Color::new([2.0, 1.0, 0.5]));
+    }
+
+    #[test]
+    #[should_panic(expected = "absorption size mismatch")]
+    fn test_rejects_short_channels() {
+        let mut grid = VoxelGrid::new([2, 1, 1], vec![0.0, 2.0]);
+        grid.absorption = vec![1.0];
+        let bounds = Aabb::new(Point3d::new([0.0, 0.0, 0.0]), Point3d::new([1.0, 1.0, 1.0]));
+        VoxelVolume::new(Arc::new(grid), bounds, 1.0, PhaseFunction::Isotropic);
+    }
+}
@@ -1,7 +1,7 @@
 use crate::Pixel;
 
 use std::fs::File;
-use std::io::{Result, Write};
+use std::io::{Error, ErrorKind, Read, Result, Write};
 
 pub struct Image {
     pub canvas: Vec<Vec<Pixel>>,
@@ -22,10 +22,27 @@ impl Image {
         }
     }
 
+    /// Reads a PGM (P2/P5) or PPM (P3/P6) file, rescaling samples to 0-255.
+    /// Grayscale images are expanded to three equal channels.
+    pub fn load(filename: &str) -> Result<Self> {
+        let netpbm = Netpbm::load(filename)?;
+        let scale = |s: usize| (s * 255 / netpbm.max_value) as i32;
+        let pixels: Vec<Pixel> = netpbm
+            .pixels()
+            .map(|c| Pixel::new([scale(c[0]), scale(c[1]), scale(c[2])]))
+            .collect();
+        Ok(Image {
+            canvas: pixels.chunks(netpbm.width).map(|row| row.to_vec()).collect(),
+            filename: filename.to_string(),
+            width: netpbm.width,
+            height: netpbm.height,
+        })
+    }
+
     pub fn output(&self) -> Result<()> {
         let mut file = File::create(&self.filename)?;
         let ppm_header = format!("P3\n{} {}\n255\n", self.width, self.height);
-        file.write(ppm_header.as_bytes())?;
+        file.write_all(ppm_header.as_bytes())?;
         let mut pixels_str: String = self
             .canvas
             .iter()
@@ -38,3 +55,95 @@ impl Image {
         Ok(())
     }
 }
+
+/// The samples of a PGM or PPM file as stored, from 0 to `max_value`.
+pub(crate) struct Netpbm {
+    pub width: usize,
+    pub height: usize,
+    pub channels: usize,
+    pub max_value: usize,
+    pub samples: Vec<usize>,
+}
+
+impl Netpbm {
+    pub fn load(filename: &str) -> Result<Self> {
+        let mut bytes = Vec::new();
+        File::open(filename)?.read_to_end(&mut bytes)?;
+        let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, format!("{}: {}", filename, msg));
+
+        // Header tokens are whitespace separated, with `#` comments.
+        let mut pos = 0;
+        let mut token = || -> Option<String> {
+            loop {
+                while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+                    pos += 1;
+                }
+                if pos < bytes.len() && bytes[pos] == b'#' {
+                    while pos < bytes.len() && bytes[pos] != b'\n' {
+                        pos += 1;
+                    }
+                    continue;
+                }
+                break;
+            }
+            let start = pos;
+            while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            (start < pos).then(|| String::from_utf8_lossy(&bytes[start..pos]).into_owned())
+        };
+        let magic = token().ok_or_else(|| invalid("empty file"))?;
+        let (channels, binary) = match magic.as_str() {
+            "P2" => (1, false),
+            "P3" => (3, false),
+            "P5" => (1, true),
+            "P6" => (3, true),
+            _ => return Err(invalid("unsupported format")),
+        };
+        let mut number = || -> Result<usize> {
+            token()
+                .and_then(|t| t.parse().ok())
+                .ok_or_else(|| invalid("malformed header"))
+        };
+        let width = number()?;
+        let height = number()?;
+        let max_value = number()?;
+        if max_value == 0 || max_value > 65535 {
+            return Err(invalid("invalid maximum value"));
+        }
+
+        let count = width * height * channels;
+        let samples: Vec<usize> = if binary {
+            // Exactly one whitespace byte separates the header from the data.
+            let start = pos + 1;
+            let size = if max_value > 255 { 2 } else { 1 };
+            let data = bytes
+                .get(start..start + count * size)
+                .ok_or_else(|| invalid("truncated data"))?;
+            if size == 2 {
+                data.chunks(2).map(|b| (b[0] as usize) << 8 | b[1] as usize).collect()
+            } else {
+                data.iter().map(|&b| b as usize).collect()
+            }
+        } else {
+            (0..count).map(|_| number()).collect::<Result<_>>()?
+        };
+
+        Ok(Netpbm {
+            width,
+            height,
+            channels,
+            max_value,
+            samples: samples.into_iter().map(|s| s.min(max_value)).collect(),
+        })
+    }
+
+    /// Red, green and blue samples of each pixel, row by row; gray is
+    /// repeated.
+    pub fn pixels(&self) -> impl Iterator<Item = [usize; 3]> + '_ {
+        let channels = self.channels;
+        self.samples
+            .chunks(channels)
+            .map(move |c| [c[0], c[channels / 2], c[channels - 1]])
+    }
+}
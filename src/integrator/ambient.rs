@@ -0,0 +1,109 @@
+use crate::geometry::Hitable;
+use crate::integrator::{Integrator, Radiance};
+use crate::renderer::RenderOption;
+use crate::{Color, Ray, Scene, Vector3d};
+
+/// Ambient occlusion: the share of rays leaving the first hit, by the cosine
+/// to its shading normal, that travel `radius` without meeting anything,
+/// shown as gray. Rays that hit nothing are white.
+pub struct AmbientOcclusion {
+    /// Distance beyond which surfaces no longer occlude, in world units.
+    pub radius: f64,
+    /// Rays cast from each hit.
+    pub samples: usize,
+}
+
+impl Default for AmbientOcclusion {
+    fn default() -> Self {
+        AmbientOcclusion {
+            radius: 1.0,
+            samples: 16,
+        }
+    }
+}
+
+impl Integrator for AmbientOcclusion {
+    fn radiance(&self, ray: &Ray, scene: &Scene, _option: &RenderOption) -> Radiance {
+        let Some(rec) = scene.objects.hit(ray) else {
+            return Radiance {
+                direct: Color::white(),
+                ..Radiance::black()
+            };
+        };
+        let open = (0..self.samples)
+            .filter(|_| {
+                let probe = Ray {
+                    origin: rec.point,
+                    direction: Vector3d::random_cosine_direction(rec.shading_normal),
+                    time: ray.time,
+                    spread: 0.0,
+                };
+                scene
+                    .objects
+                    .hit(&probe)
+                    .is_none_or(|hit| hit.t > self.radius)
+            })
+            .count();
+        let open = open as f64 / self.samples.max(1) as f64;
+        Radiance {
+            direct: Color::new([open; 3]),
+            ..Radiance::black()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::film::PixelFilter;
+    use crate::geometry::{HitableList, Sphere};
+    use crate::material::Material;
+    use crate::texture::solid;
+    use crate::Point3d;
+
+    #[test]
+    fn test_occlusion() {
+        let sphere = |y: f64, radius: f64| Sphere {
+            center: Point3d::new([0.0, y, -1.0]),
+            radius,
+            material: Material::Lambertian(solid(Color::white())),
+        };
+        let scene = Scene {
+            objects: HitableList {
+                hitables: vec![Box::new(sphere(-100.5, 100.0)), Box::new(sphere(0.0, 0.5))],
+            },
+            camera: Camera::new(1.0, 2.0, 1.0),
+            fog: None,
+        };
+        let option = RenderOption {
+            samples_per_pixel: 1,
+            max_depth: 1,
+            roulette_depth: usize::MAX,
+            indirect_clamp: None,
+            filter: PixelFilter::default(),
+            denoiser: None,
+        };
+        let occlusion = |origin: [f64; 3], direction: [f64; 3], radius: f64| {
+            let ray = Ray {
+                origin: Point3d::new(origin),
+                direction: Vector3d::new(direction),
+                time: 0.0,
+                spread: 0.0,
+            };
+            let integrator = AmbientOcclusion {
+                radius,
+                samples: 4000,
+            };
+            integrator.radiance(&ray, &scene, &option).total().x()
+        };
+        // The top of the ball sees nothing but sky.
+        assert_eq!(occlusion([0.0, 2.0, -1.0], [0.0, -1.0, 0.0], 1.0), 1.0);
+        // Ground beside the ball is partly covered by it, unless the radius
+        // is too short to reach it.
+        let beside = [0.0, -0.5, -0.45];
+        assert!(occlusion([0.0; 3], beside, 1.0) < 0.9);
+        assert_eq!(occlusion([0.0; 3], beside, 0.01), 1.0);
+        assert_eq!(occlusion([0.0; 3], [0.0, 1.0, 0.0], 1.0), 1.0);
+    }
+}
@@ -0,0 +1,545 @@
+use crate::geometry::HitRecord;
+use crate::integrator::{sample_light, Integrator, Radiance, Splat};
+use crate::material::{Material, Subsurface};
+use crate::renderer::RenderOption;
+use crate::texture::solid;
+use crate::{Color, Point3d, Ray, Scene, Vector3d};
+use std::f64::consts::PI;
+
+/// Bidirectional path tracer (Veach, 1997). Each sample traces a subpath
+/// from the camera and one from a light, then joins every vertex of one to
+/// every vertex of the other, weighting each way of forming a path by the
+/// balance heuristic. Joining light vertices straight to the camera, light
+/// tracing, splats onto wherever they land on the film, which is what finds
+/// caustics and light squeezing through small openings.
+///
+/// Only lights that can be sampled start subpaths. Other emitters, and the
+/// sky, are found by camera subpaths alone, as in path tracing. Subpaths run
+/// to `max_depth` without Russian roulette. The scene's fog scatters both
+/// subpaths, like a medium, and dims every join through it.
+#[derive(Default)]
+pub struct BidirectionalPathTracer {
+    /// The scene's lights, found by `prepare`.
+    lights: Vec<usize>,
+    /// The scene's fog as a medium for its scattering vertices, made by
+    /// `prepare`.
+    fog: Option<Material>,
+}
+
+#[derive(Clone, Copy)]
+enum Kind<'a> {
+    Camera,
+    /// The start of a light subpath, on a light.
+    Light(HitRecord<'a>),
+    Surface(HitRecord<'a>),
+}
+
+#[derive(Clone, Copy)]
+struct Vertex<'a> {
+    kind: Kind<'a>,
+    point: Point3d,
+    /// Throughput of the subpath up to the vertex, over its density.
+    beta: Color,
+    /// Scattered without a density to evaluate, so nothing can join it.
+    delta: bool,
+    /// A step of a subsurface walk inside an object, which costs no bounce.
+    walk: bool,
+    /// Area density of the vertex as sampled by its own subpath.
+    pdf_fwd: f64,
+    /// Area density of the vertex had the other subpath sampled it.
+    pdf_rev: f64,
+    /// Area density of picking the vertex by sampling the lights, zero off
+    /// the lights.
+    light_pdf: f64,
+}
+
+/// Densities on surfaces are per area along `w` from the vertex before.
+fn cos_at(vertex: &Vertex, w: &Vector3d) -> f64 {
+    match vertex.kind {
+        Kind::Camera => 1.0,
+        Kind::Surface(rec) if matches!(rec.material, Material::Medium { .. }) => 1.0,
+        Kind::Light(rec) | Kind::Surface(rec) => rec.normal.dot(w).abs(),
+    }
+}
+
+/// Turns a density over directions leaving `from` into one over area at `to`.
+fn to_area(pdf: f64, from: &Vertex, to: &Vertex) -> f64 {
+    if pdf == 0.0 {
+        return 0.0;
+    }
+    let w = to.point - from.point;
+    let distance2 = w.length_squared();
+    pdf * cos_at(to, &(w / distance2.sqrt())) / distance2
+}
+
+impl<'a> Vertex<'a> {
+    fn new(kind: Kind<'a>, point: Point3d, beta: Color) -> Self {
+        Vertex {
+            kind,
+            point,
+            beta,
+            delta: false,
+            walk: false,
+            pdf_fwd: 0.0,
+            pdf_rev: 0.0,
+            light_pdf: 0.0,
+        }
+    }
+
+    /// Area density at `next` of the vertex sampling it, having been
+    /// reached from `prev`.
+    fn pdf(&self, scene: &Scene, prev: Option<&Vertex>, next: &Vertex) -> f64 {
+        let w = (next.point - self.point).unit_vector();
+        let pdf = match (self.kind, prev) {
+            (Kind::Camera, _) => scene
+                .camera
+                .project(next.point)
+                .map_or(0.0, |(_, _, cos)| scene.camera.direction_pdf(cos)),
+            (Kind::Light(rec), _) => rec.normal.dot(&w).max(0.0) / PI,
+            (Kind::Surface(rec), Some(prev)) => {
+                let wo = (prev.point - self.point).unit_vector();
+                rec.material.pdf(&rec, &wo, &w)
+            }
+            (Kind::Surface(_), None) => 0.0,
+        };
+        to_area(pdf, self, next)
+    }
+
+    /// BSDF times the cosine toward `next` for light passing between `prev`
+    /// and `next`, which for lights is whether they face `next`.
+    fn eval(&self, prev: Option<&Vertex>, next: &Vertex) -> Color {
+        let w = (next.point - self.point).unit_vector();
+        match (self.kind, prev) {
+            (Kind::Light(rec), _) if rec.normal.dot(&w) > 0.0 => Color::white(),
+            (Kind::Surface(rec), Some(prev)) => {
+                let wo = (prev.point - self.point).unit_vector();
+                rec.material.eval(&rec, &wo, &w)
+            }
+            _ => Color::black(),
+        }
+    }
+}
+
+/// Share of light passing from `from` to the vertex at `to` through media
+/// and fog; zero when a surface is in the way. Marched surfaces are hit only
+/// to within a small distance, so a hit that close to `to` counts as
+/// reaching it.
+fn transmittance(scene: &Scene, from: Point3d, to: Point3d, time: f64) -> f64 {
+    let ray = Ray {
+        origin: from,
+        direction: to - from,
+        time,
+        spread: 0.0,
+    };
+    let distance = ray.direction.length();
+    scene.transmittance(&ray, 1.0 - 1e-3 / distance)
+}
+
+/// Balance heuristic weight of joining the first `s` light and `t` camera
+/// vertices, against every other way of sampling the same path. `sampled`
+/// stands in for the light vertex when `s` is 1.
+fn mis_weight(
+    scene: &Scene,
+    camera: &[Vertex],
+    light: &[Vertex],
+    sampled: Option<Vertex>,
+    s: usize,
+    t: usize,
+) -> f64 {
+    if s + t == 2 {
+        return 1.0;
+    }
+    let mut camera = camera[..t].to_vec();
+    let mut light = light[..s].to_vec();
+    if let Some(vertex) = sampled {
+        light[0] = vertex;
+    }
+    let pt = camera[t - 1];
+    // Emitters that cannot be sampled are only ever hit.
+    if s == 0 && pt.light_pdf == 0.0 {
+        return 1.0;
+    }
+    // Densities of the joined vertices sampled from the other side.
+    camera[t - 1].delta = false;
+    camera[t - 1].pdf_rev = match s {
+        0 => pt.light_pdf,
+        _ => light[s - 1].pdf(scene, s.checked_sub(2).map(|i| &light[i]), &pt),
+    };
+    if t > 1 {
+        camera[t - 2].pdf_rev = match (s, pt.kind) {
+            (0, Kind::Surface(rec)) => Vertex {
+                kind: Kind::Light(rec),
+                ..pt
+            }
+            .pdf(scene, None, &camera[t - 2]),
+            (0, _) => 0.0,
+            _ => pt.pdf(scene, Some(&light[s - 1]), &camera[t - 2]),
+        };
+    }
+    if s > 0 {
+        let qs = light[s - 1];
+        light[s - 1].delta = false;
+        light[s - 1].pdf_rev = pt.pdf(scene, t.checked_sub(2).map(|i| &camera[i]), &qs);
+        if s > 1 {
+            light[s - 2].pdf_rev = qs.pdf(scene, Some(&pt), &light[s - 2]);
+        }
+    }
+
+    // Sum the densities of the other strategies relative to this one.
+    let remap = |pdf: f64| if pdf == 0.0 { 1.0 } else { pdf };
+    let mut sum = 0.0;
+    let mut ratio = 1.0;
+    for i in (1..t).rev() {
+        ratio *= remap(camera[i].pdf_rev) / remap(camera[i].pdf_fwd);
+        if !camera[i].delta && !camera[i - 1].delta {
+            sum += ratio;
+        }
+    }
+    ratio = 1.0;
+    for i in (0..s).rev() {
+        ratio *= remap(light[i].pdf_rev) / remap(light[i].pdf_fwd);
+        if !light[i].delta && (i == 0 || !light[i - 1].delta) {
+            sum += ratio;
+        }
+    }
+    1.0 / (1.0 + sum)
+}
+
+impl BidirectionalPathTracer {
+    /// Extends `path` from its last vertex along `ray` until it holds
+    /// `max_vertices` besides subsurface walk steps, the ray leaves the scene or
+    /// scattering stops, starting
+    /// with throughput `beta` and `pdf` the density over directions of `ray`.
+    /// Where the scene's fog scatters the ray, the vertex takes the fog's
+    /// medium as its material. Returns the throughput and ray of a path that
+    /// left the scene.
+    fn random_walk<'a>(
+        &'a self,
+        scene: &'a Scene,
+        mut ray: Ray,
+        mut beta: Color,
+        mut pdf: f64,
+        max_vertices: usize,
+        path: &mut Vec<Vertex<'a>>,
+    ) -> Option<(Color, Ray)> {
+        let mut vertices = path.iter().filter(|vertex| !vertex.walk).count();
+        let mut steps = 0;
+        while vertices < max_vertices {
+            let hit = scene.objects.hit_object(&ray);
+            let fog_t = scene.fog.as_ref().and_then(|fog| {
+                fog.sample_distance(&ray, hit.map_or(f64::INFINITY, |(_, rec)| rec.t))
+            });
+            let (object, rec) = match (fog_t, &self.fog, hit) {
+                (Some(t), Some(material), _) => {
+                    let normal = -ray.direction.unit_vector();
+                    (None, HitRecord::new(&ray, ray.at(t), normal, t, (0.0, 0.0), material))
+                }
+                (_, _, Some((object, rec))) => (Some(object), rec),
+                _ => return Some((beta, ray)),
+            };
+            let prev = path.len() - 1;
+            let mut vertex = Vertex::new(Kind::Surface(rec), rec.point, beta);
+            vertex.pdf_fwd = to_area(pdf, &path[prev], &vertex);
+            if let Some(object) = object.map(|i| &scene.objects.hitables[i]) {
+                if object.is_light() {
+                    vertex.light_pdf = 1.0 / (self.lights.len() as f64 * object.area());
+                }
+            }
+            if rec.material.is_walk_step(&rec) {
+                vertex.walk = true;
+                steps += 1;
+                if steps > Subsurface::MAX_STEPS {
+                    break;
+                }
+            } else {
+                vertices += 1;
+                steps = 0;
+            }
+            path.push(vertex);
+            if vertices == max_vertices {
+                break;
+            }
+            let Some((weight, scattered)) = rec.material.scatter(&ray, &rec) else {
+                break;
+            };
+            let wo = -ray.direction.unit_vector();
+            let wi = scattered.direction.unit_vector();
+            let (mut pdf_fwd, mut pdf_rev) = (
+                rec.material.pdf(&rec, &wo, &wi),
+                rec.material.pdf(&rec, &wi, &wo),
+            );
+            if !(pdf_fwd > 0.0 && pdf_fwd.is_finite()) {
+                path[prev + 1].delta = true;
+                (pdf_fwd, pdf_rev) = (0.0, 0.0);
+            }
+            path[prev].pdf_rev = to_area(pdf_rev, &path[prev + 1], &path[prev]);
+            beta = beta * weight;
+            if beta.data.iter().all(|&c| c == 0.0) {
+                break;
+            }
+            ray = scattered;
+            pdf = pdf_fwd;
+        }
+        None
+    }
+
+    /// Light carried by the path joining the first `s` light and `t`
+    /// camera vertices, weighted against the other strategies, with where it
+    /// lands on the viewport when it joins the camera directly.
+    fn connect(
+        &self,
+        scene: &Scene,
+        lights: &[usize],
+        camera: &[Vertex],
+        light: &[Vertex],
+        (s, t): (usize, usize),
+        time: f64,
+    ) -> (Color, Option<(f64, f64)>) {
+        let mut sampled = None;
+        let mut splat = None;
+        let contribution = if s == 0 {
+            match camera[t - 1].kind {
+                Kind::Surface(rec) => camera[t - 1].beta * rec.material.emitted(&rec),
+                _ => Color::black(),
+            }
+        } else if t == 1 {
+            let (qs, pt) = (&light[s - 1], &camera[0]);
+            match scene.camera.project(qs.point) {
+                Some((u, v, cos)) if !qs.delta => {
+                    splat = Some((u, v));
+                    let importance = scene.camera.direction_pdf(cos) / cos;
+                    let distance2 = (pt.point - qs.point).length_squared();
+                    let visible = transmittance(scene, pt.point, qs.point, time);
+                    qs.beta
+                        * qs.eval(Some(&light[s - 2]), pt)
+                        * (visible * importance * cos / distance2)
+                }
+                _ => Color::black(),
+            }
+        } else if s == 1 {
+            let pt = &camera[t - 1];
+            match sample_light(scene, lights, time) {
+                Some(sample) if !pt.delta => {
+                    let rec = sample.rec;
+                    let mut vertex = Vertex::new(
+                        Kind::Light(rec),
+                        rec.point,
+                        rec.material.emitted(&rec) / sample.pdf,
+                    );
+                    vertex.pdf_fwd = sample.pdf;
+                    sampled = Some(vertex);
+                    let visible = transmittance(scene, pt.point, vertex.point, time);
+                    if visible > 0.0 {
+                        let w = pt.point - vertex.point;
+                        vertex.beta
+                            * vertex.eval(None, pt)
+                            * pt.beta
+                            * pt.eval(Some(&camera[t - 2]), &vertex)
+                            * (visible * cos_at(&vertex, &w.unit_vector()) / w.length_squared())
+                    } else {
+                        Color::black()
+                    }
+                }
+                _ => Color::black(),
+            }
+        } else {
+            let (qs, pt) = (&light[s - 1], &camera[t - 1]);
+            if qs.delta || pt.delta {
+                Color::black()
+            } else {
+                qs.beta
+                    * qs.eval(Some(&light[s - 2]), pt)
+                    * pt.beta
+                    * pt.eval(Some(&camera[t - 2]), qs)
+                    * (transmittance(scene, pt.point, qs.point, time)
+                        / (pt.point - qs.point).length_squared())
+            }
+        };
+        if contribution.data.iter().all(|&c| c == 0.0) {
+            return (contribution, None);
+        }
+        let weight = mis_weight(scene, camera, light, sampled, s, t);
+        (contribution * weight, splat)
+    }
+}
+
+impl Integrator for BidirectionalPathTracer {
+    fn prepare(&mut self, scene: &Scene, _option: &RenderOption) {
+        self.lights = scene.lights();
+        self.fog = scene.fog.map(|fog| Material::Medium {
+            albedo: solid(fog.albedo),
+            phase: fog.phase,
+        });
+    }
+
+    /// Light found from the camera's side alone, without the light tracing
+    /// splats; `radiance_with_splats` gives the rest.
+    fn radiance(&self, ray: &Ray, scene: &Scene, option: &RenderOption) -> Radiance {
+        self.radiance_with_splats(ray, scene, option).0
+    }
+
+    fn radiance_with_splats(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        option: &RenderOption,
+    ) -> (Radiance, Vec<Splat>) {
+        let mut radiance = Radiance::black();
+        let mut splats = Vec::new();
+        let lights = &self.lights;
+
+        let mut camera = vec![Vertex::new(Kind::Camera, ray.origin, Color::white())];
+        let pdf = scene
+            .camera
+            .project(ray.at(1.0))
+            .map_or(0.0, |(_, _, cos)| scene.camera.direction_pdf(cos));
+        let escaped = self.random_walk(
+            scene,
+            *ray,
+            Color::white(),
+            pdf,
+            option.max_depth + 1,
+            &mut camera,
+        );
+        // No other strategy reaches the sky.
+        let walks = |path: &[Vertex], n: usize| path[..n].iter().filter(|v| v.walk).count();
+        if let Some((beta, ray)) = escaped {
+            let bounces = camera.len() - 1 - walks(&camera, camera.len());
+            radiance.add(bounces, beta * scene.background(&ray));
+        }
+
+        let mut light = Vec::new();
+        if let Some(sample) = sample_light(scene, lights, ray.time) {
+            let rec = sample.rec;
+            let emitted = rec.material.emitted(&rec);
+            let mut vertex = Vertex::new(Kind::Light(rec), rec.point, emitted);
+            vertex.pdf_fwd = sample.pdf;
+            light.push(vertex);
+            // Diffuse emission, sampled by the cosine so it cancels.
+            let direction = Vector3d::random_cosine_direction(rec.normal);
+            let ray = Ray {
+                origin: rec.point,
+                direction,
+                time: ray.time,
+                spread: 0.0,
+            };
+            let pdf = direction.dot(&rec.normal) / PI;
+            if pdf > 0.0 {
+                let beta = emitted * (PI / sample.pdf);
+                self.random_walk(
+                    scene,
+                    ray,
+                    beta,
+                    pdf,
+                    option.max_depth,
+                    &mut light,
+                );
+            }
+        }
+
+        for t in 1..=camera.len() {
+            for s in 0..=light.len() {
+                let walked = walks(&light, s) + walks(&camera, t);
+                let bounces = (s + t - walked) as isize - 2;
+                if (s == 1 && t == 1) || bounces < 0 || bounces >= option.max_depth as isize {
+                    continue;
+                }
+                let strategy = (s, t);
+                let (color, splat) =
+                    self.connect(scene, lights, &camera, &light, strategy, ray.time);
+                match splat {
+                    Some(uv) => splats.push((uv, color)),
+                    None => radiance.add(bounces as usize, color),
+                }
+            }
+        }
+        (radiance, splats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::film::PixelFilter;
+    use crate::fog::Fog;
+    use crate::geometry::{HitableList, Sphere};
+    use crate::integrator::PathTracer;
+    use crate::material::PhaseFunction;
+    use crate::math::random;
+    use crate::texture::solid;
+
+    fn scene(fog: Option<Fog>) -> Scene {
+        let sphere = |center: [f64; 3], radius: f64, material: Material| Sphere {
+            center: Point3d::new(center),
+            radius,
+            material,
+        };
+        let diffuse = |albedo: f64| Material::Lambertian(solid(Color::new([albedo; 3])));
+        Scene {
+            objects: HitableList {
+                hitables: vec![
+                    Box::new(sphere([0.0, -100.5, -1.0], 100.0, diffuse(0.5))),
+                    Box::new(sphere([0.0, 0.0, -1.5], 0.5, diffuse(0.8))),
+                    Box::new(sphere(
+                        [0.8, 0.6, -1.0],
+                        0.2,
+                        Material::DiffuseLight(solid(Color::new([8.0; 3]))),
+                    )),
+                ],
+            },
+            camera: Camera::new(1.0, 2.0, 1.0),
+            fog,
+        }
+    }
+
+    /// Mean of both integrators over the viewport, taking in the splats
+    /// that land on it.
+    fn compare(scene: &Scene, n: usize) -> (f64, f64) {
+        let option = RenderOption {
+            samples_per_pixel: 1,
+            max_depth: 4,
+            roulette_depth: usize::MAX,
+            indirect_clamp: None,
+            filter: PixelFilter::default(),
+            denoiser: None,
+        };
+        let mut integrator = BidirectionalPathTracer::default();
+        integrator.prepare(scene, &option);
+        let mut bdpt = 0.0;
+        let mut path = 0.0;
+        for _ in 0..n {
+            let ray = scene.camera.get_ray(random(), random());
+            let (radiance, splats) = integrator.radiance_with_splats(&ray, scene, &option);
+            bdpt += radiance.total().x();
+            for ((u, v), color) in splats {
+                if (0.0..1.0).contains(&u) && (0.0..1.0).contains(&v) {
+                    bdpt += color.x();
+                }
+            }
+            path += PathTracer.radiance(&ray, scene, &option).total().x();
+        }
+        (bdpt / n as f64, path / n as f64)
+    }
+
+    #[test]
+    fn test_matches_path_tracing() {
+        let (bdpt, path) = compare(&scene(None), 20000);
+        assert!((bdpt - path).abs() < 0.03 * path, "{} vs {}", bdpt, path);
+    }
+
+    #[test]
+    fn test_matches_path_tracing_in_fog() {
+        // Fog up to the light, thick enough to halve the light crossing it.
+        let fog = Fog {
+            density: 0.7,
+            albedo: Color::new([0.8; 3]),
+            phase: PhaseFunction::Isotropic,
+            height: 0.6,
+        };
+        let (clear, _) = compare(&scene(None), 20000);
+        let (bdpt, path) = compare(&scene(Some(fog)), 80000);
+        assert!(bdpt < 0.8 * clear, "{} vs {}", bdpt, clear);
+        assert!((bdpt - path).abs() < 0.04 * path, "{} vs {}", bdpt, path);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::geometry::{take_tests, Counting, Hitable};
+use crate::integrator::{Integrator, PathTracer, Radiance};
+use crate::renderer::RenderOption;
+use crate::{Color, Ray, Scene};
+
+/// Quantity a `DebugIntegrator` shows in place of the lighting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugView {
+    /// Shading normal, mapped from `[-1, 1]` to `[0, 1]` per axis.
+    Normal,
+    /// Surface coordinates as red and green.
+    Uv,
+    /// Distance to the first hit.
+    Depth,
+    /// Times a path traced as usual scattered.
+    Bounces,
+    /// Intersection tests made finding the first hit.
+    TraversalCost,
+}
+
+/// Renders a property of the scene instead of its lighting, as false color
+/// where it is a single number, for finding faults in geometry and in the
+/// acceleration of ray queries. Rays that hit nothing are black, except
+/// under `Bounces` and `TraversalCost`, which still count the sky.
+pub struct DebugIntegrator {
+    pub view: DebugView,
+    /// Value shown at the hot end of the false color ramp.
+    pub scale: f64,
+    /// Held from `prepare` to `finish` when showing traversal cost.
+    counting: Option<Counting>,
+}
+
+/// Blue through cyan, green and yellow to red as `x` goes from 0 to 1.
+fn false_color(x: f64) -> Color {
+    let stops = [
+        [0.0, 0.0, 1.0],
+        [0.0, 1.0, 1.0],
+        [0.0, 1.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [1.0, 0.0, 0.0],
+    ];
+    let x = x.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+    let i = (x as usize).min(stops.len() - 2);
+    let f = x - i as f64;
+    Color::new(stops[i]) * (1.0 - f) + Color::new(stops[i + 1]) * f
+}
+
+impl DebugView {
+    /// The view called `name` on the command line.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "normal" => Some(DebugView::Normal),
+            "uv" => Some(DebugView::Uv),
+            "depth" => Some(DebugView::Depth),
+            "bounces" => Some(DebugView::Bounces),
+            "cost" => Some(DebugView::TraversalCost),
+            _ => None,
+        }
+    }
+}
+
+impl DebugIntegrator {
+    /// Shows `view` with a scale suiting the scenes at hand.
+    pub fn new(view: DebugView) -> Self {
+        let scale = match view {
+            DebugView::Depth => 10.0,
+            DebugView::Bounces => 10.0,
+            DebugView::TraversalCost => 50.0,
+            DebugView::Normal | DebugView::Uv => 1.0,
+        };
+        DebugIntegrator {
+            view,
+            scale,
+            counting: None,
+        }
+    }
+
+    fn color(&self, ray: &Ray, scene: &Scene, option: &RenderOption) -> Color {
+        match self.view {
+            DebugView::Bounces => {
+                let (_, bounces) = PathTracer.trace(ray, scene, option);
+                false_color(bounces as f64 / self.scale)
+            }
+            DebugView::TraversalCost => {
+                take_tests();
+                scene.objects.hit(ray);
+                false_color(take_tests() as f64 / self.scale)
+            }
+            view => {
+                let Some(rec) = scene.objects.hit(ray) else {
+                    return Color::black();
+                };
+                match view {
+                    DebugView::Normal => (rec.shading_normal + Color::white()) * 0.5,
+                    DebugView::Uv => Color::new([rec.u, rec.v, 0.0]),
+                    _ => false_color(rec.t * ray.direction.length() / self.scale),
+                }
+            }
+        }
+    }
+}
+
+impl Integrator for DebugIntegrator {
+    /// Starts counting intersection tests when showing their cost, until
+    /// `finish`; other renders leave them uncounted.
+    fn prepare(&mut self, _scene: &Scene, _option: &RenderOption) {
+        if self.view == DebugView::TraversalCost {
+            self.counting = Some(Counting::start());
+        }
+    }
+
+    fn finish(&mut self) {
+        self.counting = None;
+    }
+
+    fn shows_data(&self) -> bool {
+        true
+    }
+
+    fn radiance(&self, ray: &Ray, scene: &Scene, option: &RenderOption) -> Radiance {
+        Radiance {
+            emitted: self.color(ray, scene, option),
+            ..Radiance::black()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::film::{FilterKind, PixelFilter};
+    use crate::geometry::{HitableList, Sphere};
+    use crate::image::Image;
+    use crate::material::Material;
+    use crate::renderer::Renderer;
+    use crate::texture::solid;
+    use crate::{Point3d, Vector3d};
+
+    fn render(view: DebugView, scale: f64) -> Color {
+        // Three spheres side by side, the first straight ahead.
+        let hitables = (0..3)
+            .map(|i| {
+                Box::new(Sphere {
+                    center: Point3d::new([2.0 * i as f64, 0.0, -2.0]),
+                    radius: 0.5,
+                    material: Material::Lambertian(solid(Color::white())),
+                }) as Box<dyn Hitable>
+            })
+            .collect();
+        let scene = Scene {
+            objects: HitableList { hitables },
+            camera: Camera::new(1.0, 2.0, 1.0),
+            fog: None,
+        };
+        let option = RenderOption {
+            samples_per_pixel: 1,
+            max_depth: 10,
+            roulette_depth: usize::MAX,
+            indirect_clamp: None,
+            filter: PixelFilter::default(),
+            denoiser: None,
+        };
+        let ray = Ray {
+            origin: Point3d::new([0.0, 0.0, 0.0]),
+            direction: Vector3d::new([0.0, 0.0, -1.0]),
+            time: 0.0,
+            spread: 0.0,
+        };
+        let mut integrator = DebugIntegrator {
+            scale,
+            ..DebugIntegrator::new(view)
+        };
+        integrator.prepare(&scene, &option);
+        let color = integrator.radiance(&ray, &scene, &option).total();
+        integrator.finish();
+        assert!(integrator.counting.is_none());
+        color
+    }
+
+    #[test]
+    fn test_views() {
+        let close = |a: Color, b: Color| (a - b).length() < 1e-9;
+        assert!(close(
+            render(DebugView::Normal, 1.0),
+            Color::new([0.5, 0.5, 1.0])
+        ));
+        assert!(close(render(DebugView::Depth, 4.0), false_color(1.5 / 4.0)));
+        // Each sphere is tested once as an entry of the list and once as a
+        // primitive.
+        assert!(close(
+            render(DebugView::TraversalCost, 12.0),
+            false_color(0.5)
+        ));
+        assert_eq!(false_color(0.5), Color::new([0.0, 1.0, 0.0]));
+        assert_eq!(false_color(2.0), Color::new([1.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn test_written_linearly() {
+        // A wall facing the camera, whose normal (0, 0, 1) maps to
+        // (0.5, 0.5, 1) and must not be brightened by gamma.
+        let wall = Sphere {
+            center: Point3d::new([0.0, 0.0, -1e4]),
+            radius: 1e4 - 1.0,
+            material: Material::Lambertian(solid(Color::white())),
+        };
+        let scene = Scene {
+            objects: HitableList {
+                hitables: vec![Box::new(wall)],
+            },
+            camera: Camera::new(1.0, 2.0, 1.0),
+            fog: None,
+        };
+        let mut image = Image::new("debug_test.ppm", 4, 4);
+        let mut renderer = Renderer {
+            scene: &scene,
+            image: &mut image,
+            option: RenderOption {
+                samples_per_pixel: 2,
+                max_depth: 10,
+                roulette_depth: usize::MAX,
+                indirect_clamp: None,
+                filter: PixelFilter::new(FilterKind::Gaussian),
+                denoiser: None,
+            },
+            integrator: Box::new(DebugIntegrator::new(DebugView::Normal)),
+            aovs: None,
+        };
+        renderer.render();
+        // Gamma would have made the halves 181.
+        for pixel in image.canvas.iter().flatten() {
+            assert!(pixel.data[..2].iter().all(|c| (127..=128).contains(c)), "{:?}", pixel);
+            assert_eq!(pixel.data[2], 255);
+        }
+    }
+}
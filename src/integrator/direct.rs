@@ -0,0 +1,193 @@
+use crate::geometry::Hitable;
+use crate::integrator::{light_estimate, sky_estimate, Integrator, Radiance};
+use crate::renderer::RenderOption;
+use crate::{Ray, Scene};
+
+/// Light reaching the first hit straight from the lights and the sky, found
+/// by sampling them alone: a single bounce, without light reflected off
+/// other surfaces or fog, though fog still dims what it covers. Materials
+/// without a density to evaluate, such as subsurface scattering, show only
+/// their emission.
+#[derive(Default)]
+pub struct DirectLighting {
+    /// The scene's lights, found by `prepare`.
+    lights: Vec<usize>,
+}
+
+impl Integrator for DirectLighting {
+    fn prepare(&mut self, scene: &Scene, _option: &RenderOption) {
+        self.lights = scene.lights();
+    }
+
+    fn radiance(&self, ray: &Ray, scene: &Scene, _option: &RenderOption) -> Radiance {
+        let hit = scene.objects.hit(ray);
+        let fog = scene.fog.map_or(1.0, |fog| {
+            fog.transmittance(ray, hit.map_or(f64::INFINITY, |rec| rec.t))
+        });
+        let Some(rec) = hit else {
+            return Radiance {
+                emitted: scene.background(ray) * fog,
+                ..Radiance::black()
+            };
+        };
+        let wo = -ray.direction.unit_vector();
+        Radiance {
+            emitted: rec.material.emitted(&rec) * fog,
+            direct: (light_estimate(scene, &self.lights, &rec, &wo, ray.time)
+                + sky_estimate(scene, &rec, &wo, ray.time))
+                * fog,
+            ..Radiance::black()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::film::PixelFilter;
+    use crate::fog::Fog;
+    use crate::geometry::{ConstantMedium, HitRecord, HitableList, Sphere};
+    use crate::integrator::PathTracer;
+    use crate::material::{Material, PhaseFunction};
+    use crate::math::with_stream;
+    use crate::texture::solid;
+    use crate::{Color, Point3d, Vector3d};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_matches_single_bounce_path_tracing() {
+        let ground = Sphere {
+            center: Point3d::new([0.0, -100.5, -1.0]),
+            radius: 100.0,
+            material: Material::Lambertian(solid(Color::new([0.8, 0.8, 0.8]))),
+        };
+        let light = Sphere {
+            center: Point3d::new([0.0, 0.5, -1.0]),
+            radius: 0.5,
+            material: Material::DiffuseLight(solid(Color::new([4.0, 4.0, 4.0]))),
+        };
+        let scene = Scene {
+            objects: HitableList {
+                hitables: vec![Box::new(ground), Box::new(light)],
+            },
+            camera: Camera::new(1.0, 2.0, 1.0),
+            fog: None,
+        };
+        assert_eq!(scene.lights().len(), 1);
+        // Paths of one bounce see the same light as direct lighting.
+        let option = RenderOption {
+            samples_per_pixel: 1,
+            max_depth: 2,
+            roulette_depth: usize::MAX,
+            indirect_clamp: None,
+            filter: PixelFilter::default(),
+            denoiser: None,
+        };
+        let ray = Ray {
+            origin: Point3d::new([0.0, 0.0, 0.0]),
+            direction: Vector3d::new([0.6, -0.5, -1.0]),
+            time: 0.0,
+            spread: 0.0,
+        };
+        let mean = |integrator: &dyn Integrator| {
+            let n = 40000;
+            let (_, sum) = with_stream(StdRng::seed_from_u64(3), || {
+                (0..n)
+                    .map(|_| integrator.radiance(&ray, &scene, &option).total().x())
+                    .sum::<f64>()
+            });
+            sum / n as f64
+        };
+        let mut direct = DirectLighting::default();
+        direct.prepare(&scene, &option);
+        let (direct, path) = (mean(&direct), mean(&PathTracer));
+        assert!(
+            (direct - path).abs() < 0.02 * path,
+            "{} vs {}",
+            direct,
+            path
+        );
+    }
+
+    #[test]
+    fn test_media_dim_shadow_rays() {
+        // Fog in a ball around the shading point, or the scene's fog up to
+        // the same height, dims the sky seen through it by the transmittance
+        // of that distance instead of hiding it.
+        let fog = Fog {
+            density: 0.5,
+            albedo: Color::white(),
+            phase: PhaseFunction::Isotropic,
+            height: 2.0,
+        };
+        let scene = |density, fog| Scene {
+            objects: HitableList {
+                hitables: vec![Box::new(ConstantMedium::new(
+                    Box::new(Sphere {
+                        center: Point3d::new([0.0, 0.0, 0.0]),
+                        radius: 2.0,
+                        material: Material::Lambertian(solid(Color::white())),
+                    }),
+                    density,
+                    solid(Color::white()),
+                    PhaseFunction::Isotropic,
+                ))],
+            },
+            camera: Camera::new(1.0, 2.0, 1.0),
+            fog,
+        };
+        let up = Vector3d::new([0.0, 1.0, 0.0]);
+        let ray = Ray {
+            origin: Point3d::new([0.0, 1.0, 0.0]),
+            direction: -up,
+            time: 0.0,
+            spread: 0.0,
+        };
+        let material = Material::Lambertian(solid(Color::white()));
+        let rec = HitRecord::new(&ray, Point3d::new([0.0, 0.0, 0.0]), up, 1.0, (0.0, 0.0), &material);
+        // The same directions for both scenes, so only the fog differs.
+        let sky = |scene: &Scene| {
+            let (_, samples) = with_stream(StdRng::seed_from_u64(7), || {
+                (0..100)
+                    .map(|_| sky_estimate(scene, &rec, &up, 0.0).x())
+                    .collect::<Vec<_>>()
+            });
+            samples
+        };
+        let clear = sky(&scene(0.0, None));
+        let ratio = sky(&scene(0.5, None)).iter().sum::<f64>() / clear.iter().sum::<f64>();
+        assert!((ratio - (-1.0_f64).exp()).abs() < 1e-3, "{}", ratio);
+        // The scene's fog is crossed for two units over the cosine of each
+        // direction, which the same stream picks again.
+        let (_, directions) = with_stream(StdRng::seed_from_u64(7), || {
+            (0..100)
+                .map(|_| Vector3d::random_cosine_direction(up))
+                .collect::<Vec<_>>()
+        });
+        let foggy = sky(&scene(0.0, Some(fog)));
+        for ((foggy, clear), direction) in foggy.iter().zip(&clear).zip(&directions) {
+            let expected = clear * (-1.0 / direction.y()).exp();
+            assert!((foggy - expected).abs() < 1e-9, "{} vs {}", foggy, expected);
+        }
+        // The camera sees the sky through it too.
+        let option = RenderOption {
+            samples_per_pixel: 1,
+            max_depth: 1,
+            roulette_depth: usize::MAX,
+            indirect_clamp: None,
+            filter: PixelFilter::default(),
+            denoiser: None,
+        };
+        let camera_ray = Ray {
+            origin: Point3d::new([0.0, 0.0, 0.0]),
+            direction: up,
+            time: 0.0,
+            spread: 0.0,
+        };
+        let seen = |scene: &Scene| DirectLighting::default().radiance(&camera_ray, scene, &option);
+        let ratio = seen(&scene(0.0, Some(fog))).total().x() / seen(&scene(0.0, None)).total().x();
+        assert!((ratio - (-1.0_f64).exp()).abs() < 1e-12, "{}", ratio);
+    }
+}
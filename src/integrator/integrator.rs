@@ -0,0 +1,104 @@
+use crate::renderer::RenderOption;
+use crate::{Color, Ray, Scene};
+
+/// Light reaching the camera along a ray, split by how many times it scattered.
+#[derive(Clone, Copy, Debug)]
+pub struct Radiance {
+    /// Seen straight at a light or the sky.
+    pub emitted: Color,
+    /// Scattered once on the way.
+    pub direct: Color,
+    /// Scattered more than once.
+    pub indirect: Color,
+}
+
+impl Radiance {
+    pub fn black() -> Self {
+        Radiance {
+            emitted: Color::black(),
+            direct: Color::black(),
+            indirect: Color::black(),
+        }
+    }
+
+    /// Adds light that scattered `bounces` times before reaching the camera.
+    pub fn add(&mut self, bounces: usize, light: Color) {
+        let part = match bounces {
+            0 => &mut self.emitted,
+            1 => &mut self.direct,
+            _ => &mut self.indirect,
+        };
+        *part = *part + light;
+    }
+
+    pub fn total(&self) -> Color {
+        self.emitted + self.direct + self.indirect
+    }
+
+    /// Scales the indirect light down so no channel exceeds `limit`,
+    /// keeping its hue.
+    pub fn clamp_indirect(&mut self, limit: f64) {
+        let peak = self.indirect.max_component();
+        if peak > limit {
+            self.indirect = self.indirect * (limit / peak);
+        }
+    }
+}
+
+/// Light landing at a position on the viewport or film, unfiltered.
+pub type Splat = ((f64, f64), Color);
+
+/// A strategy for estimating the light arriving along camera rays.
+pub trait Integrator: Send + Sync {
+    /// Readies anything shared by all samples of `scene`, such as photon
+    /// maps, before rendering starts.
+    fn prepare(&mut self, _scene: &Scene, _option: &RenderOption) {}
+
+    /// Releases what `prepare` set up once rendering is over.
+    fn finish(&mut self) {}
+
+    /// Whether `radiance` gives data such as normals rather than light, to
+    /// be written as is: the plain mean of the samples in each pixel,
+    /// without reconstruction filter, gamma or denoising.
+    fn shows_data(&self) -> bool {
+        false
+    }
+
+    /// One estimate of the light arriving at the camera along `ray`.
+    fn radiance(&self, ray: &Ray, scene: &Scene, option: &RenderOption) -> Radiance;
+
+    /// Like `radiance`, also returning light that the same sample carries to
+    /// the camera through other points of the viewport, at the `(u, v)` of
+    /// `Camera::get_ray`. Splats are weighted as if the viewport were the
+    /// whole film and one sample were taken in all; they reach the image and
+    /// the beauty pass, but not the lighting passes.
+    fn radiance_with_splats(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        option: &RenderOption,
+    ) -> (Radiance, Vec<Splat>) {
+        (self.radiance(ray, scene, option), Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clamp_indirect() {
+        let mut radiance = Radiance {
+            emitted: Color::new([5.0, 5.0, 5.0]),
+            direct: Color::new([3.0, 0.0, 0.0]),
+            indirect: Color::new([8.0, 4.0, 2.0]),
+        };
+        radiance.clamp_indirect(2.0);
+        // Only indirect light is limited, and it keeps its hue.
+        assert_eq!(radiance.indirect, Color::new([2.0, 1.0, 0.5]));
+        assert_eq!(radiance.emitted, Color::new([5.0, 5.0, 5.0]));
+        assert_eq!(radiance.direct, Color::new([3.0, 0.0, 0.0]));
+        radiance.clamp_indirect(10.0);
+        assert_eq!(radiance.indirect, Color::new([2.0, 1.0, 0.5]));
+    }
+}
@@ -0,0 +1,93 @@
+use crate::geometry::HitRecord;
+use crate::math::random;
+use crate::{Color, Ray, Scene, Vector3d};
+use std::f64::consts::PI;
+
+/// A point picked on one of the lights of a scene.
+#[derive(Clone, Copy, Debug)]
+pub struct LightSample<'a> {
+    /// The point as hit from outside the light.
+    pub rec: HitRecord<'a>,
+    /// Density per unit area, including the chance of picking the light.
+    pub pdf: f64,
+}
+
+/// Picks one of `lights`, given as indices from `Scene::lights`, uniformly,
+/// then a point uniformly over its surface at `time`.
+pub fn sample_light<'a>(scene: &'a Scene, lights: &[usize], time: f64) -> Option<LightSample<'a>> {
+    if lights.is_empty() {
+        return None;
+    }
+    let index = ((random::<f64>() * lights.len() as f64) as usize).min(lights.len() - 1);
+    let light = &scene.objects.hitables[lights[index]];
+    Some(LightSample {
+        rec: light.sample_surface(time)?,
+        pdf: 1.0 / (lights.len() as f64 * light.area()),
+    })
+}
+
+/// Light reaching `rec` from a point picked on `lights` and leaving along
+/// the unit vector `wo`, over the density of picking that point; black when
+/// a surface is in the way, dimmed by media and fog.
+pub fn light_estimate(
+    scene: &Scene,
+    lights: &[usize],
+    rec: &HitRecord,
+    wo: &Vector3d,
+    time: f64,
+) -> Color {
+    let Some(sample) = sample_light(scene, lights, time) else {
+        return Color::black();
+    };
+    let to_light = sample.rec.point - rec.point;
+    let ray = Ray {
+        origin: rec.point,
+        direction: to_light,
+        time,
+        spread: 0.0,
+    };
+    // Only the picked point counts, not a nearer side of the same light;
+    // media and fog on the way dim it instead of blocking it.
+    let visible = scene.transmittance(&ray, 1.0 - 1e-4);
+    if visible == 0.0 {
+        return Color::black();
+    }
+    let hit = HitRecord::new(
+        &ray,
+        sample.rec.point,
+        sample.rec.normal,
+        1.0,
+        (sample.rec.u, sample.rec.v),
+        sample.rec.material,
+    );
+    let distance2 = to_light.length_squared();
+    let wi = to_light / distance2.sqrt();
+    let cos_light = sample.rec.normal.dot(&wi).abs();
+    if cos_light < 1e-9 {
+        return Color::black();
+    }
+    let pdf = sample.pdf * distance2 / cos_light;
+    hit.material.emitted(&hit) * rec.material.eval(rec, wo, &wi) * (visible / pdf)
+}
+
+/// Sky light reaching `rec` along a direction picked by the cosine to the
+/// shading normal, and leaving along the unit vector `wo`; black when a
+/// surface is in the way, dimmed by media and fog.
+pub fn sky_estimate(scene: &Scene, rec: &HitRecord, wo: &Vector3d, time: f64) -> Color {
+    let wi = Vector3d::random_cosine_direction(rec.shading_normal);
+    let pdf = wi.dot(&rec.shading_normal) / PI;
+    if pdf < 1e-9 {
+        return Color::black();
+    }
+    let ray = Ray {
+        origin: rec.point,
+        direction: wi,
+        time,
+        spread: 0.0,
+    };
+    let visible = scene.transmittance(&ray, f64::INFINITY);
+    if visible == 0.0 {
+        return Color::black();
+    }
+    scene.background(&ray) * rec.material.eval(rec, wo, &wi) * (visible / pdf)
+}
@@ -0,0 +1,375 @@
+use crate::integrator::{Integrator, PathTracer, Radiance, Splat};
+use crate::math::{random, with_stream};
+use crate::renderer::RenderOption;
+use crate::{Color, Ray, Scene};
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use std::f64::consts::PI;
+
+/// One coordinate of a point in primary sample space.
+#[derive(Clone, Copy, Default)]
+struct PrimarySample {
+    value: f64,
+    /// Iteration the value last changed at.
+    last_modified: u64,
+    /// Value and iteration before the current iteration, restored on
+    /// rejection.
+    backup: f64,
+    modify_backup: u64,
+}
+
+/// Random stream handing out the coordinates of a point in primary sample
+/// space, the random numbers a path is traced with. Each iteration moves the
+/// point by a small step around it or a large step to anywhere, and the
+/// move can be undone. Coordinates are created and updated only as the path
+/// asks for them, so a path replays exactly when traced again.
+struct PrimarySampler {
+    rng: StdRng,
+    /// Standard deviation of small steps.
+    sigma: f64,
+    large_step_probability: f64,
+    samples: Vec<PrimarySample>,
+    iteration: u64,
+    large_step: bool,
+    last_large_step: u64,
+    /// Coordinate handed out next.
+    index: usize,
+}
+
+impl PrimarySampler {
+    /// A point drawn uniformly from `seed`, its coordinates alike for a seed.
+    fn new(seed: u64, sigma: f64, large_step_probability: f64) -> Self {
+        PrimarySampler {
+            rng: StdRng::seed_from_u64(seed),
+            sigma,
+            large_step_probability,
+            samples: Vec::new(),
+            iteration: 0,
+            large_step: true,
+            last_large_step: 0,
+            index: 0,
+        }
+    }
+
+    /// Moves to the next point, to be traced from the first coordinate.
+    fn start_iteration(&mut self) {
+        self.iteration += 1;
+        self.large_step = self.rng.gen::<f64>() < self.large_step_probability;
+        self.index = 0;
+    }
+
+    fn accept(&mut self) {
+        if self.large_step {
+            self.last_large_step = self.iteration;
+        }
+    }
+
+    /// Moves back to the point before the last `start_iteration`.
+    fn reject(&mut self) {
+        for sample in &mut self.samples {
+            if sample.last_modified == self.iteration {
+                sample.value = sample.backup;
+                sample.last_modified = sample.modify_backup;
+            }
+        }
+        self.iteration -= 1;
+    }
+
+    /// The next coordinate, brought up to the current iteration.
+    fn next(&mut self) -> f64 {
+        // New coordinates, and those untouched since the last large step,
+        // take the uniform value that step would have given them.
+        if self.index >= self.samples.len() {
+            let sample = PrimarySample {
+                value: self.rng.gen(),
+                last_modified: self.last_large_step,
+                ..PrimarySample::default()
+            };
+            self.samples.push(sample);
+        }
+        let mut sample = self.samples[self.index];
+        if sample.last_modified < self.last_large_step {
+            sample.value = self.rng.gen();
+            sample.last_modified = self.last_large_step;
+        }
+        sample.backup = sample.value;
+        sample.modify_backup = sample.last_modified;
+        if self.large_step {
+            sample.value = self.rng.gen();
+        } else {
+            // The small steps missed since the last change, taken at once.
+            let steps = (self.iteration - sample.last_modified) as f64;
+            let (u1, u2) = (self.rng.gen::<f64>(), self.rng.gen::<f64>());
+            let normal = (-2.0 * (1.0 - u1).ln()).sqrt() * (2.0 * PI * u2).cos();
+            sample.value += normal * self.sigma * steps.sqrt();
+            sample.value -= sample.value.floor();
+        }
+        sample.last_modified = self.iteration;
+        self.samples[self.index] = sample;
+        self.index += 1;
+        sample.value
+    }
+}
+
+impl RngCore for PrimarySampler {
+    fn next_u32(&mut self) -> u32 {
+        (self.next() * (1u64 << 32) as f64) as u32
+    }
+
+    /// Keeps the 53 bits `random::<f64>` reads, so it returns the coordinate.
+    fn next_u64(&mut self) -> u64 {
+        ((self.next() * (1u64 << 53) as f64) as u64) << 11
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            chunk.copy_from_slice(&self.next_u64().to_le_bytes()[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+fn luminance(color: Color) -> f64 {
+    0.2126 * color.x() + 0.7152 * color.y() + 0.0722 * color.z()
+}
+
+/// Primary sample space Metropolis light transport (Kelemen et al., 2002).
+/// Paths are traced by the path tracer from random numbers that Markov
+/// chains move about, lingering on the numbers giving bright paths, so light
+/// reaching the camera through narrow openings is found again once found.
+///
+/// The image is made of splats alone. Each camera sample runs one chain of
+/// `mutations` paths anywhere on the viewport, starting from a bootstrap
+/// path picked by brightness; the camera ray itself is not traced.
+pub struct MetropolisLightTransport {
+    /// Paths traced with fresh random numbers to find the image brightness
+    /// and start the chains.
+    pub bootstrap: usize,
+    /// Paths each chain moves through.
+    pub mutations: usize,
+    /// Chance of a mutation being a large step, to anywhere.
+    pub large_step_probability: f64,
+    /// Standard deviation of small steps, in primary sample space.
+    pub sigma: f64,
+    /// Average luminance over the viewport, set by `prepare`.
+    brightness: f64,
+    /// Running sum of the luminance of the bootstrap paths, by seed.
+    bootstrap_cdf: Vec<f64>,
+}
+
+impl Default for MetropolisLightTransport {
+    fn default() -> Self {
+        MetropolisLightTransport {
+            bootstrap: 100_000,
+            mutations: 16,
+            large_step_probability: 0.3,
+            sigma: 0.01,
+            brightness: 0.0,
+            bootstrap_cdf: Vec::new(),
+        }
+    }
+}
+
+impl MetropolisLightTransport {
+    /// Traces the path of the sampler's current point, returning where it
+    /// leaves the viewport with the light it carries.
+    fn contribution(
+        &self,
+        sampler: PrimarySampler,
+        scene: &Scene,
+        option: &RenderOption,
+        spread: f64,
+    ) -> (PrimarySampler, Splat) {
+        with_stream(sampler, || {
+            let (u, v) = (random::<f64>(), random::<f64>());
+            let ray = Ray {
+                spread,
+                ..scene.camera.get_ray(u, v)
+            };
+            ((u, v), PathTracer.radiance(&ray, scene, option).total())
+        })
+    }
+
+    fn sampler(&self, seed: u64) -> PrimarySampler {
+        PrimarySampler::new(seed, self.sigma, self.large_step_probability)
+    }
+}
+
+impl Integrator for MetropolisLightTransport {
+    fn prepare(&mut self, scene: &Scene, option: &RenderOption) {
+        let mut total = 0.0;
+        self.bootstrap_cdf = (0..self.bootstrap as u64)
+            .map(|seed| {
+                let (_, (_, color)) = self.contribution(self.sampler(seed), scene, option, 0.0);
+                total += luminance(color);
+                total
+            })
+            .collect();
+        self.brightness = total / self.bootstrap.max(1) as f64;
+    }
+
+    /// Nothing; the light comes in the splats of `radiance_with_splats`, so
+    /// the emission, direct and indirect passes stay black.
+    fn radiance(&self, _ray: &Ray, _scene: &Scene, _option: &RenderOption) -> Radiance {
+        Radiance::black()
+    }
+
+    fn radiance_with_splats(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        option: &RenderOption,
+    ) -> (Radiance, Vec<Splat>) {
+        let total = self.bootstrap_cdf.last().copied().unwrap_or(0.0);
+        if total <= 0.0 {
+            return (Radiance::black(), Vec::new());
+        }
+        // Replay a bootstrap path picked by luminance, then let the chain go
+        // its own way.
+        let pick = random::<f64>() * total;
+        let seed = self.bootstrap_cdf.partition_point(|&sum| sum <= pick);
+        let seed = seed.min(self.bootstrap_cdf.len() - 1) as u64;
+        let (mut sampler, mut current) =
+            self.contribution(self.sampler(seed), scene, option, ray.spread);
+        sampler.rng = StdRng::seed_from_u64(random());
+
+        // Each path stands for `brightness / mutations` of luminance, spread
+        // between the current and proposed path by the acceptance chance.
+        let scale = self.brightness / self.mutations as f64;
+        let mut splats = Vec::new();
+        for _ in 0..self.mutations {
+            sampler.start_iteration();
+            let (next, proposed) = self.contribution(sampler, scene, option, ray.spread);
+            sampler = next;
+            let (current_luminance, proposed_luminance) =
+                (luminance(current.1), luminance(proposed.1));
+            let accept = (proposed_luminance / current_luminance).min(1.0);
+            if accept > 0.0 {
+                splats.push((
+                    proposed.0,
+                    proposed.1 * (scale * accept / proposed_luminance),
+                ));
+            }
+            splats.push((
+                current.0,
+                current.1 * (scale * (1.0 - accept) / current_luminance),
+            ));
+            if sampler.rng.gen::<f64>() < accept {
+                current = proposed;
+                sampler.accept();
+            } else {
+                sampler.reject();
+            }
+        }
+        (Radiance::black(), splats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aov::{Aovs, Pass};
+    use crate::camera::Camera;
+    use crate::film::PixelFilter;
+    use crate::geometry::{HitableList, Sphere};
+    use crate::image::Image;
+    use crate::material::Material;
+    use crate::renderer::Renderer;
+    use crate::texture::solid;
+    use crate::Point3d;
+
+    fn scene() -> Scene {
+        let sphere = |center: [f64; 3], radius: f64, material: Material| Sphere {
+            center: Point3d::new(center),
+            radius,
+            material,
+        };
+        let diffuse = |albedo: f64| Material::Lambertian(solid(Color::new([albedo; 3])));
+        Scene {
+            objects: HitableList {
+                hitables: vec![
+                    Box::new(sphere([0.0, -100.5, -1.0], 100.0, diffuse(0.5))),
+                    Box::new(sphere([0.0, 0.0, -1.5], 0.5, diffuse(0.8))),
+                    Box::new(sphere(
+                        [0.8, 0.6, -1.0],
+                        0.2,
+                        Material::DiffuseLight(solid(Color::new([8.0; 3]))),
+                    )),
+                ],
+            },
+            camera: Camera::new(1.0, 2.0, 1.0),
+            fog: None,
+        }
+    }
+
+    fn option() -> RenderOption {
+        RenderOption {
+            samples_per_pixel: 1,
+            max_depth: 4,
+            roulette_depth: usize::MAX,
+            indirect_clamp: None,
+            filter: PixelFilter::default(),
+            denoiser: None,
+        }
+    }
+
+    #[test]
+    fn test_sampler_replays() {
+        let (scene, option) = (scene(), option());
+        let mlt = MetropolisLightTransport::default();
+        let (_, first) = mlt.contribution(mlt.sampler(7), &scene, &option, 0.0);
+        let (_, again) = mlt.contribution(mlt.sampler(7), &scene, &option, 0.0);
+        assert_eq!(first.0, again.0);
+        assert_eq!(first.1, again.1);
+    }
+
+    #[test]
+    fn test_matches_path_tracing() {
+        let scene = scene();
+        let mut mlt = MetropolisLightTransport {
+            bootstrap: 10000,
+            ..MetropolisLightTransport::default()
+        };
+        mlt.prepare(&scene, &option());
+        let mutations = mlt.mutations;
+        // One chain per sample, 8000 in all.
+        let (width, height) = (8, 4);
+        let mut image = Image::new("metropolis.ppm", width, height);
+        let mut renderer = Renderer {
+            scene: &scene,
+            image: &mut image,
+            option: RenderOption {
+                samples_per_pixel: 250,
+                ..option()
+            },
+            integrator: Box::new(mlt),
+            aovs: Some(Aovs::new(width, height)),
+        };
+        renderer.render();
+        // Light over the left half of the image, where the chains must
+        // spend the right share of their time.
+        let beauty = renderer.aovs.as_ref().unwrap().get(Pass::Beauty);
+        let left = (0..height)
+            .flat_map(|y| (0..width / 2).map(move |x| (x, y)))
+            .map(|(x, y)| beauty.get(x, y).x())
+            .sum::<f64>()
+            / (width / 2 * height) as f64;
+        let option = option();
+        let n = 8000 * mutations;
+        let path = (0..n)
+            .map(|_| {
+                let (u, v) = (0.5 * random::<f64>(), random::<f64>());
+                PathTracer
+                    .radiance(&scene.camera.get_ray(u, v), &scene, &option)
+                    .total()
+                    .x()
+            })
+            .sum::<f64>()
+            / n as f64;
+        assert!((left - path).abs() < 0.05 * path, "{} vs {}", left, path);
+    }
+}
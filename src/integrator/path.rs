@@ -0,0 +1,205 @@
+use crate::geometry::Hitable;
+use crate::integrator::{Integrator, Radiance};
+use crate::material::Subsurface;
+use crate::math::random;
+use crate::renderer::RenderOption;
+use crate::{Color, Ray, Scene};
+
+/// Unidirectional path tracer following material sampling alone.
+pub struct PathTracer;
+
+/// Where a path stands between two bounces.
+struct PathState {
+    ray: Ray,
+    /// Product of the scattering weights so far.
+    throughput: Color,
+    /// Scattering events so far.
+    bounces: usize,
+    /// Steps of the subsurface walk under way, which cost no bounces.
+    steps: usize,
+}
+
+impl PathTracer {
+    /// Radiance along `ray`, with the number of times the path scattered.
+    pub fn trace(&self, ray: &Ray, scene: &Scene, option: &RenderOption) -> (Radiance, usize) {
+        let mut radiance = Radiance::black();
+        let mut state = PathState {
+            ray: *ray,
+            throughput: Color::white(),
+            bounces: 0,
+            steps: 0,
+        };
+        while state.bounces < option.max_depth {
+            let hit = scene.objects.hit(&state.ray);
+            // Fog can scatter the ray before it reaches the surface or the sky.
+            let fog_scatter = scene.fog.as_ref().and_then(|fog| {
+                let t = fog.sample_distance(&state.ray, hit.map_or(f64::INFINITY, |rec| rec.t))?;
+                let scattered = Ray {
+                    origin: state.ray.at(t),
+                    direction: fog.phase.sample(&-state.ray.direction),
+                    ..state.ray
+                };
+                Some((fog.albedo, scattered))
+            });
+            let mut walk_step = false;
+            let next = match (fog_scatter, hit) {
+                (Some(scatter), _) => Some(scatter),
+                (None, Some(rec)) => {
+                    radiance.add(state.bounces, state.throughput * rec.material.emitted(&rec));
+                    walk_step = rec.material.is_walk_step(&rec);
+                    rec.material.scatter(&state.ray, &rec)
+                }
+                (None, None) => {
+                    radiance.add(
+                        state.bounces,
+                        state.throughput * scene.background(&state.ray),
+                    );
+                    None
+                }
+            };
+            let Some((mut weight, scattered)) = next else {
+                break;
+            };
+            if walk_step {
+                if state.steps == Subsurface::MAX_STEPS {
+                    break;
+                }
+                state = PathState {
+                    ray: scattered,
+                    throughput: state.throughput * weight,
+                    steps: state.steps + 1,
+                    ..state
+                };
+                continue;
+            }
+            // Russian roulette ends paths that can add little, and boosts the
+            // survivors to make up for the others.
+            if state.bounces + 1 >= option.roulette_depth {
+                let survival = (state.throughput * weight).max_component().min(0.95);
+                if random::<f64>() >= survival {
+                    break;
+                }
+                weight = weight / survival;
+            }
+            state = PathState {
+                ray: scattered,
+                throughput: state.throughput * weight,
+                bounces: state.bounces + 1,
+                steps: 0,
+            };
+        }
+        (radiance, state.bounces)
+    }
+}
+
+impl Integrator for PathTracer {
+    fn radiance(&self, ray: &Ray, scene: &Scene, option: &RenderOption) -> Radiance {
+        self.trace(ray, scene, option).0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::film::PixelFilter;
+    use crate::geometry::{HitableList, Sphere};
+    use crate::material::{Material, Subsurface};
+    use crate::texture::solid;
+    use crate::{Point3d, Vector3d};
+
+    fn option(roulette_depth: usize) -> RenderOption {
+        RenderOption {
+            samples_per_pixel: 1,
+            max_depth: 50,
+            roulette_depth,
+            indirect_clamp: None,
+            filter: PixelFilter::default(),
+            denoiser: None,
+        }
+    }
+
+    #[test]
+    fn test_roulette_unbiased() {
+        let ground = Sphere {
+            center: Point3d::new([0.0, -100.5, -1.0]),
+            radius: 100.0,
+            material: Material::Lambertian(solid(Color::new([0.8, 0.8, 0.8]))),
+        };
+        let ball = Sphere {
+            center: Point3d::new([0.0, 0.0, -1.0]),
+            radius: 0.5,
+            material: Material::Lambertian(solid(Color::new([0.8, 0.3, 0.3]))),
+        };
+        let scene = Scene {
+            objects: HitableList {
+                hitables: vec![Box::new(ground), Box::new(ball)],
+            },
+            camera: Camera::new(1.0, 2.0, 1.0),
+            fog: None,
+        };
+        let ray = Ray {
+            origin: Point3d::new([0.0, 0.0, 0.0]),
+            direction: Vector3d::new([0.0, -0.3, -1.0]),
+            time: 0.0,
+            spread: 0.0,
+        };
+        let mean = |option: RenderOption| {
+            let n = 20000;
+            (0..n)
+                .map(|_| PathTracer.radiance(&ray, &scene, &option).total().x())
+                .sum::<f64>()
+                / n as f64
+        };
+        let (full, roulette) = (mean(option(usize::MAX)), mean(option(1)));
+        assert!((full - roulette).abs() < 0.02, "{} vs {}", full, roulette);
+        // It pays for itself by ending paths early.
+        let bounces = |option: RenderOption| {
+            (0..2000)
+                .map(|_| PathTracer.trace(&ray, &scene, &option).1)
+                .sum::<usize>()
+        };
+        assert!(bounces(option(1)) * 4 < bounces(option(usize::MAX)) * 3);
+    }
+
+    #[test]
+    fn test_subsurface_walk_costs_one_bounce() {
+        let ball = Sphere {
+            center: Point3d::new([0.0, 0.0, -1.0]),
+            radius: 0.5,
+            material: Material::Subsurface(Subsurface::new(
+                Color::new([0.999; 3]),
+                Color::new([0.1; 3]),
+            )),
+        };
+        let scene = Scene {
+            objects: HitableList {
+                hitables: vec![Box::new(ball)],
+            },
+            camera: Camera::new(1.0, 2.0, 1.0),
+            fog: None,
+        };
+        let ray = Ray {
+            origin: Point3d::new([0.0, 0.0, 0.0]),
+            direction: Vector3d::new([0.0, 0.0, -1.0]),
+            time: 0.0,
+            spread: 0.0,
+        };
+        let mean = |max_depth| {
+            let option = RenderOption {
+                max_depth,
+                ..option(usize::MAX)
+            };
+            let n = 4000;
+            (0..n)
+                .map(|_| PathTracer.radiance(&ray, &scene, &option).total().x())
+                .sum::<f64>()
+                / n as f64
+        };
+        // Going in and coming out of the ball takes one bounce however long
+        // the walk inside, so a second reaches the sky.
+        let (short, long) = (mean(2), mean(50));
+        assert!((short - long).abs() < 0.03 * long, "{} vs {}", short, long);
+        assert!(short > 0.5 * scene.background(&ray).x());
+    }
+}
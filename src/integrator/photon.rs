@@ -0,0 +1,391 @@
+use crate::geometry::{HitRecord, Hitable};
+use crate::integrator::{sample_light, Integrator, Radiance};
+use crate::material::{Material, Subsurface};
+use crate::math::random;
+use crate::photon_map::{Photon, PhotonMap};
+use crate::renderer::RenderOption;
+use crate::{Color, Ray, Scene, Vector3d};
+use std::f64::consts::PI;
+
+/// Roughness below which conductors and dielectrics pass photons on rather
+/// than have them gathered.
+const SPECULAR_ROUGHNESS: f64 = 0.1;
+
+/// Whether light leaving the material is sharp enough to form caustics.
+fn is_specular(material: &Material) -> bool {
+    match material {
+        Material::RoughConductor(conductor) => conductor.roughness < SPECULAR_ROUGHNESS,
+        Material::RoughDielectric(dielectric) => dielectric.roughness < SPECULAR_ROUGHNESS,
+        Material::Bumped { material, .. } => is_specular(material),
+        _ => false,
+    }
+}
+
+/// Whether photons can be stored on and gathered from the material, that
+/// is whether its `eval` sees all the light it scatters. Random walks,
+/// lights and media evaluate to black, and sharp lobes are left to the
+/// camera path.
+fn gathers(material: &Material) -> bool {
+    match material {
+        Material::Lambertian(_) | Material::Principled(_) | Material::Coated(_) => true,
+        Material::RoughConductor(_) | Material::RoughDielectric(_) => !is_specular(material),
+        Material::Bumped { material, .. } => gathers(material),
+        Material::Mix { a, b, .. } => gathers(a) && gathers(b),
+        Material::Subsurface(_) | Material::Medium { .. } | Material::DiffuseLight(_) => false,
+    }
+}
+
+/// How the camera path stands toward the caustics already gathered.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Chain {
+    /// Lights reached next are not in the photon map.
+    Open,
+    /// Caustics were gathered at the last vertex.
+    Gathered,
+    /// Only specular surfaces lie between the last gathering vertex and
+    /// here, so the light of a light reached next was gathered already.
+    Covered,
+}
+
+/// Path tracer taking caustics, light focused by smooth glass and metal
+/// onto other surfaces, from a caustic photon map (Jensen, 1996). Photons
+/// leave the lights, pass through specular surfaces, and are stored where
+/// they land next. Camera paths gather them at every other surface they
+/// hit, and ignore lights they reach themselves through specular surfaces
+/// from such a surface.
+///
+/// The progressive variant (Knaus and Zwicker, 2011) traces a new map for
+/// each iteration, with a radius shrinking each time so the blur of density
+/// estimation vanishes as iterations grow. Each camera sample gathers from
+/// one of the maps at random.
+///
+/// Maps are traced by `prepare`; until then, this is a plain path tracer.
+pub struct PhotonMapper {
+    /// Photons emitted for each map.
+    pub photons: usize,
+    /// Gathering radius of the first map, in world units.
+    pub radius: f64,
+    /// Maps to trace, 1 for plain photon mapping.
+    pub iterations: usize,
+    /// Share of the photons kept from one iteration to the next, in
+    /// `(0, 1)`, setting how fast the radius shrinks.
+    pub alpha: f64,
+    /// Each map with its radius.
+    maps: Vec<(PhotonMap, f64)>,
+}
+
+impl PhotonMapper {
+    pub fn new(photons: usize, radius: f64) -> Self {
+        PhotonMapper::progressive(photons, radius, 1)
+    }
+
+    pub fn progressive(photons: usize, radius: f64, iterations: usize) -> Self {
+        PhotonMapper {
+            photons,
+            radius,
+            iterations,
+            alpha: 0.7,
+            maps: Vec::new(),
+        }
+    }
+
+    /// Gathering radius of each iteration.
+    fn radii(&self) -> Vec<f64> {
+        let mut radius2 = self.radius * self.radius;
+        (1..=self.iterations)
+            .map(|i| {
+                let radius = radius2.sqrt();
+                radius2 *= (i as f64 + self.alpha) / (i as f64 + 1.0);
+                radius
+            })
+            .collect()
+    }
+
+    /// Emits `photons` from the lights of `scene`, keeping those that land
+    /// after passing through at least one specular surface. Photons the fog
+    /// scatters are dropped, as camera paths take that light themselves.
+    fn trace_photons(&self, scene: &Scene, max_depth: usize) -> PhotonMap {
+        let lights = scene.lights();
+        let mut stored = Vec::new();
+        for _ in 0..self.photons {
+            let Some(sample) = sample_light(scene, &lights, 0.0) else {
+                break;
+            };
+            let rec = sample.rec;
+            // Diffuse emission, sampled by the cosine so it cancels.
+            let mut power = rec.material.emitted(&rec) * (PI / sample.pdf / self.photons as f64);
+            let mut ray = Ray {
+                origin: rec.point,
+                direction: Vector3d::random_cosine_direction(rec.normal),
+                time: 0.0,
+                spread: 0.0,
+            };
+            for bounces in 0..max_depth {
+                let Some(hit) = scene.objects.hit(&ray) else {
+                    break;
+                };
+                if scene
+                    .fog
+                    .as_ref()
+                    .is_some_and(|fog| fog.sample_distance(&ray, hit.t).is_some())
+                {
+                    break;
+                }
+                if !is_specular(hit.material) {
+                    if bounces > 0 && gathers(hit.material) {
+                        stored.push(Photon {
+                            position: hit.point,
+                            direction: -ray.direction.unit_vector(),
+                            power,
+                        });
+                    }
+                    break;
+                }
+                let Some((weight, scattered)) = hit.material.scatter(&ray, &hit) else {
+                    break;
+                };
+                power = power * weight;
+                ray = scattered;
+            }
+        }
+        PhotonMap::new(stored)
+    }
+
+    /// Caustic light leaving `rec` along `wo`, from the photons within
+    /// `radius` on the same side of the surface.
+    fn caustics(map: &PhotonMap, radius: f64, rec: &HitRecord, wo: &Vector3d) -> Color {
+        let mut sum = Color::black();
+        map.for_each_near(rec.point, radius, |photon| {
+            let cos = photon.direction.dot(&rec.shading_normal);
+            if cos > 0.0 && photon.direction.dot(&rec.normal) > 0.0 {
+                sum = sum + photon.power * rec.material.eval(rec, wo, &photon.direction) / cos;
+            }
+        });
+        sum / (PI * radius * radius)
+    }
+}
+
+impl Integrator for PhotonMapper {
+    fn prepare(&mut self, scene: &Scene, option: &RenderOption) {
+        self.maps = self
+            .radii()
+            .into_iter()
+            .map(|radius| (self.trace_photons(scene, option.max_depth), radius))
+            .collect();
+    }
+
+    fn radiance(&self, ray: &Ray, scene: &Scene, option: &RenderOption) -> Radiance {
+        let map = match self.maps.len() {
+            0 => None,
+            n => Some(&self.maps[((random::<f64>() * n as f64) as usize).min(n - 1)]),
+        };
+        let mut radiance = Radiance::black();
+        let mut ray = *ray;
+        let mut throughput = Color::white();
+        let mut chain = Chain::Open;
+        let mut bounces = 0;
+        let mut steps = 0;
+        while bounces < option.max_depth {
+            let hit = scene.objects.hit_object(&ray);
+            let fog_scatter = scene.fog.as_ref().and_then(|fog| {
+                let t = fog.sample_distance(&ray, hit.map_or(f64::INFINITY, |(_, rec)| rec.t))?;
+                let scattered = Ray {
+                    origin: ray.at(t),
+                    direction: fog.phase.sample(&-ray.direction),
+                    ..ray
+                };
+                Some((fog.albedo, scattered))
+            });
+            let mut walk_step = false;
+            let next = match (fog_scatter, hit) {
+                (Some(scatter), _) => {
+                    chain = Chain::Open;
+                    Some(scatter)
+                }
+                (None, Some((object, rec))) => {
+                    if chain != Chain::Covered || !scene.objects.hitables[object].is_light() {
+                        radiance.add(bounces, throughput * rec.material.emitted(&rec));
+                    }
+                    chain = match (map, gathers(rec.material)) {
+                        (Some((map, radius)), true) => {
+                            let wo = -ray.direction.unit_vector();
+                            let caustics = PhotonMapper::caustics(map, *radius, &rec, &wo);
+                            radiance.add(bounces + 2, throughput * caustics);
+                            Chain::Gathered
+                        }
+                        _ if is_specular(rec.material) && chain != Chain::Open => Chain::Covered,
+                        _ => Chain::Open,
+                    };
+                    walk_step = rec.material.is_walk_step(&rec);
+                    rec.material.scatter(&ray, &rec)
+                }
+                (None, None) => {
+                    radiance.add(bounces, throughput * scene.background(&ray));
+                    None
+                }
+            };
+            let Some((mut weight, scattered)) = next else {
+                break;
+            };
+            // Subsurface walks cost no bounces, as in path tracing.
+            if walk_step {
+                if steps == Subsurface::MAX_STEPS {
+                    break;
+                }
+                throughput = throughput * weight;
+                ray = scattered;
+                steps += 1;
+                continue;
+            }
+            steps = 0;
+            if bounces + 1 >= option.roulette_depth {
+                let survival = (throughput * weight).max_component().min(0.95);
+                if random::<f64>() >= survival {
+                    break;
+                }
+                weight = weight / survival;
+            }
+            throughput = throughput * weight;
+            ray = scattered;
+            bounces += 1;
+        }
+        radiance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::film::PixelFilter;
+    use crate::fog::Fog;
+    use crate::geometry::{HitableList, Sphere};
+    use crate::integrator::PathTracer;
+    use crate::material::{PhaseFunction, RoughDielectric};
+    use crate::math::with_stream;
+    use crate::texture::solid;
+    use crate::Point3d;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_radii_shrink() {
+        let mapper = PhotonMapper::progressive(0, 0.1, 4);
+        let radii = mapper.radii();
+        assert_eq!(radii.len(), 4);
+        assert_eq!(radii[0], 0.1);
+        assert!((radii[1] - 0.1 * (1.7_f64 / 2.0).sqrt()).abs() < 1e-12);
+        assert!(radii.windows(2).all(|pair| pair[1] < pair[0]));
+    }
+
+    /// A glass ball floating between a light and the floor.
+    fn caustic_scene(fog: Option<Fog>) -> Scene {
+        let sphere = |center: [f64; 3], radius: f64, material: Material| Sphere {
+            center: Point3d::new(center),
+            radius,
+            material,
+        };
+        Scene {
+            objects: HitableList {
+                hitables: vec![
+                    Box::new(sphere(
+                        [0.0, -100.5, -1.0],
+                        100.0,
+                        Material::Lambertian(solid(Color::new([0.8; 3]))),
+                    )),
+                    Box::new(sphere([0.0, 0.3, -1.0], 0.25, glass())),
+                    Box::new(sphere(
+                        [0.0, 1.5, -1.0],
+                        0.2,
+                        Material::DiffuseLight(solid(Color::new([20.0; 3]))),
+                    )),
+                ],
+            },
+            camera: Camera::new(1.0, 2.0, 1.0),
+            fog,
+        }
+    }
+
+    fn glass() -> Material {
+        Material::RoughDielectric(RoughDielectric {
+            ior: 1.5,
+            roughness: 0.02,
+        })
+    }
+
+    #[test]
+    fn test_gathers_where_eval_sees_the_light() {
+        let diffuse = || Box::new(Material::Lambertian(solid(Color::white())));
+        let mix = |b| Material::Mix {
+            a: diffuse(),
+            b,
+            factor: solid(Color::new([0.5; 3])),
+        };
+        assert!(gathers(&diffuse()));
+        assert!(gathers(&mix(diffuse())));
+        assert!(!gathers(&glass()));
+        assert!(!gathers(&mix(Box::new(glass()))));
+        let skin = Subsurface::new(Color::new([0.8; 3]), Color::new([0.1; 3]));
+        assert!(!gathers(&Material::Subsurface(skin)));
+    }
+
+    #[test]
+    fn test_fog_dims_photons() {
+        // Photons cross about 1.8 units of fog between the light and the
+        // floor, leaving e^-0.9 of them.
+        let fog = Fog {
+            density: 0.5,
+            albedo: Color::white(),
+            phase: PhaseFunction::Isotropic,
+            height: 10.0,
+        };
+        let mapper = PhotonMapper::new(100000, 0.05);
+        let stored = |fog| {
+            let scene = caustic_scene(fog);
+            let (_, map) =
+                with_stream(StdRng::seed_from_u64(5), || mapper.trace_photons(&scene, 8));
+            map.len() as f64
+        };
+        let ratio = stored(Some(fog)) / stored(None);
+        assert!((0.25..0.5).contains(&ratio), "{}", ratio);
+    }
+
+    #[test]
+    fn test_caustic_matches_path_tracing() {
+        let scene = caustic_scene(None);
+        let option = RenderOption {
+            samples_per_pixel: 1,
+            max_depth: 8,
+            roulette_depth: usize::MAX,
+            indirect_clamp: None,
+            filter: PixelFilter::default(),
+            denoiser: None,
+        };
+        let mut mapper = PhotonMapper::progressive(20000, 0.05, 8);
+        mapper.prepare(&scene, &option);
+        assert!(mapper.maps.iter().all(|(map, _)| !map.is_empty()));
+        // Look at the floor under the ball from the side, clear of the ball.
+        let n = 100000;
+        let (mut photon, mut path) = (0.0, 0.0);
+        for _ in 0..n {
+            let (r, angle) = (0.2 * random::<f64>().sqrt(), 2.0 * PI * random::<f64>());
+            let target = Point3d::new([r * angle.cos(), -0.5, -1.0 + r * angle.sin()]);
+            let origin = Point3d::new([1.0, 0.2, -1.0]);
+            let ray = Ray {
+                origin,
+                direction: target - origin,
+                time: 0.0,
+                spread: 0.0,
+            };
+            photon += mapper.radiance(&ray, &scene, &option).total().x();
+            path += PathTracer.radiance(&ray, &scene, &option).total().x();
+        }
+        let (photon, path) = (photon / n as f64, path / n as f64);
+        assert!(
+            (photon - path).abs() < 0.06 * path,
+            "{} vs {}",
+            photon,
+            path
+        );
+    }
+}
@@ -2,32 +2,123 @@
 use std::time::Instant;
 
 mod geometry {
+    mod aabb;
+    pub use aabb::Aabb;
+    mod constant_medium;
+    pub use constant_medium::ConstantMedium;
+    mod csg;
+    pub use csg::Csg;
+    mod heightfield;
+    pub use heightfield::Heightfield;
     mod hitable;
-    pub use hitable::{HitRecord, Hitable};
+    pub use hitable::{take_tests, Counting, HitRecord, Hitable};
     mod hitable_list;
     pub use hitable_list::HitableList;
+    mod instance;
+    pub use instance::Instance;
+    mod moving_sphere;
+    pub use moving_sphere::MovingSphere;
+    mod sdf;
+    pub use sdf::{Sdf, SdfNode};
     mod sphere;
     pub use sphere::Sphere;
+    mod volume;
+    pub use volume::VoxelVolume;
+}
+
+mod integrator {
+    #[allow(clippy::module_inception)]
+    mod integrator;
+    pub use integrator::{Integrator, Radiance, Splat};
+    mod metropolis;
+    pub use metropolis::MetropolisLightTransport;
+    mod path;
+    pub use path::PathTracer;
+    mod photon;
+    pub use photon::PhotonMapper;
+    mod ambient;
+    pub use ambient::AmbientOcclusion;
+    mod bdpt;
+    pub use bdpt::BidirectionalPathTracer;
+    mod debug;
+    pub use debug::{DebugIntegrator, DebugView};
+    mod direct;
+    pub use direct::DirectLighting;
+    mod light;
+    pub use light::{light_estimate, sample_light, sky_estimate};
 }
 
 mod material {
+    #[allow(clippy::module_inception)]
     mod material;
     pub use material::Material;
+    mod bump;
+    pub use bump::BumpMap;
+    mod coated;
+    pub use coated::Coated;
     mod lambertian;
     pub use lambertian::Lambertian;
+    mod microfacet;
+    mod phase;
+    pub use phase::PhaseFunction;
+    mod principled;
+    pub use principled::Principled;
+    mod rough_conductor;
+    pub use rough_conductor::RoughConductor;
+    mod rough_dielectric;
+    pub use rough_dielectric::RoughDielectric;
+    mod subsurface;
+    pub use subsurface::Subsurface;
 }
 
+mod texture {
+    #[allow(clippy::module_inception)]
+    mod texture;
+    pub use texture::{solid, Texture};
+    mod checker;
+    pub use checker::{Checker3d, CheckerUv};
+    mod gradient;
+    pub use gradient::{Gradient, GradientAxis};
+    mod image_texture;
+    pub use image_texture::{Filter, ImageTexture, WrapMode};
+    mod marble;
+    pub use marble::Marble;
+    mod solid_color;
+    pub use solid_color::SolidColor;
+    mod stone;
+    pub use stone::Stone;
+    mod wood;
+    pub use wood::Wood;
+}
+
+mod aov;
 mod camera;
 mod color;
+mod denoise;
+mod exr;
+mod film;
+mod float_image;
+mod fog;
 mod image;
 mod math;
+mod matrix4;
+mod noise;
+mod photon_map;
 mod pixel;
 mod progress;
+mod quaternion;
 mod ray;
 mod renderer;
 mod scene;
+mod transform;
 mod vector3;
-use crate::{image::Image, math::random, renderer::Renderer};
+mod voxel_grid;
+use crate::film::{FilterKind, PixelFilter};
+use crate::integrator::{
+    AmbientOcclusion, BidirectionalPathTracer, DebugIntegrator, DebugView, DirectLighting,
+    Integrator, MetropolisLightTransport, PathTracer, PhotonMapper,
+};
+use crate::{aov::Aovs, image::Image, math::random, renderer::Renderer};
 use color::Color;
 use pixel::Pixel;
 use ray::Ray;
@@ -44,15 +135,41 @@ fn main() {
     // Scene
     let scene = Scene::sample();
 
-    // Render
+    // Render, or make a quick preview or debug view named by the first argument
+    let integrator: Box<dyn Integrator> = match std::env::args().nth(1).as_deref() {
+        None => Box::new(PathTracer),
+        Some("ao") => Box::new(AmbientOcclusion::default()),
+        Some("direct") => Box::new(DirectLighting::default()),
+        Some("bdpt") => Box::new(BidirectionalPathTracer::default()),
+        Some("photon") => Box::new(PhotonMapper::new(200_000, 0.03)),
+        Some("ppm") => Box::new(PhotonMapper::progressive(50_000, 0.08, 16)),
+        Some("mlt") => Box::new(MetropolisLightTransport::default()),
+        Some(name) => match DebugView::from_name(name) {
+            Some(view) => Box::new(DebugIntegrator::new(view)),
+            None => {
+                eprintln!("Unknown integrator {:?}", name);
+                eprintln!(
+                    "Usage: ray_tracing_renderer \
+                     [ao|direct|bdpt|photon|ppm|mlt|normal|uv|depth|bounces|cost]"
+                );
+                std::process::exit(2);
+            }
+        },
+    };
     let option = renderer::RenderOption {
         samples_per_pixel: 100,
         max_depth: 50,
+        roulette_depth: 3,
+        indirect_clamp: None,
+        filter: PixelFilter::new(FilterKind::Gaussian),
+        denoiser: None,
     };
     let mut renderer = Renderer {
         scene: &scene,
         image: &mut image,
-        option: option,
+        option,
+        integrator,
+        aovs: Some(Aovs::new(width, height)),
     };
 
     println!("Starts rendering");
@@ -61,5 +178,9 @@ fn main() {
     let time_end = Instant::now();
     let duration = (time_end - time_start).as_secs_f64();
     println!("\nRendering is completed in {:.2}s", duration);
+    let aovs = renderer.aovs;
     image.output().expect("Image output error");
+    if let Some(aovs) = aovs {
+        aovs.write_exr("./image/test.exr").expect("AOV output error");
+    }
 }
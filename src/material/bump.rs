@@ -0,0 +1,110 @@
+use crate::geometry::HitRecord;
+use crate::texture::Texture;
+use crate::Vector3d;
+use std::sync::Arc;
+
+/// Perturbs the shading normal of a hit before the material scatters.
+#[derive(Clone, Debug)]
+pub enum BumpMap {
+    /// Tangent space normals encoded as colors, `x`, `y` and `z` mapped from
+    /// `[-1, 1]` to `[0, 1]` along tangent, bitangent and normal. The texture
+    /// must hold linear values, e.g. from `ImageTexture::load_linear`.
+    Normal(Arc<dyn Texture>),
+    /// Heights from the mean of the texture channels, with `strength` scaling
+    /// the resulting slopes.
+    Height {
+        map: Arc<dyn Texture>,
+        strength: f64,
+    },
+}
+
+/// Finite difference step, in uv units for image maps and world units for
+/// solid textures.
+const DELTA: f64 = 1e-3;
+
+impl BumpMap {
+    pub fn apply<'a>(&self, rec: &HitRecord<'a>) -> HitRecord<'a> {
+        match self {
+            BumpMap::Normal(map) => {
+                let local = map.sample(rec) * 2.0 - Vector3d::new([1.0, 1.0, 1.0]);
+                rec.with_shading_normal(rec.local_to_world(&local))
+            }
+            BumpMap::Height { map, strength } => {
+                // Stepping both uv and position lets image and solid textures
+                // alike report a gradient.
+                let height = |du: f64, dv: f64| {
+                    let point = rec.point + rec.tangent * du + rec.bitangent * dv;
+                    let c = map.value(rec.u + du, rec.v + dv, &point);
+                    (c.x() + c.y() + c.z()) / 3.0
+                };
+                let h = height(0.0, 0.0);
+                let dhdu = (height(DELTA, 0.0) - h) / DELTA;
+                let dhdv = (height(0.0, DELTA) - h) / DELTA;
+                let n =
+                    rec.shading_normal - (rec.tangent * dhdu + rec.bitangent * dhdv) * *strength;
+                rec.with_shading_normal(n)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::texture::{solid, Gradient, GradientAxis};
+    use crate::{Color, Point3d, Ray};
+
+    fn record(material: &Material) -> HitRecord<'_> {
+        let ray = Ray {
+            origin: Point3d::new([0.0, 1.0, 0.0]),
+            direction: Vector3d::new([0.0, -1.0, 0.0]),
+            time: 0.0,
+            spread: 0.0,
+        };
+        let point = Point3d::new([0.0, 0.0, 0.0]);
+        HitRecord::new(
+            &ray,
+            point,
+            Vector3d::new([0.0, 1.0, 0.0]),
+            1.0,
+            (0.5, 0.5),
+            material,
+        )
+        .with_tangents(
+            Vector3d::new([1.0, 0.0, 0.0]),
+            Vector3d::new([0.0, 0.0, 1.0]),
+        )
+    }
+
+    #[test]
+    fn test_flat_normal_map() {
+        let material = Material::Lambertian(solid(Color::white()));
+        let rec = record(&material);
+        let flat = BumpMap::Normal(solid(Color::new([0.5, 0.5, 1.0])));
+        assert!((flat.apply(&rec).shading_normal - rec.normal).length() < 1e-12);
+        let tilted = BumpMap::Normal(solid(Color::new([1.0, 0.5, 1.0])));
+        let n = tilted.apply(&rec).shading_normal;
+        assert!((n - Vector3d::new([1.0, 1.0, 0.0]).unit_vector()).length() < 1e-12);
+    }
+
+    #[test]
+    fn test_height_slope() {
+        let material = Material::Lambertian(solid(Color::white()));
+        let rec = record(&material);
+        // Height rises along u at one unit per unit, so the normal leans back along -u.
+        let ramp = Gradient {
+            from: Color::black(),
+            to: Color::white(),
+            axis: GradientAxis::U,
+        };
+        let bump = BumpMap::Height {
+            map: Arc::new(ramp),
+            strength: 1.0,
+        };
+        let bumped = bump.apply(&rec);
+        let expected = Vector3d::new([-1.0, 1.0, 0.0]).unit_vector();
+        assert!((bumped.shading_normal - expected).length() < 1e-9);
+        assert_eq!(bumped.normal, rec.normal);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::geometry::HitRecord;
+use crate::material::microfacet::{fresnel_dielectric, reflect, Ggx};
+use crate::material::Material;
+use crate::math::random;
+use crate::{Color, Ray, Vector3d};
+
+/// A thin clear dielectric layer over any `base` material, like lacquer or
+/// car paint. Light the coat reflects never reaches the base, and light
+/// reaching the base crosses the coat twice, losing the Fresnel reflection
+/// and the coat's absorption each way.
+#[derive(Clone, Debug)]
+pub struct Coated {
+    pub base: Box<Material>,
+    pub ior: f64,
+    /// Perceptual roughness of the coat surface.
+    pub roughness: f64,
+    /// Fraction of light crossing the coat straight down, per channel.
+    pub tint: Color,
+}
+
+impl Coated {
+    pub fn new(base: Material) -> Self {
+        Coated {
+            base: Box::new(base),
+            ior: 1.5,
+            roughness: 0.05,
+            tint: Color::white(),
+        }
+    }
+
+    /// Chance of sampling the coat, from its reflectance toward the viewer.
+    fn coat_probability(&self, wo: &Vector3d) -> f64 {
+        fresnel_dielectric(wo.z(), self.ior).clamp(0.05, 0.95)
+    }
+
+    /// What remains of light passing down through the coat along `wo` and
+    /// back up along `wi`, both in the shading frame.
+    fn attenuation(&self, wo: &Vector3d, wi: &Vector3d) -> Color {
+        let (cos_o, cos_i) = (wo.z().abs(), wi.z().abs());
+        if cos_o == 0.0 || cos_i == 0.0 {
+            return Color::black();
+        }
+        let transmitted = (1.0 - fresnel_dielectric(cos_o, self.ior))
+            * (1.0 - fresnel_dielectric(cos_i, self.ior));
+        let path = 1.0 / cos_o + 1.0 / cos_i;
+        let absorbed: Color = (0..3).map(|i| self.tint[i].powf(path)).collect();
+        absorbed * transmitted
+    }
+
+    /// Coat reflection BSDF times cosine, in the shading frame.
+    fn coat_eval(&self, wo: &Vector3d, wi: &Vector3d) -> f64 {
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return 0.0;
+        }
+        let h = (*wo + *wi).unit_vector();
+        let ggx = Ggx::from_roughness(self.roughness);
+        fresnel_dielectric(wo.dot(&h), self.ior) * ggx.d(&h) * ggx.g(wo, wi) / (4.0 * wo.z())
+    }
+
+    fn coat_pdf(&self, wo: &Vector3d, wi: &Vector3d) -> f64 {
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return 0.0;
+        }
+        let h = (*wo + *wi).unit_vector();
+        Ggx::from_roughness(self.roughness).pdf_visible(wo, &h) / (4.0 * wo.dot(&h))
+    }
+
+    pub fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let wo = rec.world_to_local(&-ray.direction.unit_vector());
+        if !rec.front_face || wo.z() <= 0.0 {
+            return self.base.scatter(ray, rec);
+        }
+        let p = self.coat_probability(&wo);
+        if random::<f64>() < p {
+            let h = Ggx::from_roughness(self.roughness).sample_visible(&wo);
+            let wi = reflect(&wo, &h);
+            let direction = rec.local_to_world(&wi);
+            if wi.z() <= 0.0 || direction.dot(&rec.normal) <= 0.0 {
+                return None;
+            }
+            let value = self.coat_eval(&wo, &wi) / (p * self.coat_pdf(&wo, &wi));
+            let scattered = Ray {
+                origin: rec.point,
+                direction,
+                time: ray.time,
+                spread: ray.spread,
+            };
+            return Some((Color::new([value, value, value]), scattered));
+        }
+        let (weight, scattered) = self.base.scatter(ray, rec)?;
+        let wi = rec.world_to_local(&scattered.direction.unit_vector());
+        Some((weight * self.attenuation(&wo, &wi) / (1.0 - p), scattered))
+    }
+
+    /// BSDF times the cosine at `wi`, for unit directions pointing away from the hit.
+    pub fn eval(&self, rec: &HitRecord, wo: &Vector3d, wi: &Vector3d) -> Color {
+        let base = self.base.eval(rec, wo, wi);
+        let (lo, li) = (rec.world_to_local(wo), rec.world_to_local(wi));
+        // Where `scatter` leaves the base alone, so does its density.
+        if !rec.front_face || lo.z() <= 0.0 {
+            return base;
+        }
+        let coat = self.coat_eval(&lo, &li);
+        Color::new([coat, coat, coat]) + base * self.attenuation(&lo, &li)
+    }
+
+    /// Solid angle density with which `scatter` picks `wi`.
+    pub fn pdf(&self, rec: &HitRecord, wo: &Vector3d, wi: &Vector3d) -> f64 {
+        let base = self.base.pdf(rec, wo, wi);
+        let lo = rec.world_to_local(wo);
+        if !rec.front_face || lo.z() <= 0.0 {
+            return base;
+        }
+        let p = self.coat_probability(&lo);
+        p * self.coat_pdf(&lo, &rec.world_to_local(wi)) + (1.0 - p) * base
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::solid;
+    use crate::Point3d;
+
+    fn record(material: &Material) -> (Ray, HitRecord<'_>) {
+        let direction = Vector3d::new([0.6, -0.8, 0.0]);
+        let ray = Ray {
+            origin: Point3d::new([0.0, 0.0, 0.0]) - direction,
+            direction,
+            time: 0.0,
+            spread: 0.0,
+        };
+        let rec = HitRecord::new(
+            &ray,
+            Point3d::new([0.0, 0.0, 0.0]),
+            Vector3d::new([0.0, 1.0, 0.0]),
+            1.0,
+            (0.0, 0.0),
+            material,
+        );
+        (ray, rec)
+    }
+
+    #[test]
+    fn test_energy() {
+        // Coating a white diffuse base can only lose energy, and must not lose much.
+        let coated = Material::Coated(Coated::new(Material::Lambertian(solid(Color::white()))));
+        let (ray, rec) = record(&coated);
+        let n = 50_000;
+        let total: f64 = (0..n)
+            .filter_map(|_| coated.scatter(&ray, &rec))
+            .map(|(w, _)| w.x())
+            .sum();
+        let albedo = total / n as f64;
+        assert!(albedo < 1.02 && albedo > 0.8, "{}", albedo);
+    }
+
+    #[test]
+    fn test_pdf_integrates() {
+        let coated = Material::Coated(Coated {
+            roughness: 0.4,
+            ..Coated::new(Material::Lambertian(solid(Color::white())))
+        });
+        let (ray, rec) = record(&coated);
+        let wo = -ray.direction.unit_vector();
+        // Midpoint rule over the upper hemisphere around +y.
+        let n = 400;
+        let (dtheta, dphi) = (
+            std::f64::consts::FRAC_PI_2 / n as f64,
+            2.0 * std::f64::consts::PI / n as f64,
+        );
+        let total: f64 = (0..n * n)
+            .map(|i| {
+                let theta = ((i / n) as f64 + 0.5) * dtheta;
+                let phi = ((i % n) as f64 + 0.5) * dphi;
+                let wi = Vector3d::new([
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                ]);
+                coated.pdf(&rec, &wo, &wi) * theta.sin() * dtheta * dphi
+            })
+            .sum();
+        assert!((total - 1.0).abs() < 0.01, "{}", total);
+    }
+
+    #[test]
+    fn test_below_shading_normal_matches_base() {
+        // Viewed from below the bent shading normal, `scatter` samples the
+        // base alone, so `eval` and `pdf` must describe the base alone too.
+        let base = Material::Lambertian(solid(Color::white()));
+        let coated = Material::Coated(Coated::new(base.clone()));
+        let (ray, rec) = record(&coated);
+        let rec = rec.with_shading_normal(Vector3d::new([1.0, 0.3, 0.0]));
+        let wo = -ray.direction.unit_vector();
+        assert!(rec.world_to_local(&wo).z() <= 0.0);
+        let wi = Vector3d::new([0.6, 0.8, 0.0]);
+        let base_rec = HitRecord { material: &base, ..rec };
+        assert_eq!(coated.eval(&rec, &wo, &wi), base.eval(&base_rec, &wo, &wi));
+        assert_eq!(coated.pdf(&rec, &wo, &wi), base.pdf(&base_rec, &wo, &wi));
+    }
+}
@@ -1,12 +1,26 @@
-use crate::{Color, geometry::HitRecord, Ray, vector3::Vector3};
+use crate::{Color, geometry::HitRecord, Ray, texture::Texture, vector3::{Vector3, Vector3d}};
+use std::f64::consts::PI;
 
 pub struct Lambertian {}
 
 impl Lambertian {
-    pub fn scatter(albedo: &Color, rec: &HitRecord) -> Option<(Color, Ray)> {
-        let scatter_direction = rec.normal + Vector3::random_in_unit_sphere();
-        let scattered = Ray{origin: rec.point, direction: scatter_direction};
-        let attenuation = albedo.clone();
+    pub fn scatter(albedo: &dyn Texture, ray: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        // Offsetting by a unit vector gives cosine distributed directions.
+        let mut scatter_direction = rec.shading_normal + Vector3::random_in_unit_vector();
+        if scatter_direction.length_squared() < 1e-16 {
+            scatter_direction = rec.shading_normal;
+        }
+        let scattered = Ray{origin: rec.point, direction: scatter_direction, time: ray.time, spread: ray.spread};
+        let attenuation = albedo.sample(rec);
         Some((attenuation, scattered))
     }
-}
\ No newline at end of file
+
+    /// BSDF times the cosine at `wi`, for unit directions pointing away from the hit.
+    pub fn eval(albedo: &dyn Texture, rec: &HitRecord, wi: &Vector3d) -> Color {
+        albedo.sample(rec) * Self::pdf(rec, wi)
+    }
+
+    pub fn pdf(rec: &HitRecord, wi: &Vector3d) -> f64 {
+        wi.dot(&rec.shading_normal).max(0.0) / PI
+    }
+}
@@ -1,18 +1,154 @@
 use crate::geometry::HitRecord;
+use crate::texture::Texture;
 use crate::Color;
 use crate::Ray;
-use crate::material::Lambertian;
+use crate::math::random;
+use crate::material::{BumpMap, Coated, Lambertian, PhaseFunction, Principled, RoughConductor, RoughDielectric, Subsurface};
+use crate::vector3::Vector3d;
+use std::sync::Arc;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub enum Material {
-    Lambertian(Color),
+    Lambertian(Arc<dyn Texture>),
+    RoughConductor(RoughConductor),
+    RoughDielectric(RoughDielectric),
+    Principled(Principled),
+    Subsurface(Subsurface),
+    /// Scattering inside a participating medium rather than at a surface,
+    /// with `albedo` the chance of scattering rather than absorbing.
+    Medium { albedo: Arc<dyn Texture>, phase: PhaseFunction },
+    /// `material` shaded with normals perturbed by `map`.
+    Bumped { material: Box<Material>, map: BumpMap },
+    /// Emits light from its front face without scattering any.
+    DiffuseLight(Arc<dyn Texture>),
+    /// Blend of `a` and `b`, weighted toward `b` by the mean of the
+    /// `factor` channels.
+    Mix { a: Box<Material>, b: Box<Material>, factor: Arc<dyn Texture> },
+    Coated(Coated),
+}
+
+fn mix_factor(factor: &dyn Texture, rec: &HitRecord) -> f64 {
+    let c = factor.sample(rec);
+    ((c.x() + c.y() + c.z()) / 3.0).clamp(0.0, 1.0)
 }
 
 impl Material {
+    /// Samples a continuation of `ray`, returning it with its throughput
+    /// weight: BSDF times cosine over the sampling density.
     pub fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
         match self {
-            Material::Lambertian(albedo) => Lambertian::scatter(albedo, rec),
+            Material::Lambertian(albedo) => Lambertian::scatter(albedo.as_ref(), ray, rec),
+            Material::RoughConductor(conductor) => conductor.scatter(ray, rec),
+            Material::RoughDielectric(dielectric) => dielectric.scatter(ray, rec),
+            Material::Principled(principled) => principled.scatter(ray, rec),
+            Material::Subsurface(subsurface) => subsurface.scatter(ray, rec),
+            Material::Medium { albedo, phase } => Some(phase.scatter(albedo.as_ref(), ray, rec)),
+            Material::Bumped { material, map } => material.scatter(ray, &map.apply(rec)),
+            Material::DiffuseLight(_) => None,
+            // Picking one side in proportion to its weight keeps its own
+            // throughput weight unbiased for the blend.
+            Material::Mix { a, b, factor } => {
+                if random::<f64>() < mix_factor(factor.as_ref(), rec) {
+                    b.scatter(ray, rec)
+                } else {
+                    a.scatter(ray, rec)
+                }
+            }
+            Material::Coated(coated) => coated.scatter(ray, rec),
+        }
+    }
+
+    /// BSDF times the cosine at `wi` (or the phase function in a medium) for light arriving along `-wi` and leaving
+    /// along `wo`, both unit vectors pointing away from the surface.
+    pub fn eval(&self, rec: &HitRecord, wo: &Vector3d, wi: &Vector3d) -> Color {
+        match self {
+            Material::Lambertian(albedo) => Lambertian::eval(albedo.as_ref(), rec, wi),
+            Material::RoughConductor(conductor) => conductor.eval(rec, wo, wi),
+            Material::RoughDielectric(dielectric) => dielectric.eval(rec, wo, wi),
+            Material::Principled(principled) => principled.eval(rec, wo, wi),
+            // Random walks have no closed form density.
+            Material::Subsurface(_) => Color::black(),
+            Material::Medium { albedo, phase } => albedo.sample(rec) * phase.eval(wo, wi),
+            Material::Bumped { material, map } => material.eval(&map.apply(rec), wo, wi),
+            Material::DiffuseLight(_) => Color::black(),
+            Material::Mix { a, b, factor } => {
+                let f = mix_factor(factor.as_ref(), rec);
+                a.eval(rec, wo, wi) * (1.0 - f) + b.eval(rec, wo, wi) * f
+            }
+            Material::Coated(coated) => coated.eval(rec, wo, wi),
+        }
+    }
+
+    /// Solid angle density with which `scatter` picks `wi` given `wo`, for
+    /// weighting against other sampling strategies such as light sampling.
+    pub fn pdf(&self, rec: &HitRecord, wo: &Vector3d, wi: &Vector3d) -> f64 {
+        match self {
+            Material::Lambertian(_) => Lambertian::pdf(rec, wi),
+            Material::RoughConductor(conductor) => conductor.pdf(rec, wo, wi),
+            Material::RoughDielectric(dielectric) => dielectric.pdf(rec, wo, wi),
+            Material::Principled(principled) => principled.pdf(rec, wo, wi),
+            Material::Subsurface(_) => 0.0,
+            Material::Medium { phase, .. } => phase.eval(wo, wi),
+            Material::Bumped { material, map } => material.pdf(&map.apply(rec), wo, wi),
+            Material::DiffuseLight(_) => 0.0,
+            Material::Mix { a, b, factor } => {
+                let f = mix_factor(factor.as_ref(), rec);
+                a.pdf(rec, wo, wi) * (1.0 - f) + b.pdf(rec, wo, wi) * f
+            }
+            Material::Coated(coated) => coated.pdf(rec, wo, wi),
+        }
+    }
 
+    /// Whether the material gives off light, alone or under a coat, bump
+    /// map or blend.
+    pub fn is_emissive(&self) -> bool {
+        match self {
+            Material::DiffuseLight(_) => true,
+            Material::Bumped { material, .. } => material.is_emissive(),
+            Material::Mix { a, b, .. } => a.is_emissive() || b.is_emissive(),
+            Material::Coated(coated) => coated.base.is_emissive(),
+            _ => false,
+        }
+    }
+
+    /// Whether scattering at `rec` is a step of a subsurface random walk
+    /// inside the object rather than a bounce off its surface. Integrators
+    /// let a whole walk count as the one bounce that entered it.
+    pub fn is_walk_step(&self, rec: &HitRecord) -> bool {
+        matches!(self, Material::Subsurface(_)) && !rec.front_face
+    }
+
+    /// Overall surface color at the hit, free of lighting, for feature
+    /// buffers such as the albedo pass.
+    pub fn albedo(&self, rec: &HitRecord) -> Color {
+        match self {
+            Material::Lambertian(albedo) => albedo.sample(rec),
+            Material::RoughConductor(conductor) => conductor.reflectance(),
+            Material::RoughDielectric(_) => Color::white(),
+            Material::Principled(principled) => principled.base_color.sample(rec),
+            Material::Subsurface(subsurface) => subsurface.albedo,
+            Material::Medium { albedo, .. } => albedo.sample(rec),
+            Material::Bumped { material, .. } => material.albedo(rec),
+            Material::DiffuseLight(_) => Color::black(),
+            Material::Mix { a, b, factor } => {
+                let f = mix_factor(factor.as_ref(), rec);
+                a.albedo(rec) * (1.0 - f) + b.albedo(rec) * f
+            }
+            Material::Coated(coated) => coated.base.albedo(rec),
+        }
+    }
+
+    /// Radiance given off at the hit toward the incoming ray.
+    pub fn emitted(&self, rec: &HitRecord) -> Color {
+        match self {
+            Material::DiffuseLight(emit) if rec.front_face => emit.sample(rec),
+            Material::Bumped { material, .. } => material.emitted(rec),
+            Material::Mix { a, b, factor } => {
+                let f = mix_factor(factor.as_ref(), rec);
+                a.emitted(rec) * (1.0 - f) + b.emitted(rec) * f
+            }
+            Material::Coated(coated) => coated.base.emitted(rec),
+            _ => Color::black(),
         }
     }
 }
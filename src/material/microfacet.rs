@@ -0,0 +1,205 @@
+use crate::math::random;
+use crate::Vector3d;
+use std::f64::consts::PI;
+
+/// GGX (Trowbridge-Reitz) microfacet distribution with the height-correlated
+/// Smith masking-shadowing function, optionally anisotropic. Directions are in
+/// the shading frame, where the macro surface normal is `+z` and `alpha_x`
+/// applies along the tangent.
+#[derive(Clone, Copy, Debug)]
+pub struct Ggx {
+    pub alpha_x: f64,
+    pub alpha_y: f64,
+}
+
+impl Ggx {
+    /// Alphas get a small floor so the distribution stays finite.
+    pub fn new(alpha_x: f64, alpha_y: f64) -> Self {
+        Ggx {
+            alpha_x: alpha_x.max(1e-4),
+            alpha_y: alpha_y.max(1e-4),
+        }
+    }
+
+    /// Maps perceptual roughness in `[0, 1]` to `alpha = roughness^2`.
+    pub fn from_roughness(roughness: f64) -> Self {
+        Ggx::new(roughness * roughness, roughness * roughness)
+    }
+
+    /// Stretches the highlight along the tangent for positive `anisotropy`
+    /// in `[0, 1]`, keeping the mean roughness, as in the Disney BRDF.
+    pub fn anisotropic(roughness: f64, anisotropy: f64) -> Self {
+        let aspect = (1.0 - 0.9 * anisotropy).sqrt();
+        let alpha = roughness * roughness;
+        Ggx::new(alpha / aspect, alpha * aspect)
+    }
+
+    /// Density of micro normals `h` per unit projected area.
+    pub fn d(&self, h: &Vector3d) -> f64 {
+        if h.z() <= 0.0 {
+            return 0.0;
+        }
+        let (ax, ay) = (self.alpha_x, self.alpha_y);
+        let e = (h.x() / ax).powi(2) + (h.y() / ay).powi(2) + h.z() * h.z();
+        1.0 / (PI * ax * ay * e * e)
+    }
+
+    fn lambda(&self, w: &Vector3d) -> f64 {
+        let cos2 = w.z() * w.z();
+        if cos2 == 0.0 {
+            return f64::INFINITY;
+        }
+        let projected = (self.alpha_x * w.x()).powi(2) + (self.alpha_y * w.y()).powi(2);
+        ((1.0 + projected / cos2).sqrt() - 1.0) / 2.0
+    }
+
+    /// Fraction of micro normals visible from `w`.
+    pub fn g1(&self, w: &Vector3d) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Fraction of micro normals visible from both `wo` and `wi`.
+    pub fn g(&self, wo: &Vector3d, wi: &Vector3d) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Density of micro normals seen from `wo`, per solid angle of `h`.
+    pub fn pdf_visible(&self, wo: &Vector3d, h: &Vector3d) -> f64 {
+        self.g1(wo) * self.d(h) * wo.dot(h).max(0.0) / wo.z().abs()
+    }
+
+    /// Samples a micro normal visible from `wo`, which must lie above the
+    /// surface (Heitz, "Sampling the GGX Distribution of Visible Normals").
+    pub fn sample_visible(&self, wo: &Vector3d) -> Vector3d {
+        let (ax, ay) = (self.alpha_x, self.alpha_y);
+        // Stretch to the configuration where the distribution is a hemisphere.
+        let vh = Vector3d::new([ax * wo.x(), ay * wo.y(), wo.z()]).unit_vector();
+        let lensq = vh.x() * vh.x() + vh.y() * vh.y();
+        let t1 = if lensq > 0.0 {
+            Vector3d::new([-vh.y(), vh.x(), 0.0]) / lensq.sqrt()
+        } else {
+            Vector3d::new([1.0, 0.0, 0.0])
+        };
+        let t2 = vh.cross(&t1);
+        let r = random::<f64>().sqrt();
+        let phi = 2.0 * PI * random::<f64>();
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z());
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+        let nh = t1 * p1 + t2 * p2 + vh * (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+        Vector3d::new([ax * nh.x(), ay * nh.y(), nh.z().max(1e-6)]).unit_vector()
+    }
+}
+
+pub fn reflect(w: &Vector3d, h: &Vector3d) -> Vector3d {
+    *h * (2.0 * w.dot(h)) - *w
+}
+
+/// Refracts `w` through the micro normal `h` on its side, `eta` being the
+/// ratio of the index beyond the surface to the index on `w`'s side.
+/// `None` on total internal reflection.
+pub fn refract(w: &Vector3d, h: &Vector3d, eta: f64) -> Option<Vector3d> {
+    let cos_i = w.dot(h);
+    let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(-*w / eta + *h * (cos_i / eta - cos_t))
+}
+
+/// Unpolarized Fresnel reflectance of a dielectric interface, with `eta` as in
+/// `refract` and `cos_i > 0`.
+pub fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    (parallel * parallel + perpendicular * perpendicular) / 2.0
+}
+
+/// Unpolarized Fresnel reflectance of a conductor with complex index of
+/// refraction `eta + ik` against air.
+pub fn fresnel_conductor(cos_i: f64, eta: f64, k: f64) -> f64 {
+    let cos2 = cos_i * cos_i;
+    let sin2 = 1.0 - cos2;
+    let t0 = eta * eta - k * k - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let t2 = 2.0 * cos_i * a;
+    let rs = (t1 - t2) / (t1 + t2);
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+    (rs + rp) / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spherical(theta: f64, phi: f64) -> Vector3d {
+        Vector3d::new([
+            theta.sin() * phi.cos(),
+            theta.sin() * phi.sin(),
+            theta.cos(),
+        ])
+    }
+
+    #[test]
+    fn test_distribution_normalized() {
+        // The projected area of all micro facets equals the macro surface.
+        for (ax, ay) in [(0.1, 0.1), (0.5, 0.5), (1.0, 1.0), (0.2, 0.6)] {
+            let ggx = Ggx::new(ax, ay);
+            let n = 600;
+            let (dtheta, dphi) = (PI / 2.0 / n as f64, 2.0 * PI / n as f64);
+            let total: f64 = (0..n * n)
+                .map(|i| {
+                    let theta = ((i / n) as f64 + 0.5) * dtheta;
+                    let h = spherical(theta, ((i % n) as f64 + 0.5) * dphi);
+                    ggx.d(&h) * h.z() * theta.sin() * dtheta * dphi
+                })
+                .sum();
+            assert!((total - 1.0).abs() < 1e-3, "alpha {} {}: {}", ax, ay, total);
+        }
+    }
+
+    #[test]
+    fn test_visible_normals() {
+        let ggx = Ggx::anisotropic(0.6, 0.8);
+        let wo = spherical(1.2, 0.3);
+        for _ in 0..1000 {
+            let h = ggx.sample_visible(&wo);
+            assert!((h.length() - 1.0).abs() < 1e-9);
+            assert!(h.z() > 0.0 && wo.dot(&h) >= -1e-9);
+        }
+    }
+
+    #[test]
+    fn test_refract_round_trip() {
+        let n = Vector3d::new([0.0, 0.0, 1.0]);
+        let w = spherical(0.5, 1.0);
+        let t = refract(&w, &n, 1.5).unwrap();
+        assert!(t.z() < 0.0);
+        // Snell's law: sin_i = 1.5 sin_t.
+        let sin_t = (1.0 - t.z() * t.z()).sqrt();
+        assert!((0.5_f64.sin() - 1.5 * sin_t).abs() < 1e-12);
+        let back = refract(&t, &-n, 1.0 / 1.5).unwrap();
+        assert!((back - w).length() < 1e-12);
+        assert!(refract(&spherical(1.2, 0.0), &n, 1.0 / 1.5).is_none());
+    }
+
+    #[test]
+    fn test_fresnel() {
+        // Normal incidence on glass reflects ((n - 1) / (n + 1))^2.
+        assert!((fresnel_dielectric(1.0, 1.5) - 0.04).abs() < 1e-12);
+        assert_eq!(fresnel_dielectric(0.2, 1.0 / 1.5), 1.0);
+        // A conductor without absorption is a dielectric.
+        assert!((fresnel_conductor(0.7, 1.5, 0.0) - fresnel_dielectric(0.7, 1.5)).abs() < 1e-12);
+        assert!(fresnel_conductor(0.5, 0.2, 3.9) > 0.9);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::geometry::HitRecord;
+use crate::math::random;
+use crate::texture::Texture;
+use crate::{Color, Ray, Vector3d};
+use std::f64::consts::PI;
+
+/// Angular distribution of light scattered inside a medium. Like a BSDF it
+/// takes `wo` and `wi` pointing away from the scattering point, so forward
+/// scattering means `wi` close to `-wo`.
+#[derive(Clone, Copy, Debug)]
+pub enum PhaseFunction {
+    Isotropic,
+    /// Henyey-Greenstein with asymmetry `g` in `(-1, 1)`: positive values
+    /// scatter forward, as haze and smoke do, negative ones backward.
+    HenyeyGreenstein(f64),
+}
+
+impl PhaseFunction {
+    /// Density of `wi` per solid angle, which is also the phase value.
+    pub fn eval(&self, wo: &Vector3d, wi: &Vector3d) -> f64 {
+        match *self {
+            PhaseFunction::Isotropic => 1.0 / (4.0 * PI),
+            PhaseFunction::HenyeyGreenstein(g) => {
+                let cos = -wo.dot(wi) / (wo.length() * wi.length());
+                let denom = 1.0 + g * g - 2.0 * g * cos;
+                (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt())
+            }
+        }
+    }
+
+    /// Samples a unit `wi` with density `eval(wo, wi)`.
+    pub fn sample(&self, wo: &Vector3d) -> Vector3d {
+        let xi = random::<f64>();
+        let cos = match *self {
+            PhaseFunction::HenyeyGreenstein(g) if g.abs() > 1e-3 => {
+                let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+                (1.0 + g * g - s * s) / (2.0 * g)
+            }
+            _ => 1.0 - 2.0 * xi,
+        };
+        let sin = (1.0 - cos * cos).max(0.0).sqrt();
+        let phi = 2.0 * PI * random::<f64>();
+        let forward = -wo.unit_vector();
+        let (t, b) = forward.orthonormal_basis();
+        t * (sin * phi.cos()) + b * (sin * phi.sin()) + forward * cos
+    }
+
+    /// Continues `ray` from a scattering event at `rec.point`. Sampling the
+    /// phase function exactly leaves only the albedo as weight.
+    pub fn scatter(&self, albedo: &dyn Texture, ray: &Ray, rec: &HitRecord) -> (Color, Ray) {
+        let scattered = Ray {
+            origin: rec.point,
+            direction: self.sample(&-ray.direction),
+            time: ray.time,
+            spread: ray.spread,
+        };
+        (albedo.sample(rec), scattered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_henyey_greenstein() {
+        let g = 0.6;
+        let phase = PhaseFunction::HenyeyGreenstein(g);
+        let wo = Vector3d::new([0.0, 0.0, 1.0]);
+        // Normalized over the sphere.
+        let n = 2000;
+        let total: f64 = (0..n)
+            .map(|i| {
+                let cos = 2.0 * (i as f64 + 0.5) / n as f64 - 1.0;
+                let wi = Vector3d::new([(1.0 - cos * cos).sqrt(), 0.0, cos]);
+                phase.eval(&wo, &wi) * 2.0 * PI * 2.0 / n as f64
+            })
+            .sum();
+        assert!((total - 1.0).abs() < 1e-3);
+        // The mean cosine from the forward direction is g.
+        let samples = 100_000;
+        let mean: f64 = (0..samples).map(|_| -phase.sample(&wo).z()).sum::<f64>() / samples as f64;
+        assert!((mean - g).abs() < 0.01, "{}", mean);
+    }
+}
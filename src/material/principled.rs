@@ -0,0 +1,382 @@
+use crate::geometry::HitRecord;
+use crate::material::microfacet::{fresnel_dielectric, reflect, refract, Ggx};
+use crate::material::RoughDielectric;
+use crate::math::random;
+use crate::texture::{solid, Texture};
+use crate::{Color, Ray, Vector3d};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// The Disney "principled" BSDF as exported by glTF and Blender: a blend of
+/// diffuse with sheen, a metallic or dielectric specular layer, a clearcoat
+/// and rough transmission. Scalar parameters are read from the first channel
+/// of their textures and lie in `[0, 1]`.
+#[derive(Clone, Debug)]
+pub struct Principled {
+    pub base_color: Arc<dyn Texture>,
+    pub metallic: Arc<dyn Texture>,
+    pub roughness: Arc<dyn Texture>,
+    /// Dielectric reflectance at normal incidence, `0.5` giving the usual 4%.
+    pub specular: Arc<dyn Texture>,
+    /// Soft white rim for cloth.
+    pub sheen: Arc<dyn Texture>,
+    pub clearcoat: Arc<dyn Texture>,
+    pub clearcoat_roughness: Arc<dyn Texture>,
+    /// Fraction of the dielectric base that refracts instead of diffusing.
+    pub transmission: Arc<dyn Texture>,
+    /// Stretches highlights along the surface tangent.
+    pub anisotropy: Arc<dyn Texture>,
+    /// Index of refraction used for transmission.
+    pub ior: f64,
+}
+
+/// Parameters looked up at one hit.
+struct Params {
+    base_color: Color,
+    metallic: f64,
+    roughness: f64,
+    specular: f64,
+    sheen: f64,
+    clearcoat: f64,
+    clearcoat_roughness: f64,
+    transmission: f64,
+    anisotropy: f64,
+}
+
+/// The separately sampled parts of the BSDF.
+#[derive(Clone, Copy)]
+enum Lobe {
+    Diffuse,
+    Specular,
+    Clearcoat,
+    Transmission,
+}
+
+const LOBES: [Lobe; 4] = [
+    Lobe::Diffuse,
+    Lobe::Specular,
+    Lobe::Clearcoat,
+    Lobe::Transmission,
+];
+
+fn schlick_weight(cos: f64) -> f64 {
+    (1.0 - cos).clamp(0.0, 1.0).powi(5)
+}
+
+impl Principled {
+    /// A plastic-like dielectric with the given base color; adjust the
+    /// remaining fields from there.
+    pub fn new(base_color: Arc<dyn Texture>) -> Self {
+        let value = |v: f64| solid(Color::new([v, v, v]));
+        Principled {
+            base_color,
+            metallic: value(0.0),
+            roughness: value(0.5),
+            specular: value(0.5),
+            sheen: value(0.0),
+            clearcoat: value(0.0),
+            clearcoat_roughness: value(0.03),
+            transmission: value(0.0),
+            anisotropy: value(0.0),
+            ior: 1.5,
+        }
+    }
+
+    fn params(&self, rec: &HitRecord) -> Params {
+        let scalar = |texture: &Arc<dyn Texture>| texture.sample(rec).x().clamp(0.0, 1.0);
+        Params {
+            base_color: self.base_color.sample(rec),
+            metallic: scalar(&self.metallic),
+            roughness: scalar(&self.roughness),
+            specular: scalar(&self.specular),
+            sheen: scalar(&self.sheen),
+            clearcoat: scalar(&self.clearcoat),
+            clearcoat_roughness: scalar(&self.clearcoat_roughness),
+            transmission: scalar(&self.transmission),
+            anisotropy: scalar(&self.anisotropy),
+        }
+    }
+
+    /// Only the refracting base is reachable from inside the object, so back
+    /// faces behave as a plain rough dielectric.
+    fn interior(&self, params: &Params) -> RoughDielectric {
+        RoughDielectric {
+            ior: self.ior,
+            roughness: params.roughness,
+        }
+    }
+
+    pub fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let params = self.params(rec);
+        if !rec.front_face {
+            return self.interior(&params).scatter(ray, rec);
+        }
+        let wo = rec.world_to_local(&-ray.direction.unit_vector());
+        if wo.z() <= 0.0 {
+            return None;
+        }
+        let weights = lobe_weights(&params, &wo);
+        let total: f64 = weights.iter().sum();
+        let mut pick = random::<f64>() * total;
+        let lobe = LOBES
+            .iter()
+            .zip(weights)
+            .find(|(_, weight)| {
+                pick -= weight;
+                pick < 0.0
+            })
+            .map_or(Lobe::Diffuse, |(&lobe, _)| lobe);
+        let wi = sample_lobe(lobe, &params, self.ior, &wo)?;
+        let direction = rec.local_to_world(&wi);
+        if wi.z() * direction.dot(&rec.normal) <= 0.0 {
+            return None;
+        }
+        let pdf = pdf_local(&params, self.ior, &wo, &wi);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let scattered = Ray {
+            origin: rec.point,
+            direction,
+            time: ray.time,
+            spread: ray.spread,
+        };
+        Some((eval_local(&params, self.ior, &wo, &wi) / pdf, scattered))
+    }
+
+    /// BSDF times the cosine at `wi`, for unit directions pointing away from the hit.
+    pub fn eval(&self, rec: &HitRecord, wo: &Vector3d, wi: &Vector3d) -> Color {
+        let params = self.params(rec);
+        if !rec.front_face {
+            return self.interior(&params).eval(rec, wo, wi);
+        }
+        eval_local(
+            &params,
+            self.ior,
+            &rec.world_to_local(wo),
+            &rec.world_to_local(wi),
+        )
+    }
+
+    /// Solid angle density with which `scatter` picks `wi`.
+    pub fn pdf(&self, rec: &HitRecord, wo: &Vector3d, wi: &Vector3d) -> f64 {
+        let params = self.params(rec);
+        if !rec.front_face {
+            return self.interior(&params).pdf(rec, wo, wi);
+        }
+        pdf_local(
+            &params,
+            self.ior,
+            &rec.world_to_local(wo),
+            &rec.world_to_local(wi),
+        )
+    }
+}
+
+fn specular_ggx(params: &Params) -> Ggx {
+    Ggx::anisotropic(params.roughness, params.anisotropy)
+}
+
+fn clearcoat_ggx(params: &Params) -> Ggx {
+    Ggx::from_roughness(params.clearcoat_roughness)
+}
+
+/// Reflectance at normal incidence, blending dielectric and metal.
+fn specular_f0(params: &Params) -> Color {
+    let dielectric = 0.08 * params.specular;
+    Color::new([dielectric; 3]) * (1.0 - params.metallic) + params.base_color * params.metallic
+}
+
+/// Relative sampling frequency of each lobe in `LOBES` order, roughly
+/// following the energy each reflects.
+fn lobe_weights(params: &Params, wo: &Vector3d) -> [f64; 4] {
+    let dielectric = 1.0 - params.metallic;
+    let f0 = specular_f0(params);
+    let f0 = (f0.x() + f0.y() + f0.z()) / 3.0;
+    [
+        dielectric * (1.0 - params.transmission),
+        f0 + (1.0 - f0) * schlick_weight(wo.z()),
+        0.25 * params.clearcoat,
+        dielectric * params.transmission,
+    ]
+}
+
+fn sample_lobe(lobe: Lobe, params: &Params, ior: f64, wo: &Vector3d) -> Option<Vector3d> {
+    match lobe {
+        Lobe::Diffuse => {
+            // Cosine weighted hemisphere.
+            let r = random::<f64>().sqrt();
+            let phi = 2.0 * PI * random::<f64>();
+            let (x, y) = (r * phi.cos(), r * phi.sin());
+            Some(Vector3d::new([x, y, (1.0 - x * x - y * y).max(0.0).sqrt()]))
+        }
+        Lobe::Specular => {
+            let h = specular_ggx(params).sample_visible(wo);
+            Some(reflect(wo, &h)).filter(|wi| wi.z() > 0.0)
+        }
+        Lobe::Clearcoat => {
+            let h = clearcoat_ggx(params).sample_visible(wo);
+            Some(reflect(wo, &h)).filter(|wi| wi.z() > 0.0)
+        }
+        Lobe::Transmission => {
+            let h = specular_ggx(params).sample_visible(wo);
+            refract(wo, &h, ior).filter(|wi| wi.z() < 0.0)
+        }
+    }
+}
+
+/// Generalized half vector of a refraction from `wo` to `wi`, facing `+z`.
+fn refraction_half_vector(wo: &Vector3d, wi: &Vector3d, ior: f64) -> Option<Vector3d> {
+    let h = *wo + *wi * ior;
+    if h.length_squared() == 0.0 {
+        return None;
+    }
+    let h = h.unit_vector();
+    let h = if h.z() < 0.0 { -h } else { h };
+    Some(h).filter(|h| wo.dot(h) > 0.0 && wi.dot(h) < 0.0)
+}
+
+fn eval_local(params: &Params, ior: f64, wo: &Vector3d, wi: &Vector3d) -> Color {
+    if wo.z() <= 0.0 || wi.z() == 0.0 {
+        return Color::black();
+    }
+    let dielectric = 1.0 - params.metallic;
+    if wi.z() < 0.0 {
+        let Some(h) = refraction_half_vector(wo, wi, ior) else {
+            return Color::black();
+        };
+        let ggx = specular_ggx(params);
+        let f = fresnel_dielectric(wo.dot(&h), ior);
+        let denom = (wi.dot(&h) + wo.dot(&h) / ior).powi(2);
+        let value = (1.0 - f) * ggx.d(&h) * ggx.g(wo, wi) * (wi.dot(&h) * wo.dot(&h)).abs()
+            / (denom * wo.z() * ior * ior);
+        return params.base_color * (dielectric * params.transmission * value);
+    }
+
+    let h = (*wo + *wi).unit_vector();
+    let cos_d = wi.dot(&h);
+
+    // Diffuse with grazing retro-reflection, plus sheen.
+    let fd90 = 0.5 + 2.0 * params.roughness * cos_d * cos_d;
+    let retro = |cos: f64| 1.0 + (fd90 - 1.0) * schlick_weight(cos);
+    let diffuse = params.base_color * (retro(wo.z()) * retro(wi.z()) / PI);
+    let sheen = params.sheen * schlick_weight(cos_d);
+    let base =
+        (diffuse + Color::new([sheen; 3])) * (dielectric * (1.0 - params.transmission) * wi.z());
+
+    let f0 = specular_f0(params);
+    let fresnel = f0 + (Color::white() - f0) * schlick_weight(wo.dot(&h));
+    let ggx = specular_ggx(params);
+    let specular = fresnel * (ggx.d(&h) * ggx.g(wo, wi) / (4.0 * wo.z()));
+
+    let coat = clearcoat_ggx(params);
+    let coat_fresnel = 0.04 + 0.96 * schlick_weight(wo.dot(&h));
+    let clearcoat =
+        0.25 * params.clearcoat * coat_fresnel * coat.d(&h) * coat.g(wo, wi) / (4.0 * wo.z());
+
+    base + specular + Color::new([clearcoat; 3])
+}
+
+/// Mixture of the lobe densities, matching the lobe choice in `scatter`.
+fn pdf_local(params: &Params, ior: f64, wo: &Vector3d, wi: &Vector3d) -> f64 {
+    if wo.z() <= 0.0 || wi.z() == 0.0 {
+        return 0.0;
+    }
+    let weights = lobe_weights(params, wo);
+    let total: f64 = weights.iter().sum();
+    if total <= 0.0 {
+        return 0.0;
+    }
+    let [diffuse, specular, clearcoat, transmission] = weights.map(|w| w / total);
+    if wi.z() < 0.0 {
+        let Some(h) = refraction_half_vector(wo, wi, ior) else {
+            return 0.0;
+        };
+        let denom = (wi.dot(&h) + wo.dot(&h) / ior).powi(2);
+        return transmission * specular_ggx(params).pdf_visible(wo, &h) * wi.dot(&h).abs() / denom;
+    }
+    let h = (*wo + *wi).unit_vector();
+    let reflection = |ggx: Ggx| ggx.pdf_visible(wo, &h) / (4.0 * wo.dot(&h));
+    diffuse * wi.z() / PI
+        + specular * reflection(specular_ggx(params))
+        + clearcoat * reflection(clearcoat_ggx(params))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::Point3d;
+
+    fn sphere_grid(n: usize) -> impl Iterator<Item = Vector3d> {
+        (0..n * n).map(move |i| {
+            let z = 2.0 * ((i / n) as f64 + 0.5) / n as f64 - 1.0;
+            let phi = 2.0 * PI * ((i % n) as f64 + 0.5) / n as f64;
+            let r = (1.0 - z * z).sqrt();
+            Vector3d::new([r * phi.cos(), r * phi.sin(), z])
+        })
+    }
+
+    #[test]
+    fn test_consistent_and_bounded() {
+        let value = |v: f64| solid(Color::new([v, v, v]));
+        let principled = Principled {
+            metallic: value(0.3),
+            roughness: value(0.6),
+            sheen: value(0.5),
+            clearcoat: value(1.0),
+            clearcoat_roughness: value(0.3),
+            transmission: value(0.5),
+            anisotropy: value(0.5),
+            ..Principled::new(value(0.8))
+        };
+        let material = Material::Principled(principled.clone());
+        let direction = Vector3d::new([0.4, -0.7, 0.1]).unit_vector();
+        let ray = Ray {
+            origin: Point3d::new([0.0, 0.0, 0.0]) - direction,
+            direction,
+            time: 0.0,
+            spread: 0.0,
+        };
+        let up = Vector3d::new([0.0, 1.0, 0.0]);
+        let rec = HitRecord::new(
+            &ray,
+            Point3d::new([0.0, 0.0, 0.0]),
+            up,
+            1.0,
+            (0.0, 0.0),
+            &material,
+        );
+
+        let wo = -direction;
+        let grid = 800;
+        let cell = 4.0 * PI / (grid * grid) as f64;
+        let (mut integral, mut density) = (0.0, 0.0);
+        for wi in sphere_grid(grid) {
+            integral += principled.eval(&rec, &wo, &wi).x() * cell;
+            density += principled.pdf(&rec, &wo, &wi) * cell;
+        }
+        let n = 100_000;
+        let (mut weight, mut produced) = (0.0, 0.0);
+        for _ in 0..n {
+            if let Some((w, _)) = principled.scatter(&ray, &rec) {
+                weight += w.x();
+                produced += 1.0;
+            }
+        }
+        let (weight, produced) = (weight / n as f64, produced / n as f64);
+        assert!(
+            (integral - weight).abs() < 0.02,
+            "{} vs {}",
+            integral,
+            weight
+        );
+        assert!(
+            (density - produced).abs() < 0.01,
+            "{} vs {}",
+            density,
+            produced
+        );
+        assert!(integral < 1.0);
+    }
+}
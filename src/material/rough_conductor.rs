@@ -0,0 +1,159 @@
+use crate::geometry::HitRecord;
+use crate::material::microfacet::{fresnel_conductor, reflect, Ggx};
+use crate::{Color, Ray, Vector3d};
+
+/// Metal with GGX microfacets and the Fresnel reflectance of a complex index
+/// of refraction `eta + ik`, given per RGB channel.
+#[derive(Clone, Copy, Debug)]
+pub struct RoughConductor {
+    pub eta: Color,
+    pub k: Color,
+    /// Perceptual roughness in `[0, 1]`.
+    pub roughness: f64,
+}
+
+impl RoughConductor {
+    pub fn gold(roughness: f64) -> Self {
+        RoughConductor {
+            eta: Color::new([0.143, 0.374, 1.442]),
+            k: Color::new([3.983, 2.385, 1.603]),
+            roughness,
+        }
+    }
+
+    pub fn copper(roughness: f64) -> Self {
+        RoughConductor {
+            eta: Color::new([0.200, 0.924, 1.102]),
+            k: Color::new([3.912, 2.452, 2.142]),
+            roughness,
+        }
+    }
+
+    pub fn aluminium(roughness: f64) -> Self {
+        RoughConductor {
+            eta: Color::new([1.657, 0.880, 0.521]),
+            k: Color::new([9.224, 6.270, 4.837]),
+            roughness,
+        }
+    }
+
+    /// Reflectance at normal incidence, the color the metal appears to have.
+    pub fn reflectance(&self) -> Color {
+        self.fresnel(1.0)
+    }
+
+    fn fresnel(&self, cos_i: f64) -> Color {
+        (0..3)
+            .map(|i| fresnel_conductor(cos_i, self.eta[i], self.k[i]))
+            .collect()
+    }
+
+    pub fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let wo = rec.world_to_local(&-ray.direction.unit_vector());
+        if wo.z() <= 0.0 {
+            return None;
+        }
+        let h = Ggx::from_roughness(self.roughness).sample_visible(&wo);
+        let wi = reflect(&wo, &h);
+        let direction = rec.local_to_world(&wi);
+        // Shading normals can send rays below the actual surface.
+        if wi.z() <= 0.0 || direction.dot(&rec.normal) <= 0.0 {
+            return None;
+        }
+        let pdf = self.pdf_local(&wo, &wi);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let scattered = Ray {
+            origin: rec.point,
+            direction,
+            time: ray.time,
+            spread: ray.spread,
+        };
+        Some((self.eval_local(&wo, &wi) / pdf, scattered))
+    }
+
+    /// BSDF times the cosine at `wi`, for unit directions pointing away from the hit.
+    pub fn eval(&self, rec: &HitRecord, wo: &Vector3d, wi: &Vector3d) -> Color {
+        self.eval_local(&rec.world_to_local(wo), &rec.world_to_local(wi))
+    }
+
+    /// Solid angle density with which `scatter` picks `wi`.
+    pub fn pdf(&self, rec: &HitRecord, wo: &Vector3d, wi: &Vector3d) -> f64 {
+        self.pdf_local(&rec.world_to_local(wo), &rec.world_to_local(wi))
+    }
+
+    fn eval_local(&self, wo: &Vector3d, wi: &Vector3d) -> Color {
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return Color::black();
+        }
+        let h = (*wo + *wi).unit_vector();
+        let ggx = Ggx::from_roughness(self.roughness);
+        self.fresnel(wo.dot(&h)) * (ggx.d(&h) * ggx.g(wo, wi) / (4.0 * wo.z()))
+    }
+
+    fn pdf_local(&self, wo: &Vector3d, wi: &Vector3d) -> f64 {
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return 0.0;
+        }
+        let h = (*wo + *wi).unit_vector();
+        Ggx::from_roughness(self.roughness).pdf_visible(wo, &h) / (4.0 * wo.dot(&h))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::texture::solid;
+    use crate::Point3d;
+
+    fn incoming(theta: f64) -> Ray {
+        Ray {
+            origin: Point3d::new([-theta.sin(), theta.cos(), 0.0]),
+            direction: Vector3d::new([theta.sin(), -theta.cos(), 0.0]),
+            time: 0.0,
+            spread: 0.0,
+        }
+    }
+
+    fn record<'a>(ray: &Ray, material: &'a Material) -> HitRecord<'a> {
+        let up = Vector3d::new([0.0, 1.0, 0.0]);
+        HitRecord::new(
+            ray,
+            Point3d::new([0.0, 0.0, 0.0]),
+            up,
+            1.0,
+            (0.0, 0.0),
+            material,
+        )
+    }
+
+    #[test]
+    fn test_white_furnace() {
+        // A perfect mirror at the facet level loses energy only to masking.
+        let mirror = RoughConductor {
+            eta: Color::new([1.0, 1.0, 1.0]),
+            k: Color::new([1e4, 1e4, 1e4]),
+            roughness: 0.3,
+        };
+        let material = Material::Lambertian(solid(Color::white()));
+        let ray = incoming(0.6);
+        let rec = record(&ray, &material);
+        let n = 20000;
+        let total: f64 = (0..n)
+            .filter_map(|_| mirror.scatter(&ray, &rec))
+            .map(|(weight, _)| weight.x())
+            .sum();
+        let albedo = total / n as f64;
+        assert!(albedo > 0.95 && albedo <= 1.0 + 1e-9, "albedo {}", albedo);
+    }
+
+    #[test]
+    fn test_gold_is_yellow() {
+        let gold = RoughConductor::gold(0.1);
+        let f = gold.fresnel(1.0);
+        assert!(f.x() > f.z() && f.y() > f.z());
+        assert!(f.x() > 0.9);
+    }
+}
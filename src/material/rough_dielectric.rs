@@ -0,0 +1,204 @@
+use crate::geometry::HitRecord;
+use crate::material::microfacet::{fresnel_dielectric, reflect, refract, Ggx};
+use crate::math::random;
+use crate::{Color, Ray, Vector3d};
+
+/// Glass-like interface with GGX microfacets, reflecting or refracting in
+/// proportion to the Fresnel term.
+#[derive(Clone, Copy, Debug)]
+pub struct RoughDielectric {
+    /// Index of refraction inside the surface, with vacuum outside.
+    pub ior: f64,
+    /// Perceptual roughness in `[0, 1]`.
+    pub roughness: f64,
+}
+
+impl RoughDielectric {
+    /// Ratio of the index beyond the surface to the index on the ray's side.
+    fn eta(&self, rec: &HitRecord) -> f64 {
+        if rec.front_face {
+            self.ior
+        } else {
+            1.0 / self.ior
+        }
+    }
+
+    pub fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let wo = rec.world_to_local(&-ray.direction.unit_vector());
+        if wo.z() <= 0.0 {
+            return None;
+        }
+        let eta = self.eta(rec);
+        let h = Ggx::from_roughness(self.roughness).sample_visible(&wo);
+        let wi = if random::<f64>() < fresnel_dielectric(wo.dot(&h), eta) {
+            Some(reflect(&wo, &h)).filter(|wi| wi.z() > 0.0)
+        } else {
+            refract(&wo, &h, eta).filter(|wi| wi.z() < 0.0)
+        }?;
+        let direction = rec.local_to_world(&wi);
+        // Reflections must stay on the ray's side of the actual surface,
+        // refractions must cross it.
+        if wi.z() * direction.dot(&rec.normal) <= 0.0 {
+            return None;
+        }
+        let pdf = self.pdf_local(&wo, &wi, eta);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let scattered = Ray {
+            origin: rec.point,
+            direction,
+            time: ray.time,
+            spread: ray.spread,
+        };
+        Some((self.eval_local(&wo, &wi, eta) / pdf, scattered))
+    }
+
+    /// BSDF times the cosine at `wi`, for unit directions pointing away from the hit.
+    pub fn eval(&self, rec: &HitRecord, wo: &Vector3d, wi: &Vector3d) -> Color {
+        let (wo, wi) = (rec.world_to_local(wo), rec.world_to_local(wi));
+        self.eval_local(&wo, &wi, self.eta(rec))
+    }
+
+    /// Solid angle density with which `scatter` picks `wi`.
+    pub fn pdf(&self, rec: &HitRecord, wo: &Vector3d, wi: &Vector3d) -> f64 {
+        let (wo, wi) = (rec.world_to_local(wo), rec.world_to_local(wi));
+        self.pdf_local(&wo, &wi, self.eta(rec))
+    }
+
+    /// The micro normal turning `wo` into `wi`, with the Fresnel reflectance
+    /// there. `None` when the pair is impossible through any visible facet.
+    fn half_vector(&self, wo: &Vector3d, wi: &Vector3d, eta: f64) -> Option<(Vector3d, f64)> {
+        if wo.z() <= 0.0 || wi.z() == 0.0 {
+            return None;
+        }
+        let h = if wi.z() > 0.0 {
+            *wo + *wi
+        } else {
+            *wo + *wi * eta
+        };
+        if h.length_squared() == 0.0 {
+            return None;
+        }
+        let h = h.unit_vector();
+        let h = if h.z() < 0.0 { -h } else { h };
+        if wo.dot(&h) <= 0.0 || wi.dot(&h) * wi.z() <= 0.0 {
+            return None;
+        }
+        Some((h, fresnel_dielectric(wo.dot(&h), eta)))
+    }
+
+    fn eval_local(&self, wo: &Vector3d, wi: &Vector3d, eta: f64) -> Color {
+        let Some((h, f)) = self.half_vector(wo, wi, eta) else {
+            return Color::black();
+        };
+        let ggx = Ggx::from_roughness(self.roughness);
+        let dg = ggx.d(&h) * ggx.g(wo, wi);
+        let value = if wi.z() > 0.0 {
+            f * dg / (4.0 * wo.z())
+        } else {
+            let denom = (wi.dot(&h) + wo.dot(&h) / eta).powi(2);
+            // Radiance is compressed into the narrower cone on the denser side.
+            (1.0 - f) * dg * (wi.dot(&h) * wo.dot(&h)).abs() / (denom * wo.z() * eta * eta)
+        };
+        Color::new([value, value, value])
+    }
+
+    fn pdf_local(&self, wo: &Vector3d, wi: &Vector3d, eta: f64) -> f64 {
+        let Some((h, f)) = self.half_vector(wo, wi, eta) else {
+            return 0.0;
+        };
+        let visible = Ggx::from_roughness(self.roughness).pdf_visible(wo, &h);
+        if wi.z() > 0.0 {
+            visible / (4.0 * wo.dot(&h)) * f
+        } else {
+            let denom = (wi.dot(&h) + wo.dot(&h) / eta).powi(2);
+            visible * wi.dot(&h).abs() / denom * (1.0 - f)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::Point3d;
+    use std::f64::consts::PI;
+
+    /// Midpoints of an equal area grid over the unit sphere.
+    fn sphere_grid(n: usize) -> impl Iterator<Item = Vector3d> {
+        (0..n * n).map(move |i| {
+            let z = 2.0 * ((i / n) as f64 + 0.5) / n as f64 - 1.0;
+            let phi = 2.0 * PI * ((i % n) as f64 + 0.5) / n as f64;
+            let r = (1.0 - z * z).sqrt();
+            Vector3d::new([r * phi.cos(), r * phi.sin(), z])
+        })
+    }
+
+    /// The mean `scatter` weight must match the integral of `eval` over the
+    /// sphere, and `pdf` must integrate to the chance of producing a ray.
+    fn check_consistency(front_face: bool) {
+        let glass = RoughDielectric {
+            ior: 1.5,
+            roughness: 0.6,
+        };
+        let material = Material::RoughDielectric(glass);
+        let direction = Vector3d::new([0.5, -0.8, 0.2]).unit_vector();
+        let ray = Ray {
+            origin: Point3d::new([0.0, 0.0, 0.0]) - direction,
+            direction,
+            time: 0.0,
+            spread: 0.0,
+        };
+        let normal = Vector3d::new([0.0, if front_face { 1.0 } else { -1.0 }, 0.0]);
+        let rec = HitRecord::new(
+            &ray,
+            Point3d::new([0.0, 0.0, 0.0]),
+            normal,
+            1.0,
+            (0.0, 0.0),
+            &material,
+        );
+        assert_eq!(rec.front_face, front_face);
+
+        let wo = -direction;
+        let (mut integral, mut density) = (0.0, 0.0);
+        let grid = 800;
+        let cell = 4.0 * PI / (grid * grid) as f64;
+        for wi in sphere_grid(grid) {
+            integral += glass.eval(&rec, &wo, &wi).x() * cell;
+            density += glass.pdf(&rec, &wo, &wi) * cell;
+        }
+        let n = 100_000;
+        let (mut weight, mut produced) = (0.0, 0.0);
+        for _ in 0..n {
+            if let Some((w, _)) = glass.scatter(&ray, &rec) {
+                weight += w.x();
+                produced += 1.0;
+            }
+        }
+        let (weight, produced) = (weight / n as f64, produced / n as f64);
+        assert!(
+            (integral - weight).abs() < 0.02,
+            "{} vs {}",
+            integral,
+            weight
+        );
+        assert!(
+            (density - produced).abs() < 0.01,
+            "{} vs {}",
+            density,
+            produced
+        );
+    }
+
+    #[test]
+    fn test_consistent_entering() {
+        check_consistency(true);
+    }
+
+    #[test]
+    fn test_consistent_leaving() {
+        check_consistency(false);
+    }
+}
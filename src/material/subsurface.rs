@@ -0,0 +1,149 @@
+use crate::geometry::HitRecord;
+use crate::material::{PhaseFunction, RoughDielectric};
+use crate::math::{random_range, sample_exponential};
+use crate::{Color, Ray};
+
+/// Translucent material such as skin, wax or marble, rendered by a random
+/// walk through the interior of a closed object. Light refracts in through
+/// a dielectric interface, scatters inside the volume and leaves elsewhere.
+///
+/// A ray travelling inside the object next hits its back face,
+/// recognizable by `front_face` being false, and `scatter` then samples the
+/// interior up to that hit. Integrators follow these steps without spending
+/// bounces on them, up to `MAX_STEPS` per walk.
+#[derive(Clone, Copy, Debug)]
+pub struct Subsurface {
+    /// Overall color the surface appears to have, per channel.
+    pub albedo: Color,
+    /// Average distance light travels inside between interactions, per channel.
+    pub mean_free_path: Color,
+    pub ior: f64,
+    /// Perceptual roughness of the interface.
+    pub roughness: f64,
+    /// Henyey-Greenstein asymmetry of the interior scattering.
+    pub anisotropy: f64,
+}
+
+/// Single scattering albedo that makes a semi-infinite medium reflect
+/// `albedo` overall (Chiang et al., "Practical and Controllable Subsurface
+/// Scattering for Production Path Tracing").
+fn single_scattering_albedo(albedo: f64) -> f64 {
+    let a = albedo.clamp(0.0, 0.999);
+    let s = 4.09712 + 4.20863 * a - (9.59217 + 41.6808 * a + 17.7126 * a * a).sqrt();
+    1.0 - s * s
+}
+
+impl Subsurface {
+    /// Most interactions a walk has inside before it is given up.
+    pub const MAX_STEPS: usize = 256;
+
+    pub fn new(albedo: Color, mean_free_path: Color) -> Self {
+        Subsurface {
+            albedo,
+            mean_free_path,
+            ior: 1.4,
+            roughness: 0.3,
+            anisotropy: 0.0,
+        }
+    }
+
+    fn interface(&self) -> RoughDielectric {
+        RoughDielectric {
+            ior: self.ior,
+            roughness: self.roughness,
+        }
+    }
+
+    pub fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        if rec.front_face {
+            return self.interface().scatter(ray, rec);
+        }
+        // The ray travelled through the interior to reach `rec`. Distances
+        // follow one channel's extinction at random, weighted by the mean
+        // density over all channels so every channel stays unbiased.
+        let extinction: Color = (0..3).map(|i| 1.0 / self.mean_free_path[i]).collect();
+        let length = ray.direction.length();
+        let boundary = rec.t * length;
+        let distance = sample_exponential(extinction[random_range(0, 3)]);
+        let travelled = distance.min(boundary);
+        let transmittance: Color = (0..3).map(|i| (-extinction[i] * travelled).exp()).collect();
+        if distance >= boundary {
+            // Reaching the boundary has probability equal to the mean transmittance.
+            let pdf = (0..3).map(|i| transmittance[i]).sum::<f64>() / 3.0;
+            let (weight, scattered) = self.interface().scatter(ray, rec)?;
+            return Some((transmittance * weight / pdf, scattered));
+        }
+        let pdf = (0..3)
+            .map(|i| extinction[i] * transmittance[i])
+            .sum::<f64>()
+            / 3.0;
+        let scattering: Color = (0..3)
+            .map(|i| single_scattering_albedo(self.albedo[i]) * extinction[i])
+            .collect();
+        let phase = PhaseFunction::HenyeyGreenstein(self.anisotropy);
+        let scattered = Ray {
+            origin: ray.at(distance / length),
+            direction: phase.sample(&-ray.direction),
+            time: ray.time,
+            spread: ray.spread,
+        };
+        Some((scattering * transmittance / pdf, scattered))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{Hitable, Sphere};
+    use crate::material::Material;
+    use crate::{Point3d, Vector3d};
+
+    #[test]
+    fn test_albedo_inversion() {
+        assert!(single_scattering_albedo(0.0).abs() < 1e-3);
+        assert!(single_scattering_albedo(0.5) > 0.5);
+        assert!(single_scattering_albedo(0.999) <= 1.0);
+    }
+
+    /// Follows random walks through a sphere, as the renderer would, and
+    /// returns the mean weight of the paths leaving it.
+    fn escaping(albedo: f64) -> f64 {
+        let mut sss = Subsurface::new(Color::new([albedo; 3]), Color::new([0.2, 0.2, 0.2]));
+        sss.roughness = 0.0;
+        let sphere = Sphere {
+            center: Point3d::new([0.0, 0.0, 0.0]),
+            radius: 1.0,
+            material: Material::Subsurface(sss),
+        };
+        let n = 2000;
+        let mut total = 0.0;
+        for _ in 0..n {
+            let mut ray = Ray {
+                origin: Point3d::new([0.0, 0.0, 3.0]),
+                direction: Vector3d::new([0.0, 0.0, -1.0]),
+                time: 0.0,
+                spread: 0.0,
+            };
+            let mut weight = Color::white();
+            for _ in 0..10_000 {
+                let Some(rec) = sphere.hit(&ray) else {
+                    total += weight.x() + weight.y() + weight.z();
+                    break;
+                };
+                let Some((w, scattered)) = rec.material.scatter(&ray, &rec) else {
+                    break;
+                };
+                weight = weight * w;
+                ray = scattered;
+            }
+        }
+        total / (3 * n) as f64
+    }
+
+    #[test]
+    fn test_energy() {
+        // A medium that only scatters loses no light.
+        assert!((escaping(0.999) - 1.0).abs() < 0.05);
+        assert!(escaping(0.3) < 0.5);
+    }
+}
@@ -1,8 +1,40 @@
 use rand::distributions::uniform::{SampleRange, SampleUniform};
 use rand::distributions::{Distribution, Standard};
-use rand::{thread_rng, Rng};
+use rand::{thread_rng, Rng, RngCore};
+use std::any::Any;
+use std::cell::RefCell;
 use std::f64::consts::PI;
 
+thread_local! {
+    /// Stream `random` and `random_range` draw from on this thread in place
+    /// of `thread_rng`, if any.
+    static STREAM: RefCell<Option<Box<dyn RandomStream>>> = RefCell::new(None);
+}
+
+/// Source of random numbers that can stand in for `thread_rng`, for
+/// instance to replay or perturb the numbers a path was traced with.
+pub trait RandomStream: RngCore + Any {}
+
+impl<T: RngCore + Any> RandomStream for T {}
+
+/// Calls `f` with the random numbers on this thread drawn from `stream`,
+/// then returns the stream along with the result.
+pub fn with_stream<S: RandomStream, R>(stream: S, f: impl FnOnce() -> R) -> (S, R) {
+    let previous = STREAM.with(|cell| cell.replace(Some(Box::new(stream))));
+    let result = f();
+    let stream = STREAM.with(|cell| cell.replace(previous));
+    let stream = stream.and_then(|stream| (stream as Box<dyn Any>).downcast::<S>().ok());
+    (*stream.expect("random stream replaced while in use"), result)
+}
+
+/// Calls `f` on the stream installed by `with_stream`, or on `thread_rng`.
+fn draw<T>(f: impl FnOnce(&mut dyn RngCore) -> T) -> T {
+    STREAM.with(|cell| match cell.borrow_mut().as_mut() {
+        Some(stream) => f(stream.as_mut()),
+        None => f(&mut thread_rng()),
+    })
+}
+
 pub fn degree_to_radian(degree: f64) -> f64 {
     degree * PI / 180.0
 }
@@ -17,12 +49,18 @@ where
     T: SampleUniform,
     std::ops::Range<T>: SampleRange<T>,
 {
-    thread_rng().gen_range(min..max)
+    draw(|rng| rng.gen_range(min..max))
 }
 
 pub fn random<T>() -> T
 where
     Standard: Distribution<T>,
 {
-    thread_rng().gen()
+    draw(|rng| rng.gen())
+}
+
+/// Free flight distance through a medium of extinction coefficient `rate`,
+/// exponentially distributed with mean `1 / rate`.
+pub fn sample_exponential(rate: f64) -> f64 {
+    -(1.0 - random::<f64>()).ln() / rate
 }
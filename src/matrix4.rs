@@ -0,0 +1,113 @@
+use crate::vector3::{Point3d, Vector3d};
+use std::ops::{Index, IndexMut, Mul};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Matrix4 {
+    pub data: [[f64; 4]; 4],
+}
+
+impl Matrix4 {
+    pub fn new(data: [[f64; 4]; 4]) -> Self {
+        Matrix4 { data }
+    }
+
+    pub fn identity() -> Self {
+        let mut data = [[0.0; 4]; 4];
+        for (i, row) in data.iter_mut().enumerate() {
+            row[i] = 1.0;
+        }
+        Matrix4 { data }
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut data = [[0.0; 4]; 4];
+        for (i, row) in data.iter_mut().enumerate() {
+            for (j, item) in row.iter_mut().enumerate() {
+                *item = self[j][i];
+            }
+        }
+        Matrix4 { data }
+    }
+
+    /// Gauss-Jordan elimination with partial pivoting, `None` if singular.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.data;
+        let mut inv = Matrix4::identity().data;
+        for col in 0..4 {
+            let pivot = (col..4)
+                .max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))
+                .unwrap();
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+            let p = a[col][col];
+            for j in 0..4 {
+                a[col][j] /= p;
+                inv[col][j] /= p;
+            }
+            for row in 0..4 {
+                if row == col {
+                    continue;
+                }
+                let f = a[row][col];
+                for j in 0..4 {
+                    a[row][j] -= f * a[col][j];
+                    inv[row][j] -= f * inv[col][j];
+                }
+            }
+        }
+        Some(Matrix4 { data: inv })
+    }
+
+    /// Treats `point` as `(x, y, z, 1)`, dividing through by `w` when it is not one.
+    pub fn transform_point(&self, point: &Point3d) -> Point3d {
+        let m = &self.data;
+        let p = point.data;
+        let row = |r: &[f64; 4]| r[0] * p[0] + r[1] * p[1] + r[2] * p[2] + r[3];
+        let result = Point3d::new([row(&m[0]), row(&m[1]), row(&m[2])]);
+        let w = row(&m[3]);
+        if w == 1.0 {
+            result
+        } else {
+            result / w
+        }
+    }
+
+    /// Treats `vector` as `(x, y, z, 0)`, so translation is ignored.
+    pub fn transform_vector(&self, vector: &Vector3d) -> Vector3d {
+        let m = &self.data;
+        let v = vector.data;
+        let row = |r: &[f64; 4]| r[0] * v[0] + r[1] * v[1] + r[2] * v[2];
+        Vector3d::new([row(&m[0]), row(&m[1]), row(&m[2])])
+    }
+}
+
+impl Index<usize> for Matrix4 {
+    type Output = [f64; 4];
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.data[index]
+    }
+}
+
+impl IndexMut<usize> for Matrix4 {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.data[index]
+    }
+}
+
+impl Mul for Matrix4 {
+    type Output = Matrix4;
+
+    fn mul(self, other: Matrix4) -> Self::Output {
+        let mut data = [[0.0; 4]; 4];
+        for (i, row) in data.iter_mut().enumerate() {
+            for (j, item) in row.iter_mut().enumerate() {
+                *item = (0..4).map(|k| self[i][k] * other[k][j]).sum();
+            }
+        }
+        Matrix4 { data }
+    }
+}
@@ -0,0 +1,190 @@
+use crate::{Point3d, Vector3d};
+
+/// The twelve cube edge directions of improved Perlin noise.
+const GRADIENTS: [[f64; 3]; 12] = [
+    [1.0, 1.0, 0.0],
+    [-1.0, 1.0, 0.0],
+    [1.0, -1.0, 0.0],
+    [-1.0, -1.0, 0.0],
+    [1.0, 0.0, 1.0],
+    [-1.0, 0.0, 1.0],
+    [1.0, 0.0, -1.0],
+    [-1.0, 0.0, -1.0],
+    [0.0, 1.0, 1.0],
+    [0.0, -1.0, 1.0],
+    [0.0, 1.0, -1.0],
+    [0.0, -1.0, -1.0],
+];
+
+/// Deterministic lattice noise. Two generators with the same seed produce
+/// the same values, so procedural textures are stable across renders.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Noise {
+    pub seed: u64,
+}
+
+/// Result of a cellular noise lookup.
+#[derive(Clone, Copy, Debug)]
+pub struct Worley {
+    /// Distance to the nearest feature point.
+    pub f1: f64,
+    /// Distance to the second nearest feature point.
+    pub f2: f64,
+    /// Hash of the cell owning the nearest feature point, for per-cell variation.
+    pub id: u64,
+}
+
+impl Noise {
+    pub fn new(seed: u64) -> Self {
+        Noise { seed }
+    }
+
+    /// Mixes lattice coordinates with the seed (splitmix64 finalizer).
+    fn hash(&self, x: i64, y: i64, z: i64) -> u64 {
+        let mut h = self.seed
+            ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+            ^ (z as u64).wrapping_mul(0x1656_67B1_9E37_79F9);
+        h ^= h >> 30;
+        h = h.wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        h ^= h >> 27;
+        h = h.wrapping_mul(0x94D0_49BB_1331_11EB);
+        h ^ (h >> 31)
+    }
+
+    /// Gradient noise, roughly in `[-1, 1]` and zero on every lattice point.
+    pub fn perlin(&self, p: &Point3d) -> f64 {
+        let cell = p.data.map(f64::floor);
+        let f = [p.x() - cell[0], p.y() - cell[1], p.z() - cell[2]];
+        let fade = f.map(|t| t * t * t * (t * (t * 6.0 - 15.0) + 10.0));
+        let [i, j, k] = cell.map(|c| c as i64);
+        (0..8)
+            .map(|corner| {
+                let d = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+                let g = GRADIENTS[(self.hash(i + d[0], j + d[1], k + d[2]) % 12) as usize];
+                let mut weight = 1.0;
+                let mut dot = 0.0;
+                for axis in 0..3 {
+                    let offset = f[axis] - d[axis] as f64;
+                    dot += g[axis] * offset;
+                    weight *= if d[axis] == 1 {
+                        fade[axis]
+                    } else {
+                        1.0 - fade[axis]
+                    };
+                }
+                weight * dot
+            })
+            .sum()
+    }
+
+    /// Fractal Brownian motion: `octaves` layers of noise, each at twice the
+    /// frequency and half the amplitude of the last.
+    pub fn fbm(&self, p: &Point3d, octaves: usize) -> f64 {
+        let mut sum = 0.0;
+        let mut frequency = 1.0;
+        let mut amplitude = 1.0;
+        for _ in 0..octaves {
+            sum += amplitude * self.perlin(&(*p * frequency));
+            frequency *= 2.0;
+            amplitude *= 0.5;
+        }
+        sum
+    }
+
+    /// Like `fbm`, but sums absolute values, giving creases where noise crosses zero.
+    pub fn turbulence(&self, p: &Point3d, octaves: usize) -> f64 {
+        let mut sum = 0.0;
+        let mut frequency = 1.0;
+        let mut amplitude = 1.0;
+        for _ in 0..octaves {
+            sum += amplitude * self.perlin(&(*p * frequency)).abs();
+            frequency *= 2.0;
+            amplitude *= 0.5;
+        }
+        sum
+    }
+
+    /// Cellular noise with one feature point jittered inside each unit cell.
+    pub fn worley(&self, p: &Point3d) -> Worley {
+        let [i, j, k] = p.data.map(|a| a.floor() as i64);
+        let mut result = Worley {
+            f1: f64::INFINITY,
+            f2: f64::INFINITY,
+            id: 0,
+        };
+        for dz in -1..=1 {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let (x, y, z) = (i + dx, j + dy, k + dz);
+                    let id = self.hash(x, y, z);
+                    // Three 21 bit fields of the hash place the point in the cell.
+                    let jitter = |shift: u32| ((id >> shift) & 0x1F_FFFF) as f64 / 0x20_0000 as f64;
+                    let feature = Vector3d::new([
+                        x as f64 + jitter(0),
+                        y as f64 + jitter(21),
+                        z as f64 + jitter(42),
+                    ]);
+                    let distance = (feature - *p).length();
+                    if distance < result.f1 {
+                        result.f2 = result.f1;
+                        result.f1 = distance;
+                        result.id = id;
+                    } else if distance < result.f2 {
+                        result.f2 = distance;
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points() -> impl Iterator<Item = Point3d> {
+        (0..200).map(|i| {
+            let t = i as f64 * 0.173;
+            Point3d::new([t.sin() * 7.3, t * 0.61, (t * 1.7).cos() * 3.1])
+        })
+    }
+
+    #[test]
+    fn test_perlin_lattice_and_range() {
+        let noise = Noise::new(7);
+        assert_eq!(noise.perlin(&Point3d::new([3.0, -2.0, 5.0])), 0.0);
+        for p in points() {
+            let n = noise.perlin(&p);
+            assert!(n.abs() <= 1.1, "{} out of range at {}", n, p);
+        }
+    }
+
+    #[test]
+    fn test_seeded() {
+        let p = Point3d::new([0.3, 1.7, -2.2]);
+        assert_eq!(Noise::new(1).fbm(&p, 4), Noise::new(1).fbm(&p, 4));
+        assert_ne!(Noise::new(1).perlin(&p), Noise::new(2).perlin(&p));
+    }
+
+    #[test]
+    fn test_turbulence_non_negative() {
+        let noise = Noise::new(3);
+        assert!(points().all(|p| noise.turbulence(&p, 5) >= 0.0));
+    }
+
+    #[test]
+    fn test_worley() {
+        let noise = Noise::new(11);
+        for p in points() {
+            let w = noise.worley(&p);
+            assert!(w.f1 <= w.f2);
+            // Some feature point always lies within a cell diagonal.
+            assert!(w.f1 <= 3.0_f64.sqrt());
+        }
+        let w = noise.worley(&Point3d::new([0.5, 0.5, 0.5]));
+        let near = noise.worley(&Point3d::new([0.5, 0.5, 0.5 + 1e-6]));
+        assert_eq!(w.id, near.id);
+    }
+}
@@ -0,0 +1,131 @@
+use crate::{Color, Point3d, Vector3d};
+
+/// A packet of light stored where it landed on a surface.
+#[derive(Clone, Copy, Debug)]
+pub struct Photon {
+    pub position: Point3d,
+    /// Unit vector pointing back the way the photon came.
+    pub direction: Vector3d,
+    pub power: Color,
+}
+
+/// Photons in a kd-tree for finding those near a point. The tree is
+/// implicit: each range of `photons` has its splitting photon at the middle,
+/// with the lower half before it and the upper half after.
+pub struct PhotonMap {
+    photons: Vec<Photon>,
+    /// Axis each photon splits its range along.
+    axes: Vec<usize>,
+}
+
+/// Orders `photons` into the tree layout, splitting along the widest axis.
+fn build(photons: &mut [Photon], axes: &mut [usize]) {
+    if photons.len() <= 1 {
+        return;
+    }
+    let mut lower = [f64::INFINITY; 3];
+    let mut upper = [f64::NEG_INFINITY; 3];
+    for photon in photons.iter() {
+        for axis in 0..3 {
+            lower[axis] = lower[axis].min(photon.position[axis]);
+            upper[axis] = upper[axis].max(photon.position[axis]);
+        }
+    }
+    let axis = (0..3)
+        .max_by(|&a, &b| (upper[a] - lower[a]).total_cmp(&(upper[b] - lower[b])))
+        .unwrap_or(0);
+    let middle = photons.len() / 2;
+    photons.select_nth_unstable_by(middle, |a, b| a.position[axis].total_cmp(&b.position[axis]));
+    axes[middle] = axis;
+    let (photons_below, photons_above) = photons.split_at_mut(middle);
+    let (axes_below, axes_above) = axes.split_at_mut(middle);
+    build(photons_below, axes_below);
+    build(&mut photons_above[1..], &mut axes_above[1..]);
+}
+
+impl PhotonMap {
+    pub fn new(mut photons: Vec<Photon>) -> Self {
+        let mut axes = vec![0; photons.len()];
+        build(&mut photons, &mut axes);
+        PhotonMap { photons, axes }
+    }
+
+    pub fn len(&self) -> usize {
+        self.photons.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.photons.is_empty()
+    }
+
+    /// Calls `f` on every photon within `radius` of `point`.
+    pub fn for_each_near(&self, point: Point3d, radius: f64, mut f: impl FnMut(&Photon)) {
+        self.search(0, self.photons.len(), point, radius * radius, &mut f);
+    }
+
+    fn search(
+        &self,
+        start: usize,
+        end: usize,
+        point: Point3d,
+        radius2: f64,
+        f: &mut impl FnMut(&Photon),
+    ) {
+        if start >= end {
+            return;
+        }
+        let middle = start + (end - start) / 2;
+        let photon = &self.photons[middle];
+        if (photon.position - point).length_squared() <= radius2 {
+            f(photon);
+        }
+        if end - start == 1 {
+            return;
+        }
+        let offset = point[self.axes[middle]] - photon.position[self.axes[middle]];
+        let (near, far) = if offset < 0.0 {
+            ((start, middle), (middle + 1, end))
+        } else {
+            ((middle + 1, end), (start, middle))
+        };
+        self.search(near.0, near.1, point, radius2, f);
+        if offset * offset <= radius2 {
+            self.search(far.0, far.1, point, radius2, f);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::random;
+
+    fn photon(position: Point3d) -> Photon {
+        Photon {
+            position,
+            direction: Vector3d::new([0.0, 1.0, 0.0]),
+            power: Color::white(),
+        }
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let positions: Vec<Point3d> = (0..2000)
+            .map(|_| Point3d::new([random(), random::<f64>() * 0.1, random()]))
+            .collect();
+        let map = PhotonMap::new(positions.iter().map(|&p| photon(p)).collect());
+        assert_eq!(map.len(), positions.len());
+        for _ in 0..50 {
+            let point = Point3d::new([random(), 0.05, random()]);
+            let radius = 0.1 * random::<f64>();
+            let mut found = Vec::new();
+            map.for_each_near(point, radius, |photon| found.push(photon.position));
+            let expected = positions
+                .iter()
+                .filter(|p| (**p - point).length() <= radius)
+                .count();
+            assert_eq!(found.len(), expected);
+            assert!(found.iter().all(|p| (*p - point).length() <= radius));
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use crate::math::degree_to_radian;
+use crate::matrix4::Matrix4;
+use crate::Vector3d;
+
+/// A unit quaternion representing a rotation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quaternion {
+    pub w: f64,
+    pub v: Vector3d,
+}
+
+impl Quaternion {
+    pub fn identity() -> Self {
+        Quaternion {
+            w: 1.0,
+            v: Vector3d::new([0.0, 0.0, 0.0]),
+        }
+    }
+
+    pub fn from_axis_angle(axis: Vector3d, degree: f64) -> Self {
+        let half = degree_to_radian(degree) / 2.0;
+        Quaternion {
+            w: half.cos(),
+            v: axis.unit_vector() * half.sin(),
+        }
+    }
+
+    pub fn dot(&self, other: &Quaternion) -> f64 {
+        self.w * other.w + self.v.dot(&other.v)
+    }
+
+    fn normalized(&self) -> Self {
+        let length = self.dot(self).sqrt();
+        Quaternion {
+            w: self.w / length,
+            v: self.v / length,
+        }
+    }
+
+    /// Spherical linear interpolation along the shorter arc.
+    pub fn slerp(&self, other: &Quaternion, t: f64) -> Self {
+        let mut cos_theta = self.dot(other);
+        let mut other = *other;
+        if cos_theta < 0.0 {
+            other = Quaternion {
+                w: -other.w,
+                v: -other.v,
+            };
+            cos_theta = -cos_theta;
+        }
+        if cos_theta > 0.9995 {
+            // Nearly parallel, fall back to normalized lerp.
+            let q = Quaternion {
+                w: self.w + (other.w - self.w) * t,
+                v: self.v + (other.v - self.v) * t,
+            };
+            return q.normalized();
+        }
+        let theta = cos_theta.acos();
+        let a = ((1.0 - t) * theta).sin() / theta.sin();
+        let b = (t * theta).sin() / theta.sin();
+        Quaternion {
+            w: self.w * a + other.w * b,
+            v: self.v * a + other.v * b,
+        }
+    }
+
+    pub fn to_matrix(self) -> Matrix4 {
+        let (w, x, y, z) = (self.w, self.v.x(), self.v.y(), self.v.z());
+        Matrix4::new([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+                0.0,
+            ],
+            [
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+                0.0,
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+}
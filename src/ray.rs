@@ -4,6 +4,10 @@ use crate::vector3::{Point3d, Vector3d};
 pub struct Ray {
     pub origin: Point3d,
     pub direction: Vector3d,
+    pub time: f64,
+    /// Angle in radians by which the ray's footprint widens per unit of
+    /// `t * |direction|`, used to pick texture detail. Zero for an ideal ray.
+    pub spread: f64,
 }
 
 impl Ray {
@@ -1,66 +1,202 @@
-use crate::geometry::Hitable;
+use crate::aov::{AovSample, Aovs, FirstHit, Pass};
+use crate::denoise::Denoiser;
+use crate::film::{Film, PixelFilter};
+use crate::integrator::{Integrator, Splat};
 use crate::image::Image;
 use crate::progress;
 use crate::random;
-use crate::Color;
-use crate::Pixel;
 use crate::Ray;
 use crate::Scene;
-use crate::Vector3d;
-
-fn ray_color(ray: &Ray, scene: &Scene, depth: usize) -> Color {
-    if depth == 0 {
-        return Color::new([0.0, 0.0, 0.0]);
-    }
-    if let Some(rec) = scene.objects.hit(ray) {
-        let target = rec.point + Vector3d::random_in_hemisphere(rec.normal);
-        return (ray_color(
-            &Ray {
-                origin: rec.point,
-                direction: target - rec.point,
-            },
-            scene, depth - 1
-        )) * 0.5;
-    }
-    let unit_direction = ray.direction.unit_vector();
-    let t = 0.5 * (unit_direction.y() + 1.0);
-    return Color::new([1.0, 1.0, 1.0]) * (1.0 - t) + Color::new([0.5, 0.7, 1.0]) * t;
-}
 
 pub struct RenderOption {
     pub samples_per_pixel: i32,
     pub max_depth: usize,
+    /// Bounces after which paths are randomly ended in proportion to how
+    /// little they can still contribute.
+    pub roulette_depth: usize,
+    /// Largest channel value one sample may receive from indirect light,
+    /// suppressing fireflies at the cost of some energy.
+    pub indirect_clamp: Option<f64>,
+    /// Weighs samples into the pixels around them.
+    pub filter: PixelFilter,
+    /// Filters the noise out of the finished image, using the passes.
+    pub denoiser: Option<Denoiser>,
 }
 
 pub struct Renderer<'a> {
     pub scene: &'a Scene,
     pub image: &'a mut Image,
     pub option: RenderOption,
+    pub integrator: Box<dyn Integrator>,
+    /// Passes to fill in alongside the image, if wanted.
+    pub aovs: Option<Aovs>,
 }
 
 impl<'a> Renderer<'a> {
-    fn render_pixel(&self, x: usize, y: usize) -> Pixel {
+    /// Traces a camera ray, finding what it first hits when passes are wanted.
+    /// Also returns the splats the integrator made, by viewport position.
+    fn sample(&self, ray: &Ray) -> (AovSample, Vec<Splat>) {
+        let (mut radiance, splats) =
+            self.integrator
+                .radiance_with_splats(ray, self.scene, &self.option);
+        if let Some(limit) = self.option.indirect_clamp {
+            radiance.clamp_indirect(limit);
+        }
+        let hit = self.aovs.as_ref().and_then(|aovs| {
+            let (object, rec) = self.scene.objects.hit_object(ray)?;
+            Some(FirstHit {
+                depth: rec.t * ray.direction.length(),
+                normal: rec.shading_normal,
+                albedo: rec.material.albedo(&rec),
+                position: rec.point,
+                uv: (rec.u, rec.v),
+                object,
+                material: aovs.material_id(rec.material),
+            })
+        });
+        let sample = AovSample {
+            emission: radiance.emitted,
+            direct: radiance.direct,
+            indirect: radiance.indirect,
+            hit,
+        };
+        (sample, splats)
+    }
+
+    /// Samples the pixel in column `x` and row `y` from the top, returning
+    /// each sample with its position on the film. Splats made along the way
+    /// are added to `splats` by position on the film.
+    fn render_pixel(&self, x: usize, y: usize, splats: &mut Vec<Splat>) -> Vec<((f64, f64), AovSample)> {
         let width = self.image.width;
         let height = self.image.height;
-        let samples = self.option.samples_per_pixel;
         let camera = &self.scene.camera;
-        let r = || -> f64 { random() };
-        let u = || (x as f64 + r()) / (width - 1) as f64;
-        let v = || (y as f64 + r()) / (height - 1) as f64;
-        let color: Color = (0..samples)
-            .map(|_| camera.get_ray(u(), v()))
-            .map(|r| ray_color(&r, &self.scene, self.option.max_depth))
-            .sum();
-        return color.to_pixel(samples);
+        let spread = camera.pixel_spread(width);
+        (0..self.option.samples_per_pixel)
+            .map(|_| {
+                let (fx, fy) = (x as f64 + random::<f64>(), y as f64 + random::<f64>());
+                let (u, v) = (fx / width as f64, 1.0 - fy / height as f64);
+                let ray = Ray { spread, ..camera.get_ray(u, v) };
+                let (sample, sample_splats) = self.sample(&ray);
+                splats.extend(sample_splats.into_iter().map(|((u, v), color)| {
+                    ((u * width as f64, (1.0 - v) * height as f64), color)
+                }));
+                ((fx, fy), sample)
+            })
+            .collect()
     }
 
     pub fn render(&mut self) {
-        let height = self.image.height;
+        let (width, height) = (self.image.width, self.image.height);
+        if self.option.denoiser.is_some() && self.aovs.is_none() {
+            self.aovs = Some(Aovs::new(width, height));
+        }
+        if let Some(aovs) = &mut self.aovs {
+            aovs.number_materials(self.scene);
+        }
+        self.integrator.prepare(self.scene, &self.option);
+        let data = self.integrator.shows_data();
+        // A box filter of a pixel's width averages each pixel's own samples.
+        let filter = if data { PixelFilter::default() } else { self.option.filter };
+        let mut film = Film::new(width, height, filter);
+        // Splats are weighted as if their sample were the only one over the
+        // viewport, which spans the whole film.
+        let samples = width * height * self.option.samples_per_pixel as usize;
+        film.splat_scale = (width * height) as f64 / samples as f64;
         for y in 0..height {
             let percentage = (y as f32 / height as f32) * 100.0;
             progress::show(percentage);
-            for x in 0..self.image.width {
-                self.image.canvas[y][x] = self.render_pixel(x, height - 1 - y);
+            for x in 0..width {
+                let mut splats = Vec::new();
+                let samples = self.render_pixel(x, y, &mut splats);
+                for ((fx, fy), sample) in &samples {
+                    film.add_sample(*fx, *fy, sample.beauty());
+                }
+                for ((fx, fy), color) in splats {
+                    film.add_splat(fx, fy, color);
+                }
+                if let Some(aovs) = &mut self.aovs {
+                    let samples: Vec<AovSample> = samples.iter().map(|(_, s)| *s).collect();
+                    aovs.set_pixel(x, y, &samples);
+                }
+            }
+        }
+        self.integrator.finish();
+        // The beauty pass and the denoiser take the filtered image with its
+        // splats, which integrators such as Metropolis light transport give
+        // all their light in, so they see what would have been written.
+        let resolved = film.resolve();
+        if let Some(aovs) = &mut self.aovs {
+            *aovs.get_mut(Pass::Beauty) = resolved.clone();
+        }
+        let image = match (&self.option.denoiser, &self.aovs) {
+            (Some(denoiser), Some(aovs)) if !data => denoiser.denoise(&resolved, aovs),
+            _ => resolved,
+        };
+        for (y, row) in self.image.canvas.iter_mut().enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let color = image.get(x, y);
+                *pixel = if data { color.to_linear_pixel() } else { color.to_pixel(1) };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::geometry::HitableList;
+    use crate::integrator::Radiance;
+    use crate::Color;
+
+    /// Sends all light back to where its camera ray left the viewport.
+    struct Splatter;
+
+    impl Integrator for Splatter {
+        fn radiance(&self, _ray: &Ray, _scene: &Scene, _option: &RenderOption) -> Radiance {
+            Radiance::black()
+        }
+
+        fn radiance_with_splats(
+            &self,
+            ray: &Ray,
+            scene: &Scene,
+            _option: &RenderOption,
+        ) -> (Radiance, Vec<Splat>) {
+            let (u, v, _) = scene.camera.project(ray.origin + ray.direction).unwrap();
+            (Radiance::black(), vec![((u, v), Color::new([0.25; 3]))])
+        }
+    }
+
+    #[test]
+    fn test_splats_cover_every_pixel() {
+        let scene = Scene {
+            objects: HitableList { hitables: vec![] },
+            camera: Camera::new(1.0, 2.0, 1.0),
+            fog: None,
+        };
+        let mut image = Image::new("splats.ppm", 8, 4);
+        let mut renderer = Renderer {
+            scene: &scene,
+            image: &mut image,
+            option: RenderOption {
+                samples_per_pixel: 16,
+                max_depth: 1,
+                roulette_depth: usize::MAX,
+                indirect_clamp: None,
+                filter: PixelFilter::default(),
+                denoiser: None,
+            },
+            integrator: Box::new(Splatter),
+            aovs: None,
+        };
+        renderer.render();
+        // Each pixel gets back exactly the light of its own samples,
+        // edges included.
+        let expected = Color::new([0.25; 3]).to_pixel(1);
+        for row in &image.canvas {
+            for pixel in row {
+                assert_eq!(*pixel, expected);
             }
         }
     }
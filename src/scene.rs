@@ -1,26 +1,97 @@
 use crate::camera::Camera;
 use crate::geometry::*;
-use crate::Point3d;
-use crate::material::Material;
+use crate::quaternion::Quaternion;
+use crate::transform::{AnimatedTransform, Keyframe, Transform};
+use crate::{Point3d, Ray, Vector3d};
+use crate::material::{BumpMap, Coated, Material, PhaseFunction, Principled, RoughConductor, RoughDielectric, Subsurface};
+use crate::float_image::FloatImage;
+use crate::fog::Fog;
+use crate::noise::Noise;
+use crate::voxel_grid::VoxelGrid;
+use crate::texture::{
+    solid, Checker3d, Texture, CheckerUv, Filter, Gradient, GradientAxis, ImageTexture, Marble, Stone, WrapMode, Wood,
+};
 use crate::Color;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+fn gray() -> Material {
+    Material::Lambertian(solid(Color::new([0.5, 0.5, 0.5])))
+}
 
 pub struct Scene {
     pub objects: HitableList,
     pub camera: Camera,
+    pub fog: Option<Fog>,
 }
 
 impl Scene {
+    /// Light arriving from the sky along a ray that hits nothing.
+    pub fn background(&self, ray: &Ray) -> Color {
+        let unit_direction = ray.direction.unit_vector();
+        let t = 0.5 * (unit_direction.y() + 1.0);
+        Color::new([1.0, 1.0, 1.0]) * (1.0 - t) + Color::new([0.5, 0.7, 1.0]) * t
+    }
+
+    /// Share of light passing along `ray` up to `t_max` through the media
+    /// among the objects and the fog; zero when a surface is in the way.
+    pub fn transmittance(&self, ray: &Ray, t_max: f64) -> f64 {
+        let fog = self.fog.map_or(1.0, |fog| fog.transmittance(ray, t_max));
+        fog * self.objects.transmittance(ray, t_max)
+    }
+
+    /// Numbers the materials of the objects from zero in the order the
+    /// objects list them, by address. Copies of a material share its number,
+    /// so the numbers stay the same from run to run.
+    pub fn material_ids(&self) -> HashMap<usize, usize> {
+        let mut ids = HashMap::new();
+        let mut numbers: HashMap<String, usize> = HashMap::new();
+        for material in self.objects.materials() {
+            let address = material as *const Material as usize;
+            if ids.contains_key(&address) {
+                continue;
+            }
+            let next = numbers.len();
+            let id = *numbers.entry(format!("{:?}", material)).or_insert(next);
+            ids.insert(address, id);
+        }
+        ids
+    }
+
+    /// Indices in `objects` of the objects that can be sampled as lights.
+    /// Integrators find them once, in `Integrator::prepare`.
+    pub fn lights(&self) -> Vec<usize> {
+        (0..self.objects.hitables.len())
+            .filter(|&i| self.objects.hitables[i].is_light())
+            .collect()
+    }
+
     pub fn sample() -> Self {
-        let mut world: Vec<Box<dyn Hitable + Sync>> = Vec::new();
+        let mut world: Vec<Box<dyn Hitable>> = Vec::new();
+        let stripes = CheckerUv {
+            even: solid(Color::new([0.8, 0.3, 0.2])),
+            odd: Arc::new(Gradient {
+                from: Color::new([0.9, 0.9, 0.9]),
+                to: Color::new([0.2, 0.3, 0.8]),
+                axis: GradientAxis::V,
+            }),
+            frequency: (8.0, 1.0),
+        };
+        let ground = Checker3d {
+            even: solid(Color::new([0.2, 0.3, 0.1])),
+            odd: solid(Color::new([0.9, 0.9, 0.9])),
+            scale: 2.0,
+        };
         let sphere0: Sphere = Sphere {
             center: Point3d::new([0.0, 0.0, -1.0]),
             radius: 0.5,
-            material: Material::Lambertian(Color::white())
+            material: Material::Lambertian(Arc::new(stripes))
         };
         let sphere1 = Sphere {
             center: Point3d::new([0.0, -100.5, -1.0]),
             radius: 100.0,
-            material: Material::Lambertian(Color::white())
+            material: Material::Lambertian(Arc::new(ground))
         };
         world.push(Box::new(sphere0));
         world.push(Box::new(sphere1));
@@ -29,9 +100,690 @@ impl Scene {
         let viewport_width = 3.5;
         let focal_length = 1.0;
 
-        return Scene {
+        Scene {
+            objects: HitableList { hitables: world },
+            camera: Camera::new(aspect_ratio, viewport_width, focal_length),
+            fog: None,
+        }
+    }
+
+    /// A hollowed sphere with a window cut into it next to a lens-shaped intersection.
+    pub fn csg() -> Self {
+        let sphere = |center: [f64; 3], radius: f64| -> Box<dyn Hitable> {
+            Box::new(Sphere {
+                center: Point3d::new(center),
+                radius,
+                material: gray(),
+            })
+        };
+        let shell = Csg::difference(sphere([-0.6, 0.0, -1.5], 0.5), sphere([-0.6, 0.0, -1.5], 0.45));
+        let window = Csg::difference(Box::new(shell), sphere([-0.6, 0.1, -1.0], 0.25));
+        let lens = Csg::intersection(sphere([0.45, 0.0, -1.5], 0.5), sphere([0.75, 0.0, -1.5], 0.5));
+
+        let world: Vec<Box<dyn Hitable>> = vec![
+            Box::new(window),
+            Box::new(lens),
+            sphere([0.0, -100.5, -1.0], 100.0),
+        ];
+
+        let aspect_ratio = 16.0 / 9.0;
+        let viewport_width = 3.5;
+        let focal_length = 1.0;
+
+        Scene {
+            objects: HitableList { hitables: world },
+            camera: Camera::new(aspect_ratio, viewport_width, focal_length),
+            fog: None,
+        }
+    }
+
+    /// Organic shapes only a distance field can express: merged blobs,
+    /// a twisted bar and an endless row of rings.
+    pub fn sdf() -> Self {
+        let material = gray();
+        let at = |x: f64, y: f64, z: f64, node: SdfNode| SdfNode::Translate {
+            offset: Vector3d::new([x, y, z]),
+            node: Box::new(node),
+        };
+        let blobs = SdfNode::SmoothUnion {
+            a: Box::new(at(-0.15, 0.0, 0.0, SdfNode::Sphere { radius: 0.25 })),
+            b: Box::new(SdfNode::Capsule {
+                a: Point3d::new([0.0, -0.2, 0.0]),
+                b: Point3d::new([0.2, 0.25, 0.0]),
+                radius: 0.12,
+            }),
+            k: 0.2,
+        };
+        let bar = SdfNode::Twist {
+            rate: 3.0,
+            node: Box::new(SdfNode::Box {
+                half_extents: Vector3d::new([0.12, 0.4, 0.12]),
+            }),
+        };
+        let rings = SdfNode::Repeat {
+            period: Vector3d::new([0.0, 0.0, 0.8]),
+            node: Box::new(SdfNode::Torus {
+                major: 0.2,
+                minor: 0.05,
+            }),
+        };
+        let mut rings = Sdf::new(at(1.2, -0.25, 0.0, rings), material.clone());
+        rings.max_distance = 20.0;
+
+        let world: Vec<Box<dyn Hitable>> = vec![
+            Box::new(Sdf::new(at(-0.7, 0.0, -1.5, blobs), material.clone())),
+            Box::new(Sdf::new(at(0.2, -0.1, -1.5, bar), material.clone())),
+            Box::new(rings),
+            Box::new(Sphere {
+                center: Point3d::new([0.0, -100.5, -1.0]),
+                radius: 100.0,
+                material,
+            }),
+        ];
+
+        let aspect_ratio = 16.0 / 9.0;
+        let viewport_width = 3.5;
+        let focal_length = 1.0;
+
+        Scene {
+            objects: HitableList { hitables: world },
+            camera: Camera::new(aspect_ratio, viewport_width, focal_length),
+            fog: None,
+        }
+    }
+
+    /// Spheres streaking sideways and a tumbling instance, seen through an open shutter.
+    pub fn motion_blur() -> Self {
+        let mut world: Vec<Box<dyn Hitable>> = Vec::new();
+        for i in 0..3 {
+            let x = i as f64 - 1.0;
+            world.push(Box::new(MovingSphere {
+                center0: Point3d::new([x, 0.0, -1.5]),
+                center1: Point3d::new([x + 0.1 * (i + 1) as f64, 0.1, -1.5]),
+                time0: 0.0,
+                time1: 1.0,
+                radius: 0.25,
+                material: gray(),
+            }));
+        }
+        let disc: Arc<dyn Hitable> = Arc::new(Sphere {
+            center: Point3d::new([0.0, 0.0, 0.0]),
+            radius: 1.0,
+            material: gray(),
+        });
+        let y = Vector3d::new([0.0, 1.0, 0.0]);
+        let scale = Vector3d::new([0.4, 0.1, 0.2]);
+        let animation = AnimatedTransform::new(vec![
+            Keyframe::new(0.0, Vector3d::new([0.0, 0.6, -1.5]), Quaternion::identity(), scale),
+            Keyframe::new(1.0, Vector3d::new([0.0, 0.6, -1.5]), Quaternion::from_axis_angle(y, 60.0), scale),
+        ]);
+        world.push(Box::new(Instance::animated(disc, animation)));
+        let ground = Sphere {
+            center: Point3d::new([0.0, -100.5, -1.0]),
+            radius: 100.0,
+            material: gray(),
+        };
+        world.push(Box::new(ground));
+
+        let aspect_ratio = 16.0 / 9.0;
+        let viewport_width = 3.5;
+        let focal_length = 1.0;
+
+        Scene {
+            objects: HitableList { hitables: world },
+            camera: Camera::new(aspect_ratio, viewport_width, focal_length).with_shutter(0.0, 1.0),
+            fog: None,
+        }
+    }
+
+    /// Terrain with the heights in `filename`, or rolling procedural terrain
+    /// without a readable file.
+    pub fn terrain(filename: Option<&str>) -> Self {
+        let material = Material::Lambertian(Arc::new(Gradient {
+            from: Color::new([0.2, 0.35, 0.1]),
+            to: Color::new([0.9, 0.9, 0.9]),
+            axis: GradientAxis::Along {
+                origin: Point3d::new([0.0, -0.8, 0.0]),
+                direction: Vector3d::new([0.0, 0.6, 0.0]),
+            },
+        }));
+        let size = Vector3d::new([8.0, 0.8, 8.0]);
+        let loaded = filename.and_then(|f| Heightfield::from_image(f, size, material.clone()).ok());
+        let heightfield = loaded.unwrap_or_else(|| {
+            let n = 256;
+            let heights = (0..n * n)
+                .map(|i| {
+                    let (x, z) = ((i % n) as f64 / n as f64, (i / n) as f64 / n as f64);
+                    let h = (x * 9.0).sin() * (z * 7.0).cos() + 0.5 * (x * 23.0 + z * 17.0).sin();
+                    0.5 + 0.3 * h
+                })
+                .collect();
+            Heightfield::new((n, n), heights, size, material)
+        });
+        let transform = Transform::translate(Vector3d::new([-4.0, -1.0, -9.0]));
+        let world: Vec<Box<dyn Hitable>> = vec![Box::new(Instance::new(Arc::new(heightfield), transform))];
+
+        let aspect_ratio = 16.0 / 9.0;
+        let viewport_width = 3.5;
+        let focal_length = 1.0;
+
+        Scene {
+            objects: HitableList { hitables: world },
+            camera: Camera::new(aspect_ratio, viewport_width, focal_length),
+            fog: None,
+        }
+    }
+
+    /// A grid of small spheres that all share one underlying object.
+    pub fn instanced() -> Self {
+        let mut world: Vec<Box<dyn Hitable>> = Vec::new();
+        let sphere: Arc<dyn Hitable> = Arc::new(Sphere {
+            center: Point3d::new([0.0, 0.0, 0.0]),
+            radius: 1.0,
+            material: gray(),
+        });
+        for i in -5..=5 {
+            for j in 1..=10 {
+                let offset = Vector3d::new([i as f64 * 0.4, -0.4, -(j as f64) * 0.4]);
+                let transform = Transform::translate(offset)
+                    * Transform::rotate(Vector3d::new([0.0, 1.0, 0.0]), (i * j) as f64 * 10.0)
+                    * Transform::scale(Vector3d::new([0.15, 0.08, 0.1]));
+                world.push(Box::new(Instance::new(sphere.clone(), transform)));
+            }
+        }
+        let ground = Sphere {
+            center: Point3d::new([0.0, -100.5, -1.0]),
+            radius: 100.0,
+            material: gray(),
+        };
+        world.push(Box::new(ground));
+
+        let aspect_ratio = 16.0 / 9.0;
+        let viewport_width = 3.5;
+        let focal_length = 1.0;
+
+        Scene {
             objects: HitableList { hitables: world },
             camera: Camera::new(aspect_ratio, viewport_width, focal_length),
+            fog: None,
+        }
+    }
+
+    /// A sphere wrapped in an image, over a ground plane with a fine grid
+    /// that only stays free of moire with mip-mapping. Without a readable
+    /// `filename` the sphere gets a generated latitude/longitude grid.
+    pub fn textured(filename: Option<&str>) -> Self {
+        let grid = |n: usize, lines: usize| {
+            let mut image = FloatImage::new(n, n);
+            for y in 0..n {
+                for x in 0..n {
+                    let line = x % (n / lines) == 0 || y % (n / lines) == 0;
+                    let c = if line { 0.05 } else { 0.8 };
+                    image.set(x, y, Color::new([c, c, c * 0.7]));
+                }
+            }
+            image
+        };
+        let loaded = filename.and_then(|f| ImageTexture::load(f).ok());
+        let mut globe = loaded.unwrap_or_else(|| ImageTexture::new(grid(256, 16)));
+        globe.wrap = WrapMode::Clamp;
+        let mut tiles = ImageTexture::new(grid(1024, 64));
+        tiles.wrap = WrapMode::Mirror;
+        tiles.filter = Filter::Trilinear;
+        // Tangent space ripples, encoded the way normal maps store them.
+        let mut ripples = FloatImage::new(256, 128);
+        for y in 0..128 {
+            for x in 0..256 {
+                let (s, t) = (x as f64 / 256.0 * 64.0 * PI, y as f64 / 128.0 * 32.0 * PI);
+                let n = Vector3d::new([0.3 * s.sin(), 0.3 * t.sin(), 1.0]).unit_vector();
+                ripples.set(x, y, (n + Vector3d::new([1.0, 1.0, 1.0])) * 0.5);
+            }
+        }
+        let globe = Material::Bumped {
+            material: Box::new(Material::Lambertian(Arc::new(globe))),
+            map: BumpMap::Normal(Arc::new(ImageTexture::new(ripples))),
         };
+        let tiles: Arc<dyn Texture> = Arc::new(tiles);
+        let grooved = Material::Bumped {
+            material: Box::new(Material::Lambertian(tiles.clone())),
+            map: BumpMap::Height {
+                map: tiles,
+                strength: 0.05,
+            },
+        };
+        let size = Vector3d::new([40.0, 1.0, 40.0]);
+        let ground = Heightfield::new((2, 2), vec![0.0; 4], size, grooved);
+        let transform = Transform::translate(Vector3d::new([-20.0, -0.5, -40.0]));
+
+        let world: Vec<Box<dyn Hitable>> = vec![
+            Box::new(Sphere {
+                center: Point3d::new([0.0, 0.0, -1.0]),
+                radius: 0.5,
+                material: globe,
+            }),
+            Box::new(Instance::new(Arc::new(ground), transform)),
+        ];
+
+        let aspect_ratio = 16.0 / 9.0;
+        let viewport_width = 3.5;
+        let focal_length = 1.0;
+
+        Scene {
+            objects: HitableList { hitables: world },
+            camera: Camera::new(aspect_ratio, viewport_width, focal_length),
+            fog: None,
+        }
+    }
+
+    /// Marble, wood and stone spheres, all generated from noise.
+    pub fn procedural() -> Self {
+        let noise = Noise::new(42);
+        let marble = Marble {
+            light: Color::new([0.9, 0.9, 0.85]),
+            vein: Color::new([0.15, 0.15, 0.2]),
+            scale: 4.0,
+            octaves: 6,
+            distortion: 6.0,
+            noise,
+        };
+        let wood = Wood {
+            light: Color::new([0.75, 0.55, 0.3]),
+            dark: Color::new([0.4, 0.22, 0.08]),
+            rings: 12.0,
+            scale: 3.0,
+            octaves: 4,
+            noise,
+        };
+        let stone = Stone {
+            stone: Color::new([0.55, 0.5, 0.45]),
+            mortar: Color::new([0.2, 0.2, 0.2]),
+            scale: 4.0,
+            octaves: 4,
+            mortar_width: 0.08,
+            noise,
+        };
+        let sphere = |x: f64, material: Material| -> Box<dyn Hitable> {
+            Box::new(Sphere {
+                center: Point3d::new([x, 0.0, -1.6]),
+                radius: 0.5,
+                material,
+            })
+        };
+        let world = vec![
+            sphere(-1.05, Material::Lambertian(Arc::new(marble))),
+            sphere(0.0, Material::Lambertian(Arc::new(wood))),
+            sphere(
+                1.05,
+                Material::Bumped {
+                    material: Box::new(Material::Lambertian(Arc::new(stone))),
+                    map: BumpMap::Height {
+                        map: Arc::new(stone),
+                        strength: 0.02,
+                    },
+                },
+            ),
+            Box::new(Sphere {
+                center: Point3d::new([0.0, -100.5, -1.0]),
+                radius: 100.0,
+                material: Material::Lambertian(Arc::new(Stone { scale: 2.0, ..stone })),
+            }),
+        ];
+
+        let aspect_ratio = 16.0 / 9.0;
+        let viewport_width = 3.5;
+        let focal_length = 1.0;
+
+        Scene {
+            objects: HitableList { hitables: world },
+            camera: Camera::new(aspect_ratio, viewport_width, focal_length),
+            fog: None,
+        }
+    }
+
+    /// Gold, copper and aluminium at increasing roughness, and frosted glass.
+    pub fn metals() -> Self {
+        let materials = [
+            Material::RoughConductor(RoughConductor::gold(0.1)),
+            Material::RoughConductor(RoughConductor::copper(0.3)),
+            Material::RoughConductor(RoughConductor::aluminium(0.5)),
+            Material::RoughDielectric(RoughDielectric {
+                ior: 1.5,
+                roughness: 0.2,
+            }),
+        ];
+        let mut world: Vec<Box<dyn Hitable>> = materials
+            .into_iter()
+            .enumerate()
+            .map(|(i, material)| -> Box<dyn Hitable> {
+                Box::new(Sphere {
+                    center: Point3d::new([i as f64 * 0.9 - 1.35, -0.1, -1.5]),
+                    radius: 0.4,
+                    material,
+                })
+            })
+            .collect();
+        let checker = Checker3d {
+            even: solid(Color::new([0.8, 0.8, 0.8])),
+            odd: solid(Color::new([0.2, 0.3, 0.1])),
+            scale: 2.0,
+        };
+        world.push(Box::new(Sphere {
+            center: Point3d::new([0.0, -100.5, -1.0]),
+            radius: 100.0,
+            material: Material::Lambertian(Arc::new(checker)),
+        }));
+
+        let aspect_ratio = 16.0 / 9.0;
+        let viewport_width = 3.5;
+        let focal_length = 1.0;
+
+        Scene {
+            objects: HitableList { hitables: world },
+            camera: Camera::new(aspect_ratio, viewport_width, focal_length),
+            fog: None,
+        }
+    }
+
+    /// Principled materials: coated plastic, brushed metal, velvet and glass
+    /// whose roughness varies with noise.
+    pub fn principled() -> Self {
+        let value = |v: f64| solid(Color::new([v, v, v]));
+        let plastic = Principled {
+            clearcoat: value(1.0),
+            roughness: value(0.6),
+            ..Principled::new(solid(Color::new([0.7, 0.1, 0.1])))
+        };
+        let brushed = Principled {
+            metallic: value(1.0),
+            roughness: value(0.4),
+            anisotropy: value(0.9),
+            ..Principled::new(solid(Color::new([0.9, 0.9, 0.9])))
+        };
+        let velvet = Principled {
+            roughness: value(1.0),
+            sheen: value(1.0),
+            specular: value(0.0),
+            ..Principled::new(solid(Color::new([0.1, 0.1, 0.5])))
+        };
+        let blotches = Marble {
+            light: Color::new([0.4, 0.4, 0.4]),
+            vein: Color::new([0.0, 0.0, 0.0]),
+            scale: 6.0,
+            octaves: 4,
+            distortion: 3.0,
+            noise: Noise::new(7),
+        };
+        let glass = Principled {
+            transmission: value(1.0),
+            roughness: Arc::new(blotches),
+            ..Principled::new(solid(Color::new([0.9, 1.0, 0.95])))
+        };
+        let mut world: Vec<Box<dyn Hitable>> = [plastic, brushed, velvet, glass]
+            .into_iter()
+            .enumerate()
+            .map(|(i, material)| -> Box<dyn Hitable> {
+                Box::new(Sphere {
+                    center: Point3d::new([i as f64 * 0.9 - 1.35, -0.1, -1.5]),
+                    radius: 0.4,
+                    material: Material::Principled(material),
+                })
+            })
+            .collect();
+        world.push(Box::new(Sphere {
+            center: Point3d::new([0.0, -100.5, -1.0]),
+            radius: 100.0,
+            material: gray(),
+        }));
+
+        let aspect_ratio = 16.0 / 9.0;
+        let viewport_width = 3.5;
+        let focal_length = 1.0;
+
+        Scene {
+            objects: HitableList { hitables: world },
+            camera: Camera::new(aspect_ratio, viewport_width, focal_length),
+            fog: None,
+        }
+    }
+
+    /// Lacquered paint, amber-varnished copper and gold inlaid into diffuse
+    /// stone, showing coated and mixed materials.
+    pub fn layered() -> Self {
+        let paint = Coated::new(Material::Lambertian(solid(Color::new([0.6, 0.05, 0.05]))));
+        let varnish = Coated {
+            roughness: 0.1,
+            tint: Color::new([0.95, 0.75, 0.4]),
+            ..Coated::new(Material::RoughConductor(RoughConductor::copper(0.5)))
+        };
+        let inlay = Material::Mix {
+            a: Box::new(Material::Lambertian(solid(Color::new([0.6, 0.6, 0.55])))),
+            b: Box::new(Material::RoughConductor(RoughConductor::gold(0.2))),
+            factor: Arc::new(Checker3d {
+                even: solid(Color::black()),
+                odd: solid(Color::white()),
+                scale: 8.0,
+            }),
+        };
+        let materials = [Material::Coated(paint), Material::Coated(varnish), inlay];
+        let mut world: Vec<Box<dyn Hitable>> = materials
+            .into_iter()
+            .enumerate()
+            .map(|(i, material)| -> Box<dyn Hitable> {
+                Box::new(Sphere {
+                    center: Point3d::new([i as f64 * 1.0 - 1.0, -0.1, -1.5]),
+                    radius: 0.4,
+                    material,
+                })
+            })
+            .collect();
+        world.push(Box::new(Sphere {
+            center: Point3d::new([0.0, -100.5, -1.0]),
+            radius: 100.0,
+            material: gray(),
+        }));
+
+        let aspect_ratio = 16.0 / 9.0;
+        let viewport_width = 3.5;
+        let focal_length = 1.0;
+
+        Scene {
+            objects: HitableList { hitables: world },
+            camera: Camera::new(aspect_ratio, viewport_width, focal_length),
+            fog: None,
+        }
+    }
+
+    /// Skin, wax and marble spheres lit through by random walk subsurface
+    /// scattering.
+    pub fn translucent() -> Self {
+        let skin = Subsurface::new(
+            Color::new([0.8, 0.55, 0.45]),
+            Color::new([0.12, 0.07, 0.05]),
+        );
+        let wax = Subsurface {
+            anisotropy: 0.3,
+            ..Subsurface::new(Color::new([0.9, 0.85, 0.6]), Color::new([0.1, 0.1, 0.07]))
+        };
+        let marble = Subsurface {
+            roughness: 0.05,
+            ior: 1.5,
+            ..Subsurface::new(Color::new([0.9, 0.9, 0.9]), Color::new([0.05, 0.05, 0.05]))
+        };
+        let mut world: Vec<Box<dyn Hitable>> = [skin, wax, marble]
+            .into_iter()
+            .enumerate()
+            .map(|(i, material)| -> Box<dyn Hitable> {
+                Box::new(Sphere {
+                    center: Point3d::new([i as f64 * 1.0 - 1.0, -0.1, -1.5]),
+                    radius: 0.4,
+                    material: Material::Subsurface(material),
+                })
+            })
+            .collect();
+        world.push(Box::new(Sphere {
+            center: Point3d::new([0.0, -100.5, -1.0]),
+            radius: 100.0,
+            material: gray(),
+        }));
+
+        let aspect_ratio = 16.0 / 9.0;
+        let viewport_width = 3.5;
+        let focal_length = 1.0;
+
+        Scene {
+            objects: HitableList { hitables: world },
+            camera: Camera::new(aspect_ratio, viewport_width, focal_length),
+            fog: None,
+        }
+    }
+
+    /// A smoke ball and a solid sphere standing in low ground fog.
+    pub fn foggy() -> Self {
+        let smoke_boundary = Sphere {
+            center: Point3d::new([-0.6, 0.0, -1.5]),
+            radius: 0.5,
+            material: gray(),
+        };
+        let smoke = ConstantMedium::new(
+            Box::new(smoke_boundary),
+            4.0,
+            solid(Color::new([0.3, 0.3, 0.35])),
+            PhaseFunction::HenyeyGreenstein(0.4),
+        );
+        let world: Vec<Box<dyn Hitable>> = vec![
+            Box::new(smoke),
+            Box::new(Sphere {
+                center: Point3d::new([0.6, 0.0, -1.5]),
+                radius: 0.5,
+                material: Material::Lambertian(solid(Color::new([0.7, 0.3, 0.2]))),
+            }),
+            Box::new(Sphere {
+                center: Point3d::new([0.0, -100.5, -1.0]),
+                radius: 100.0,
+                material: gray(),
+            }),
+        ];
+
+        let aspect_ratio = 16.0 / 9.0;
+        let viewport_width = 3.5;
+        let focal_length = 1.0;
+
+        Scene {
+            objects: HitableList { hitables: world },
+            camera: Camera::new(aspect_ratio, viewport_width, focal_length),
+            fog: Some(Fog {
+                density: 0.3,
+                albedo: Color::new([0.9, 0.9, 0.9]),
+                phase: PhaseFunction::HenyeyGreenstein(0.7),
+                height: -0.2,
+            }),
+        }
+    }
+
+    /// A smoke plume loaded from a voxel grid file, or a procedural puff of
+    /// smoke around a glowing core when `filename` is `None`.
+    pub fn smoke(filename: Option<&str>) -> Self {
+        let grid = match filename {
+            Some(filename) => VoxelGrid::load(filename).expect("Voxel grid load error"),
+            None => procedural_smoke(48),
+        };
+        let bounds = Aabb::new(Point3d::new([-0.6, -0.5, -2.1]), Point3d::new([0.6, 0.7, -0.9]));
+        let smoke = VoxelVolume::new(
+            Arc::new(grid),
+            bounds,
+            15.0,
+            PhaseFunction::HenyeyGreenstein(0.3),
+        );
+        let world: Vec<Box<dyn Hitable>> = vec![
+            Box::new(smoke),
+            Box::new(Sphere {
+                center: Point3d::new([0.0, -100.5, -1.0]),
+                radius: 100.0,
+                material: gray(),
+            }),
+        ];
+
+        let aspect_ratio = 16.0 / 9.0;
+        let viewport_width = 3.5;
+        let focal_length = 1.0;
+
+        Scene {
+            objects: HitableList { hitables: world },
+            camera: Camera::new(aspect_ratio, viewport_width, focal_length),
+            fog: None,
+        }
+    }
+
+    /// A closed room lit only by a lamp outside its one window, with a glass
+    /// ball in the light: the kind of scene bidirectional methods are for.
+    pub fn interior() -> Self {
+        let cuboid = |x: f64, y: f64, z: f64, half: [f64; 3]| SdfNode::Translate {
+            offset: Vector3d::new([x, y, z]),
+            node: Box::new(SdfNode::Box {
+                half_extents: Vector3d::new(half),
+            }),
+        };
+        let inside = SdfNode::Union(
+            Box::new(cuboid(0.0, 0.5, -1.2, [2.0, 1.0, 2.5])),
+            Box::new(cuboid(2.1, 0.9, -2.0, [0.3, 0.25, 0.35])),
+        );
+        let room = SdfNode::Difference(
+            Box::new(cuboid(0.0, 0.5, -1.2, [2.2, 1.2, 2.7])),
+            Box::new(inside),
+        );
+        let walls = Material::Lambertian(solid(Color::new([0.7, 0.7, 0.7])));
+
+        let world: Vec<Box<dyn Hitable>> = vec![
+            Box::new(Sdf::new(room, walls)),
+            Box::new(Sphere {
+                center: Point3d::new([3.2, 1.8, -2.0]),
+                radius: 0.4,
+                material: Material::DiffuseLight(solid(Color::new([40.0, 36.0, 30.0]))),
+            }),
+            Box::new(Sphere {
+                center: Point3d::new([0.9, -0.15, -2.0]),
+                radius: 0.35,
+                material: Material::RoughDielectric(RoughDielectric {
+                    ior: 1.5,
+                    roughness: 0.02,
+                }),
+            }),
+            Box::new(Sphere {
+                center: Point3d::new([-0.8, -0.1, -2.2]),
+                radius: 0.4,
+                material: Material::Lambertian(solid(Color::new([0.7, 0.3, 0.2]))),
+            }),
+        ];
+
+        let aspect_ratio = 16.0 / 9.0;
+        let viewport_width = 3.5;
+        let focal_length = 1.0;
+
+        Scene {
+            objects: HitableList { hitables: world },
+            camera: Camera::new(aspect_ratio, viewport_width, focal_length),
+            fog: None,
+        }
+    }
+}
+
+/// A turbulent ball of smoke whose dense center absorbs and glows like fire.
+fn procedural_smoke(n: usize) -> VoxelGrid {
+    let noise = Noise::new(7);
+    let mut grid = VoxelGrid::new([n, n, n], vec![0.0; n * n * n]);
+    grid.absorption = vec![0.0; n * n * n];
+    grid.emission = vec![[0.0; 3]; n * n * n];
+    for i in 0..n * n * n {
+        let p = Point3d::new([i % n, i / n % n, i / (n * n)].map(|c| (c as f64 + 0.5) / n as f64));
+        let offset = p - Point3d::new([0.5, 0.5, 0.5]);
+        let puff = 1.0 - offset.length() * 2.2 + noise.fbm(&(p * 4.0), 4) * 0.5;
+        if puff <= 0.0 {
+            continue;
+        }
+        grid.density[i] = puff as f32;
+        let heat = (puff - 0.5).max(0.0) * 2.0;
+        grid.absorption[i] = (heat * 2.0) as f32;
+        grid.emission[i] = [heat * 12.0, heat * heat * 6.0, heat.powi(4) * 2.0].map(|c| c as f32);
     }
+    grid
 }
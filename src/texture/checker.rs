@@ -0,0 +1,76 @@
+use crate::texture::Texture;
+use crate::{Color, Point3d};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// Alternates two textures in space, `scale` cells per unit length.
+/// Works on surfaces without usable coordinates, such as SDFs.
+#[derive(Debug)]
+pub struct Checker3d {
+    pub even: Arc<dyn Texture>,
+    pub odd: Arc<dyn Texture>,
+    pub scale: f64,
+}
+
+impl Texture for Checker3d {
+    fn value(&self, u: f64, v: f64, point: &Point3d) -> Color {
+        let sines: f64 = point.data.iter().map(|a| (PI * self.scale * a).sin()).product();
+        if sines < 0.0 {
+            self.odd.value(u, v, point)
+        } else {
+            self.even.value(u, v, point)
+        }
+    }
+}
+
+/// Alternates two textures in surface coordinates, `frequency.0` cells across
+/// `u` and `frequency.1` cells across `v`.
+#[derive(Debug)]
+pub struct CheckerUv {
+    pub even: Arc<dyn Texture>,
+    pub odd: Arc<dyn Texture>,
+    pub frequency: (f64, f64),
+}
+
+impl Texture for CheckerUv {
+    fn value(&self, u: f64, v: f64, point: &Point3d) -> Color {
+        let cell = (u * self.frequency.0).floor() + (v * self.frequency.1).floor();
+        if cell.rem_euclid(2.0) == 0.0 {
+            self.even.value(u, v, point)
+        } else {
+            self.odd.value(u, v, point)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::solid;
+
+    #[test]
+    fn test_checker_uv() {
+        let checker = CheckerUv {
+            even: solid(Color::white()),
+            odd: solid(Color::black()),
+            frequency: (4.0, 2.0),
+        };
+        let p = Point3d::new([0.0, 0.0, 0.0]);
+        assert_eq!(checker.value(0.1, 0.1, &p), Color::white());
+        assert_eq!(checker.value(0.3, 0.1, &p), Color::black());
+        assert_eq!(checker.value(0.3, 0.6, &p), Color::white());
+    }
+
+    #[test]
+    fn test_checker_3d() {
+        let checker = Checker3d {
+            even: solid(Color::white()),
+            odd: solid(Color::black()),
+            scale: 1.0,
+        };
+        let at = |x, y, z| checker.value(0.0, 0.0, &Point3d::new([x, y, z]));
+        assert_eq!(at(0.5, 0.5, 0.5), Color::white());
+        assert_eq!(at(1.5, 0.5, 0.5), Color::black());
+        assert_eq!(at(1.5, -0.5, 0.5), Color::white());
+    }
+}
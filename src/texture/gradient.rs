@@ -0,0 +1,32 @@
+use crate::texture::Texture;
+use crate::{Color, Point3d, Vector3d};
+
+#[derive(Clone, Copy, Debug)]
+pub enum GradientAxis {
+    U,
+    V,
+    /// Runs from `origin` (0) to `origin + direction` (1) in space.
+    Along { origin: Point3d, direction: Vector3d },
+}
+
+/// Linear blend from `from` to `to`, clamped at both ends.
+#[derive(Clone, Copy, Debug)]
+pub struct Gradient {
+    pub from: Color,
+    pub to: Color,
+    pub axis: GradientAxis,
+}
+
+impl Texture for Gradient {
+    fn value(&self, u: f64, v: f64, point: &Point3d) -> Color {
+        let t = match self.axis {
+            GradientAxis::U => u,
+            GradientAxis::V => v,
+            GradientAxis::Along { origin, direction } => {
+                (*point - origin).dot(&direction) / direction.length_squared()
+            }
+        };
+        let t = t.clamp(0.0, 1.0);
+        self.from * (1.0 - t) + self.to * t
+    }
+}
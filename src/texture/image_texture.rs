@@ -0,0 +1,265 @@
+use crate::float_image::FloatImage;
+use crate::geometry::HitRecord;
+use crate::texture::Texture;
+use crate::{Color, Point3d};
+use std::io::Result;
+
+/// How texel coordinates outside the image are mapped back into it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WrapMode {
+    Repeat,
+    Clamp,
+    Mirror,
+}
+
+impl WrapMode {
+    fn apply(&self, i: i64, n: usize) -> usize {
+        let n = n as i64;
+        let i = match self {
+            WrapMode::Repeat => i.rem_euclid(n),
+            WrapMode::Clamp => i.clamp(0, n - 1),
+            WrapMode::Mirror => {
+                let m = i.rem_euclid(2 * n);
+                if m >= n {
+                    2 * n - 1 - m
+                } else {
+                    m
+                }
+            }
+        };
+        i as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,
+    Bilinear,
+    /// Bilinear on the two mip levels bracketing the ray footprint, blended.
+    Trilinear,
+}
+
+/// An image mapped over `(u, v)`, with `v = 0` at the bottom row.
+#[derive(Debug)]
+pub struct ImageTexture {
+    pub wrap: WrapMode,
+    pub filter: Filter,
+    /// Mip pyramid, full resolution first, each level half the previous,
+    /// rounded up.
+    levels: Vec<FloatImage>,
+}
+
+impl ImageTexture {
+    /// `image` must already hold linear values.
+    pub fn new(image: FloatImage) -> Self {
+        let mut levels = vec![image];
+        loop {
+            let last = levels.last().unwrap();
+            if last.width == 1 && last.height == 1 {
+                break;
+            }
+            levels.push(downsample(last));
+        }
+        ImageTexture {
+            wrap: WrapMode::Repeat,
+            filter: Filter::Trilinear,
+            levels,
+        }
+    }
+
+    /// Loads a color map. PNG and PPM data is decoded from sRGB; HDR is already linear.
+    pub fn load(filename: &str) -> Result<Self> {
+        let mut image = FloatImage::load(filename)?;
+        let lower = filename.to_ascii_lowercase();
+        if !(lower.ends_with(".hdr") || lower.ends_with(".pic")) {
+            image.data = image.data.iter().map(|c| c.srgb_to_linear()).collect();
+        }
+        Ok(ImageTexture::new(image))
+    }
+
+    /// Loads a non-color map such as roughness, using the stored values as is.
+    pub fn load_linear(filename: &str) -> Result<Self> {
+        Ok(ImageTexture::new(FloatImage::load(filename)?))
+    }
+
+    pub fn width(&self) -> usize {
+        self.levels[0].width
+    }
+
+    pub fn height(&self) -> usize {
+        self.levels[0].height
+    }
+
+    fn texel(&self, level: usize, x: i64, y: i64) -> Color {
+        let image = &self.levels[level];
+        image.get(
+            self.wrap.apply(x, image.width),
+            self.wrap.apply(y, image.height),
+        )
+    }
+
+    fn nearest(&self, level: usize, u: f64, v: f64) -> Color {
+        let image = &self.levels[level];
+        let x = (u * image.width as f64).floor() as i64;
+        let y = ((1.0 - v) * image.height as f64).floor() as i64;
+        self.texel(level, x, y)
+    }
+
+    fn bilinear(&self, level: usize, u: f64, v: f64) -> Color {
+        let image = &self.levels[level];
+        // Texel centers sit at half-integer coordinates.
+        let x = u * image.width as f64 - 0.5;
+        let y = (1.0 - v) * image.height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let top = self.texel(level, x0, y0) * (1.0 - fx) + self.texel(level, x0 + 1, y0) * fx;
+        let bottom =
+            self.texel(level, x0, y0 + 1) * (1.0 - fx) + self.texel(level, x0 + 1, y0 + 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+
+    /// Blends the levels whose texel size brackets `footprint`, given in uv units.
+    pub fn trilinear(&self, u: f64, v: f64, footprint: f64) -> Color {
+        let texels = footprint * self.width().max(self.height()) as f64;
+        let max_level = (self.levels.len() - 1) as f64;
+        let level = texels.max(1.0).log2().min(max_level);
+        let lower = level.floor() as usize;
+        let t = level - lower as f64;
+        if t == 0.0 {
+            return self.bilinear(lower, u, v);
+        }
+        self.bilinear(lower, u, v) * (1.0 - t) + self.bilinear(lower + 1, u, v) * t
+    }
+}
+
+/// Source texels under each of `to` texels spanning the same length as
+/// `from`, weighted by how much of each they cover.
+fn box_taps(from: usize, to: usize) -> Vec<Vec<(usize, f64)>> {
+    let scale = from as f64 / to as f64;
+    (0..to)
+        .map(|i| {
+            let (start, end) = (i as f64 * scale, (i + 1) as f64 * scale);
+            (start.floor() as usize..(end.ceil() as usize).min(from))
+                .map(|j| {
+                    let overlap = end.min((j + 1) as f64) - start.max(j as f64);
+                    (j, overlap / scale)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Halves each side, rounding up, box filtering the area each new texel
+/// covers. Even sides average 2x2 blocks; odd sides keep every texel, each
+/// new texel taking in fractions of the ones on its edges.
+fn downsample(image: &FloatImage) -> FloatImage {
+    let width = image.width.div_ceil(2);
+    let height = image.height.div_ceil(2);
+    let (xs, ys) = (box_taps(image.width, width), box_taps(image.height, height));
+    let mut result = FloatImage::new(width, height);
+    for (y, y_taps) in ys.iter().enumerate() {
+        for (x, x_taps) in xs.iter().enumerate() {
+            let sum: Color = y_taps
+                .iter()
+                .flat_map(|&(sy, wy)| x_taps.iter().map(move |&(sx, wx)| (sx, sy, wx * wy)))
+                .map(|(sx, sy, weight)| image.get(sx, sy) * weight)
+                .sum();
+            result.set(x, y, sum);
+        }
+    }
+    result
+}
+
+impl Texture for ImageTexture {
+    /// Unfiltered by footprint: trilinear lookups fall back to the full-resolution level.
+    fn value(&self, u: f64, v: f64, _point: &Point3d) -> Color {
+        match self.filter {
+            Filter::Nearest => self.nearest(0, u, v),
+            Filter::Bilinear | Filter::Trilinear => self.bilinear(0, u, v),
+        }
+    }
+
+    fn sample(&self, rec: &HitRecord) -> Color {
+        match self.filter {
+            Filter::Trilinear => self.trilinear(rec.u, rec.v, rec.footprint),
+            _ => self.value(rec.u, rec.v, &rec.point),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkerboard(size: usize) -> FloatImage {
+        let mut image = FloatImage::new(size, size);
+        for y in 0..size {
+            for x in 0..size {
+                let c = if (x + y) % 2 == 0 { 1.0 } else { 0.0 };
+                image.set(x, y, Color::new([c, c, c]));
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn test_wrap_modes() {
+        assert_eq!(WrapMode::Repeat.apply(-1, 4), 3);
+        assert_eq!(WrapMode::Repeat.apply(5, 4), 1);
+        assert_eq!(WrapMode::Clamp.apply(-3, 4), 0);
+        assert_eq!(WrapMode::Clamp.apply(9, 4), 3);
+        assert_eq!(WrapMode::Mirror.apply(-1, 4), 0);
+        assert_eq!(WrapMode::Mirror.apply(4, 4), 3);
+        assert_eq!(WrapMode::Mirror.apply(9, 4), 1);
+    }
+
+    #[test]
+    fn test_bilinear_between_texels() {
+        let mut image = FloatImage::new(2, 1);
+        image.set(0, 0, Color::new([0.0, 0.0, 0.0]));
+        image.set(1, 0, Color::new([1.0, 1.0, 1.0]));
+        let mut texture = ImageTexture::new(image);
+        texture.wrap = WrapMode::Clamp;
+        let p = Point3d::new([0.0, 0.0, 0.0]);
+        assert_eq!(texture.value(0.25, 0.5, &p), Color::new([0.0, 0.0, 0.0]));
+        assert_eq!(texture.value(0.5, 0.5, &p), Color::new([0.5, 0.5, 0.5]));
+        texture.filter = Filter::Nearest;
+        assert_eq!(texture.value(0.6, 0.5, &p), Color::new([1.0, 1.0, 1.0]));
+    }
+
+    #[test]
+    fn test_mip_levels_average() {
+        let texture = ImageTexture::new(checkerboard(8));
+        assert_eq!(texture.levels.len(), 4);
+        assert_eq!(texture.levels[1].get(0, 0), Color::new([0.5, 0.5, 0.5]));
+        // A footprint covering the whole texture resolves to the flat average.
+        let far = texture.trilinear(0.3, 0.7, 1.0);
+        assert!((far - Color::new([0.5, 0.5, 0.5])).length() < 1e-9);
+        // A tiny footprint sees individual texels.
+        let near = texture.trilinear(1.0 / 16.0, 1.0 - 1.0 / 16.0, 0.0);
+        assert_eq!(near, Color::new([1.0, 1.0, 1.0]));
+    }
+
+    #[test]
+    fn test_odd_levels_keep_every_texel() {
+        let mut image = FloatImage::new(3, 1);
+        image.set(2, 0, Color::new([3.0, 3.0, 3.0]));
+        let texture = ImageTexture::new(image);
+        let sizes: Vec<usize> = texture.levels.iter().map(|level| level.width).collect();
+        assert_eq!(sizes, vec![3, 2, 1]);
+        let level = &texture.levels[1];
+        assert!((level.get(0, 0).x() - 0.0).abs() < 1e-12);
+        assert!((level.get(1, 0).x() - 2.0).abs() < 1e-12);
+        // The average survives down to the last level.
+        assert!((texture.levels[2].get(0, 0).x() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_srgb_decoding() {
+        let linear = Color::new([0.0, 0.5, 1.0]).srgb_to_linear();
+        assert_eq!(linear.x(), 0.0);
+        assert!((linear.y() - 0.214).abs() < 1e-3);
+        assert!((linear.z() - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,26 @@
+use crate::noise::Noise;
+use crate::texture::Texture;
+use crate::{Color, Point3d};
+
+/// Bands along x, warped by turbulence into veins.
+#[derive(Clone, Copy, Debug)]
+pub struct Marble {
+    pub light: Color,
+    pub vein: Color,
+    /// Noise frequency per unit length.
+    pub scale: f64,
+    pub octaves: usize,
+    /// How far the turbulence bends the bands.
+    pub distortion: f64,
+    pub noise: Noise,
+}
+
+impl Texture for Marble {
+    fn value(&self, _u: f64, _v: f64, point: &Point3d) -> Color {
+        let p = *point * self.scale;
+        let phase = p.x() + self.distortion * self.noise.turbulence(&p, self.octaves);
+        // Narrow the dark part of each band so veins stay thin.
+        let t = (0.5 + 0.5 * phase.sin()).powf(0.3);
+        self.vein * (1.0 - t) + self.light * t
+    }
+}
@@ -0,0 +1,13 @@
+use crate::texture::Texture;
+use crate::{Color, Point3d};
+
+#[derive(Clone, Copy, Debug)]
+pub struct SolidColor {
+    pub color: Color,
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _point: &Point3d) -> Color {
+        self.color
+    }
+}
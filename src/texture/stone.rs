@@ -0,0 +1,57 @@
+use crate::noise::Noise;
+use crate::texture::Texture;
+use crate::{Color, Point3d};
+
+/// Irregular cobbles from cellular noise, set in mortar.
+#[derive(Clone, Copy, Debug)]
+pub struct Stone {
+    pub stone: Color,
+    pub mortar: Color,
+    /// Cells per unit length.
+    pub scale: f64,
+    /// Octaves of the speckle over each cobble.
+    pub octaves: usize,
+    /// Gap between cobbles in cell units.
+    pub mortar_width: f64,
+    pub noise: Noise,
+}
+
+impl Texture for Stone {
+    fn value(&self, _u: f64, _v: f64, point: &Point3d) -> Color {
+        let p = *point * self.scale;
+        let cell = self.noise.worley(&p);
+        if cell.f2 - cell.f1 < self.mortar_width {
+            return self.mortar;
+        }
+        let tint = 0.7 + 0.3 * (cell.id >> 11) as f64 / (1_u64 << 53) as f64;
+        let speckle = 1.0 + 0.2 * self.noise.fbm(&(p * 8.0), self.octaves);
+        self.stone * (tint * speckle).max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mortar_between_cells() {
+        let stone = Stone {
+            stone: Color::white(),
+            mortar: Color::black(),
+            scale: 1.0,
+            octaves: 3,
+            mortar_width: 0.1,
+            noise: Noise::new(5),
+        };
+        // Walk a line until the nearest cell changes; the boundary is mortar.
+        let at = |x: f64| Point3d::new([x, 0.3, 0.7]);
+        let start = stone.noise.worley(&at(0.0)).id;
+        let x = (1..400)
+            .map(|i| i as f64 * 0.01)
+            .find(|&x| stone.noise.worley(&at(x)).id != start)
+            .unwrap();
+        assert_eq!(stone.value(0.0, 0.0, &at(x)), Color::black());
+        let inside = (0..400).map(|i| stone.value(0.0, 0.0, &at(i as f64 * 0.01)));
+        assert!(inside.filter(|&c| c != Color::black()).count() > 200);
+    }
+}
@@ -0,0 +1,21 @@
+use crate::geometry::HitRecord;
+use crate::texture::SolidColor;
+use crate::{Color, Point3d};
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// A color that varies over a surface, looked up by surface coordinates
+/// `(u, v)` or by the hit point in space.
+pub trait Texture: Debug + Send + Sync {
+    fn value(&self, u: f64, v: f64, point: &Point3d) -> Color;
+
+    /// Lookup at a hit, where textures that filter can use `rec.footprint`.
+    fn sample(&self, rec: &HitRecord) -> Color {
+        self.value(rec.u, rec.v, &rec.point)
+    }
+}
+
+/// Shorthand for a constant texture.
+pub fn solid(color: Color) -> Arc<dyn Texture> {
+    Arc::new(SolidColor { color })
+}
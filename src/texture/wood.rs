@@ -0,0 +1,27 @@
+use crate::noise::Noise;
+use crate::texture::Texture;
+use crate::{Color, Point3d};
+
+/// Growth rings around the y axis, perturbed by fBm.
+#[derive(Clone, Copy, Debug)]
+pub struct Wood {
+    pub light: Color,
+    pub dark: Color,
+    /// Rings per unit of radius.
+    pub rings: f64,
+    /// Noise frequency per unit length.
+    pub scale: f64,
+    pub octaves: usize,
+    pub noise: Noise,
+}
+
+impl Texture for Wood {
+    fn value(&self, _u: f64, _v: f64, point: &Point3d) -> Color {
+        let radius = (point.x() * point.x() + point.z() * point.z()).sqrt();
+        let grain = self.noise.fbm(&(*point * self.scale), self.octaves);
+        let ring = (radius * self.rings + grain).rem_euclid(1.0);
+        // Late wood darkens gradually through the ring, then ends abruptly.
+        let t = ring * ring;
+        self.light * (1.0 - t) + self.dark * t
+    }
+}
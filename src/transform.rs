@@ -0,0 +1,334 @@
+use crate::geometry::Aabb;
+use crate::math::degree_to_radian;
+use crate::matrix4::Matrix4;
+use crate::quaternion::Quaternion;
+use crate::{Point3d, Ray, Vector3d};
+use std::ops::Mul;
+
+/// An affine transform together with its inverse and the matrix used for normals.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub matrix: Matrix4,
+    pub inverse: Matrix4,
+    pub normal: Matrix4,
+}
+
+impl Transform {
+    pub fn new(matrix: Matrix4) -> Self {
+        let inverse = matrix.inverse().expect("Transform matrix is not invertible");
+        Transform {
+            matrix,
+            inverse,
+            normal: inverse.transpose(),
+        }
+    }
+
+    pub fn identity() -> Self {
+        Transform::new(Matrix4::identity())
+    }
+
+    pub fn translate(offset: Vector3d) -> Self {
+        let mut m = Matrix4::identity();
+        for i in 0..3 {
+            m[i][3] = offset[i];
+        }
+        Transform::new(m)
+    }
+
+    pub fn scale(factor: Vector3d) -> Self {
+        let mut m = Matrix4::identity();
+        for i in 0..3 {
+            m[i][i] = factor[i];
+        }
+        Transform::new(m)
+    }
+
+    /// Rotation by `degree` around `axis`, counter-clockwise looking down the axis.
+    pub fn rotate(axis: Vector3d, degree: f64) -> Self {
+        let a = axis.unit_vector();
+        let (x, y, z) = (a.x(), a.y(), a.z());
+        let theta = degree_to_radian(degree);
+        let (s, c) = theta.sin_cos();
+        let t = 1.0 - c;
+        Transform::new(Matrix4::new([
+            [t * x * x + c, t * x * y - s * z, t * x * z + s * y, 0.0],
+            [t * x * y + s * z, t * y * y + c, t * y * z - s * x, 0.0],
+            [t * x * z - s * y, t * y * z + s * x, t * z * z + c, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]))
+    }
+
+    pub fn inverted(&self) -> Self {
+        Transform {
+            matrix: self.inverse,
+            inverse: self.matrix,
+            normal: self.matrix.transpose(),
+        }
+    }
+
+    /// The factor every length is multiplied by, for transforms that keep
+    /// shapes: rotations, reflections, translations and uniform scaling.
+    /// `None` for those that stretch or shear.
+    pub fn uniform_scale(&self) -> Option<f64> {
+        let axes = [0, 1, 2].map(|i| {
+            let mut axis = Vector3d::new([0.0; 3]);
+            axis[i] = 1.0;
+            self.apply_vector(&axis)
+        });
+        let scale = axes[0].length();
+        let tolerance = 1e-9 * scale * scale;
+        let similar = (0..3).all(|i| {
+            (axes[i].length_squared() - scale * scale).abs() < tolerance
+                && axes[i].dot(&axes[(i + 1) % 3]).abs() < tolerance
+        });
+        similar.then_some(scale)
+    }
+
+    pub fn apply_point(&self, point: &Point3d) -> Point3d {
+        self.matrix.transform_point(point)
+    }
+
+    pub fn apply_vector(&self, vector: &Vector3d) -> Vector3d {
+        self.matrix.transform_vector(vector)
+    }
+
+    /// Transformed normals are not normalized.
+    pub fn apply_normal(&self, normal: &Vector3d) -> Vector3d {
+        self.normal.transform_vector(normal)
+    }
+
+    /// The direction is not normalized, so `t` is preserved across spaces.
+    pub fn apply_ray(&self, ray: &Ray) -> Ray {
+        Ray {
+            origin: self.apply_point(&ray.origin),
+            direction: self.apply_vector(&ray.direction),
+            time: ray.time,
+            spread: ray.spread,
+        }
+    }
+
+    pub fn inverse_ray(&self, ray: &Ray) -> Ray {
+        Ray {
+            origin: self.inverse.transform_point(&ray.origin),
+            direction: self.inverse.transform_vector(&ray.direction),
+            time: ray.time,
+            spread: ray.spread,
+        }
+    }
+
+    pub fn apply_aabb(&self, aabb: &Aabb) -> Aabb {
+        let corners = aabb.corners().map(|p| self.apply_point(&p));
+        Aabb::from_points(&corners)
+    }
+}
+
+/// `a * b` applies `b` first, then `a`.
+impl Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, other: Transform) -> Self::Output {
+        let inverse = other.inverse * self.inverse;
+        Transform {
+            matrix: self.matrix * other.matrix,
+            inverse,
+            normal: inverse.transpose(),
+        }
+    }
+}
+
+/// A pose at one instant, kept decomposed so poses can be interpolated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keyframe {
+    pub time: f64,
+    pub translation: Vector3d,
+    pub rotation: Quaternion,
+    pub scale: Vector3d,
+}
+
+impl Keyframe {
+    pub fn new(time: f64, translation: Vector3d, rotation: Quaternion, scale: Vector3d) -> Self {
+        Keyframe {
+            time,
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    /// Scale, then rotate, then translate.
+    pub fn transform(&self) -> Transform {
+        Transform::translate(self.translation)
+            * Transform::new(self.rotation.to_matrix())
+            * Transform::scale(self.scale)
+    }
+
+    /// The pose a fraction `t` of the way to `other`.
+    fn lerp(&self, other: &Keyframe, t: f64) -> Keyframe {
+        Keyframe {
+            time: self.time + (other.time - self.time) * t,
+            translation: self.translation + (other.translation - self.translation) * t,
+            rotation: self.rotation.slerp(&other.rotation, t),
+            scale: self.scale + (other.scale - self.scale) * t,
+        }
+    }
+}
+
+/// A transform interpolated between keyframes, held constant outside them.
+#[derive(Clone, Debug)]
+pub struct AnimatedTransform {
+    pub keyframes: Vec<Keyframe>,
+}
+
+impl AnimatedTransform {
+    pub fn new(mut keyframes: Vec<Keyframe>) -> Self {
+        assert!(!keyframes.is_empty(), "Animation needs at least one keyframe");
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        AnimatedTransform { keyframes }
+    }
+
+    pub fn at(&self, time: f64) -> Transform {
+        let keys = &self.keyframes;
+        let next = keys.partition_point(|k| k.time <= time);
+        if next == 0 {
+            keys[0].transform()
+        } else if next == keys.len() {
+            keys[next - 1].transform()
+        } else {
+            // Keys either side of `time` differ in time, so the span is not zero.
+            let (a, b) = (&keys[next - 1], &keys[next]);
+            a.lerp(b, (time - a.time) / (b.time - a.time)).transform()
+        }
+    }
+
+    /// Box enclosing `aabb` over the whole animation. Rotation sweeps curves,
+    /// so each segment is sampled, and the box of each step between samples
+    /// is padded by how far a point can stray from the chord between them.
+    pub fn apply_aabb(&self, aabb: &Aabb) -> Aabb {
+        const STEPS: usize = 16;
+        let keys = &self.keyframes;
+        let first = keys[0].transform().apply_aabb(aabb);
+        keys.windows(2).fold(first, |acc, pair| {
+            let (a, b) = (&pair[0], &pair[1]);
+            // Slerp turns at a steady rate, `angle / STEPS` per step. A point
+            // at distance `r` from the pivot, turning by `phi` while its scale
+            // changes linearly, strays at most `2 r sin(phi / 4)` from the
+            // chord between its ends.
+            let angle = 2.0 * a.rotation.dot(&b.rotation).abs().min(1.0).acos();
+            let reach = aabb
+                .corners()
+                .iter()
+                .flat_map(|corner| [a, b].map(|key| (key.scale * *corner).length()))
+                .fold(0.0, f64::max);
+            let pad = 2.0 * reach * (angle / STEPS as f64 / 4.0).sin();
+            let pad = Vector3d::new([pad, pad, pad]);
+            let mut previous = a.transform().apply_aabb(aabb);
+            (1..=STEPS).fold(acc, |acc, i| {
+                let next = a.lerp(b, i as f64 / STEPS as f64).transform().apply_aabb(aabb);
+                let step = Aabb::surrounding(&previous, &next);
+                previous = next;
+                Aabb::surrounding(&acc, &Aabb::new(step.min - pad, step.max + pad))
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vector3d, b: Vector3d) {
+        assert!((a - b).length() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_inverse() {
+        let t = Transform::translate(Vector3d::new([1.0, 2.0, 3.0]))
+            * Transform::rotate(Vector3d::new([1.0, 1.0, 0.0]), 30.0)
+            * Transform::scale(Vector3d::new([2.0, 3.0, 4.0]));
+        let product = t.matrix * t.inverse;
+        for i in 0..4 {
+            for j in 0..4 {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((product[i][j] - expected).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_apply_point_and_vector() {
+        let t = Transform::translate(Vector3d::new([1.0, 0.0, 0.0]))
+            * Transform::rotate(Vector3d::new([0.0, 0.0, 1.0]), 90.0);
+        let p = t.apply_point(&Point3d::new([1.0, 0.0, 0.0]));
+        assert_close(p, Point3d::new([1.0, 1.0, 0.0]));
+        let v = t.apply_vector(&Vector3d::new([1.0, 0.0, 0.0]));
+        assert_close(v, Vector3d::new([0.0, 1.0, 0.0]));
+    }
+
+    #[test]
+    fn test_apply_normal() {
+        let t = Transform::scale(Vector3d::new([2.0, 1.0, 1.0]));
+        let tangent = t.apply_vector(&Vector3d::new([1.0, -1.0, 0.0]));
+        let normal = t.apply_normal(&Vector3d::new([1.0, 1.0, 0.0]));
+        assert!(tangent.dot(&normal).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_apply_aabb() {
+        let t = Transform::rotate(Vector3d::new([0.0, 1.0, 0.0]), 45.0);
+        let aabb = Aabb::new(Point3d::new([-1.0, -1.0, -1.0]), Point3d::new([1.0, 1.0, 1.0]));
+        let rotated = t.apply_aabb(&aabb);
+        let r = 2.0_f64.sqrt();
+        assert_close(rotated.min, Point3d::new([-r, -1.0, -r]));
+        assert_close(rotated.max, Point3d::new([r, 1.0, r]));
+    }
+
+    #[test]
+    fn test_animated_transform() {
+        let y = Vector3d::new([0.0, 1.0, 0.0]);
+        let one = Vector3d::new([1.0, 1.0, 1.0]);
+        let animation = AnimatedTransform::new(vec![
+            Keyframe::new(1.0, Vector3d::new([2.0, 0.0, 0.0]), Quaternion::from_axis_angle(y, 90.0), one),
+            Keyframe::new(0.0, Vector3d::new([0.0, 0.0, 0.0]), Quaternion::identity(), one),
+        ]);
+        let p = Point3d::new([1.0, 0.0, 0.0]);
+        assert_close(animation.at(-1.0).apply_point(&p), p);
+        assert_close(animation.at(2.0).apply_point(&p), Point3d::new([2.0, 0.0, -1.0]));
+        let h = 0.5_f64.sqrt();
+        assert_close(animation.at(0.5).apply_point(&p), Point3d::new([1.0 + h, 0.0, -h]));
+    }
+
+    #[test]
+    fn test_animated_aabb_encloses_motion() {
+        let one = Vector3d::new([1.0, 1.0, 1.0]);
+        let animation = AnimatedTransform::new(vec![
+            Keyframe::new(0.0, Vector3d::new([0.0, 0.0, 0.0]), Quaternion::identity(), one),
+            Keyframe::new(1.0, Vector3d::new([4.0, 0.0, 0.0]), Quaternion::identity(), one),
+        ]);
+        let aabb = Aabb::new(Point3d::new([-1.0, -1.0, -1.0]), Point3d::new([1.0, 1.0, 1.0]));
+        let swept = animation.apply_aabb(&aabb);
+        assert_close(swept.min, Point3d::new([-1.0, -1.0, -1.0]));
+        assert_close(swept.max, Point3d::new([5.0, 1.0, 1.0]));
+    }
+
+    #[test]
+    fn test_animated_aabb_encloses_rotation() {
+        let (y, one) = (Vector3d::new([0.0, 1.0, 0.0]), Vector3d::new([1.0, 1.0, 1.0]));
+        let animation = AnimatedTransform::new(vec![
+            Keyframe::new(0.0, Vector3d::new([0.0, 0.0, 0.0]), Quaternion::identity(), one),
+            Keyframe::new(1.0, Vector3d::new([0.0, 0.0, 0.0]), Quaternion::from_axis_angle(y, 170.0), one),
+            Keyframe::new(1.0, Vector3d::new([1.0, 0.0, 0.0]), Quaternion::from_axis_angle(y, 170.0), one),
+        ]);
+        let aabb = Aabb::new(Point3d::new([2.0, 0.0, 0.0]), Point3d::new([3.0, 0.1, 0.1]));
+        let swept = animation.apply_aabb(&aabb);
+        assert!(swept.min.data.iter().chain(swept.max.data.iter()).all(|x| x.is_finite()));
+        for i in 0..=1000 {
+            let transform = animation.at(i as f64 / 1000.0);
+            for corner in aabb.corners() {
+                let p = transform.apply_point(&corner);
+                for axis in 0..3 {
+                    assert!(swept.min[axis] <= p[axis] && p[axis] <= swept.max[axis], "{:?}", p);
+                }
+            }
+        }
+    }
+}
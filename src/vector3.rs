@@ -1,6 +1,6 @@
+use crate::math::{random, random_range};
 use rand::distributions::uniform::{SampleRange, SampleUniform};
 use rand::distributions::{Distribution, Standard};
-use rand::{thread_rng, Rng};
 use std::cmp::PartialEq;
 use std::fmt::{Debug, Display};
 use std::iter::{FromIterator, Sum};
@@ -92,12 +92,12 @@ where
     }
 }
 
-impl<T> ToString for Vector3<T>
+impl<T> Display for Vector3<T>
 where
     T: Scalar,
 {
-    fn to_string(&self) -> String {
-        format!("{} {} {}", self.x(), self.y(), self.z())
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.x(), self.y(), self.z())
     }
 }
 
@@ -109,13 +109,13 @@ where
     Standard: Distribution<[T; 3]>,
 {
     pub fn random() -> Self {
-        Vector3::new(thread_rng().gen())
+        Vector3::new(random())
     }
 }
 
 impl Vector3<f64> {
     pub fn random_range(min: f64, max: f64) -> Self {
-        let r = || thread_rng().gen_range(min..max);
+        let r = || random_range(min, max);
         Vector3::new([r(), r(), r()])
     }
 
@@ -132,6 +132,29 @@ impl Vector3<f64> {
         Self::random_in_unit_sphere().unit_vector()
     }
 
+    /// Any two unit vectors completing a right-handed orthonormal basis with
+    /// this unit vector (Duff et al., "Building an Orthonormal Basis, Revisited").
+    pub fn orthonormal_basis(&self) -> (Self, Self) {
+        let sign = 1.0_f64.copysign(self.z());
+        let a = -1.0 / (sign + self.z());
+        let b = self.x() * self.y() * a;
+        (
+            Vector3::new([1.0 + sign * self.x() * self.x() * a, sign * b, -sign * self.x()]),
+            Vector3::new([b, sign + self.y() * self.y() * a, -self.y()]),
+        )
+    }
+
+    /// Unit direction around the unit vector `normal`, distributed by the
+    /// cosine to it, so with density `cos / PI`.
+    pub fn random_cosine_direction(normal: Vector3<f64>) -> Self {
+        // Offsetting by a unit vector gives cosine distributed directions.
+        let direction = normal + Vector3::random_in_unit_vector();
+        if direction.length_squared() < 1e-16 {
+            return normal;
+        }
+        direction.unit_vector()
+    }
+
     pub fn random_in_hemisphere(normal: Vector3<f64>) -> Self {
         let in_unit_sphere = Vector3::random_in_unit_sphere();
         if in_unit_sphere.dot(&normal) > 0.0 {
@@ -150,8 +173,8 @@ where
         let mut data = [Default::default(); 3];
         let mut iterator = iter.into_iter();
 
-        for i in 0..3 {
-            data[i] = iterator
+        for item in data.iter_mut() {
+            *item = iterator
                 .next()
                 .expect("Iterator has insufficient elements.");
         }
@@ -0,0 +1,230 @@
+use crate::{Color, Point3d};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Read, Result, Write};
+
+/// Dense volume data sampled at voxel centers over the unit cube, stored
+/// with x varying fastest, then y, then z.
+#[derive(Clone, Debug)]
+pub struct VoxelGrid {
+    pub resolution: [usize; 3],
+    /// Scattering coefficient per voxel.
+    pub density: Vec<f32>,
+    /// Absorption coefficient per voxel; empty when nothing absorbs.
+    pub absorption: Vec<f32>,
+    /// Emitted radiance per voxel, released where light is absorbed; empty
+    /// when nothing glows.
+    pub emission: Vec<[f32; 3]>,
+}
+
+/// Channel names understood in grid files.
+const CHANNELS: [&str; 5] = [
+    "density",
+    "absorption",
+    "emission.r",
+    "emission.g",
+    "emission.b",
+];
+
+fn invalid(filename: &str, msg: impl std::fmt::Display) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("{}: {}", filename, msg))
+}
+
+impl VoxelGrid {
+    pub fn new(resolution: [usize; 3], density: Vec<f32>) -> Self {
+        assert!(!resolution.contains(&0), "VoxelGrid needs a voxel along each axis");
+        assert_eq!(
+            density.len(),
+            resolution.iter().product::<usize>(),
+            "VoxelGrid size mismatch"
+        );
+        VoxelGrid {
+            resolution,
+            density,
+            absorption: Vec::new(),
+            emission: Vec::new(),
+        }
+    }
+
+    /// Reads a dense grid: a text header `vdbl <nx> <ny> <nz> <channel>...`
+    /// ended by a newline, then little-endian `f32` values with all channels
+    /// of a voxel together. Channels are `density`, `absorption` and
+    /// `emission.r`, `emission.g`, `emission.b`; others are skipped.
+    pub fn load(filename: &str) -> Result<Self> {
+        let mut reader = BufReader::new(File::open(filename)?);
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        let mut fields = header.split_whitespace();
+        if fields.next() != Some("vdbl") {
+            return Err(invalid(filename, "missing vdbl signature"));
+        }
+        let mut resolution = [0; 3];
+        for n in &mut resolution {
+            *n = fields
+                .next()
+                .ok_or_else(|| invalid(filename, "truncated header"))?
+                .parse()
+                .map_err(|e| invalid(filename, e))?;
+        }
+        if resolution.contains(&0) {
+            return Err(invalid(filename, "empty resolution"));
+        }
+        let channels: Vec<Option<usize>> = fields
+            .map(|name| CHANNELS.iter().position(|&c| c == name))
+            .collect();
+        if !channels.contains(&Some(0)) {
+            return Err(invalid(filename, "no density channel"));
+        }
+
+        let count = resolution.iter().product::<usize>();
+        let mut bytes = vec![0; count * channels.len() * 4];
+        reader.read_exact(&mut bytes)?;
+        let mut values = [(); 5].map(|_| Vec::new());
+        for (i, chunk) in bytes.chunks_exact(4).enumerate() {
+            if let Some(channel) = channels[i % channels.len()] {
+                values[channel].push(f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]));
+            }
+        }
+        let [density, absorption, r, g, b] = values;
+        let emission = if r.is_empty() && g.is_empty() && b.is_empty() {
+            Vec::new()
+        } else {
+            let channel = |c: &Vec<f32>, i: usize| c.get(i).copied().unwrap_or(0.0);
+            (0..count)
+                .map(|i| [channel(&r, i), channel(&g, i), channel(&b, i)])
+                .collect()
+        };
+        Ok(VoxelGrid {
+            resolution,
+            density,
+            absorption,
+            emission,
+        })
+    }
+
+    /// Writes the grid in the format read by `load`.
+    pub fn save(&self, filename: &str) -> Result<()> {
+        let [nx, ny, nz] = self.resolution;
+        let mut header = format!("vdbl {} {} {} density", nx, ny, nz);
+        if !self.absorption.is_empty() {
+            header += " absorption";
+        }
+        if !self.emission.is_empty() {
+            header += " emission.r emission.g emission.b";
+        }
+        let mut bytes = header.into_bytes();
+        bytes.push(b'\n');
+        for i in 0..self.density.len() {
+            bytes.extend(self.density[i].to_le_bytes());
+            if let Some(a) = self.absorption.get(i) {
+                bytes.extend(a.to_le_bytes());
+            }
+            if let Some(e) = self.emission.get(i) {
+                e.iter().for_each(|c| bytes.extend(c.to_le_bytes()));
+            }
+        }
+        File::create(filename)?.write_all(&bytes)
+    }
+
+    /// Largest total extinction in any voxel, bounding every interpolated value.
+    pub fn max_extinction(&self) -> f64 {
+        (0..self.density.len())
+            .map(|i| self.density[i] + self.absorption.get(i).copied().unwrap_or(0.0))
+            .fold(0.0_f32, f32::max) as f64
+    }
+
+    /// Voxel indices and weights around `p` in the unit cube, clamped at the faces.
+    fn trilinear(&self, p: &Point3d) -> [(usize, f64); 8] {
+        let [nx, ny, nz] = self.resolution;
+        let axis = |x: f64, n: usize| {
+            let x = (x * n as f64 - 0.5).clamp(0.0, (n - 1) as f64);
+            let i = (x.floor() as usize).min(n.saturating_sub(2));
+            (i, (i + 1).min(n - 1), x - i as f64)
+        };
+        let (x0, x1, fx) = axis(p.x(), nx);
+        let (y0, y1, fy) = axis(p.y(), ny);
+        let (z0, z1, fz) = axis(p.z(), nz);
+        let mut corners = [(0, 0.0); 8];
+        for (c, corner) in corners.iter_mut().enumerate() {
+            let (x, wx) = if c & 1 == 0 { (x0, 1.0 - fx) } else { (x1, fx) };
+            let (y, wy) = if c & 2 == 0 { (y0, 1.0 - fy) } else { (y1, fy) };
+            let (z, wz) = if c & 4 == 0 { (z0, 1.0 - fz) } else { (z1, fz) };
+            *corner = ((z * ny + y) * nx + x, wx * wy * wz);
+        }
+        corners
+    }
+
+    fn interpolate(&self, channel: &[f32], p: &Point3d) -> f64 {
+        if channel.is_empty() {
+            return 0.0;
+        }
+        self.trilinear(p)
+            .iter()
+            .map(|&(i, w)| channel[i] as f64 * w)
+            .sum()
+    }
+
+    pub fn density(&self, p: &Point3d) -> f64 {
+        self.interpolate(&self.density, p)
+    }
+
+    pub fn absorption(&self, p: &Point3d) -> f64 {
+        self.interpolate(&self.absorption, p)
+    }
+
+    pub fn emission(&self, p: &Point3d) -> Color {
+        if self.emission.is_empty() {
+            return Color::black();
+        }
+        self.trilinear(p)
+            .iter()
+            .map(|&(i, w)| {
+                self.emission[i]
+                    .iter()
+                    .map(|&c| c as f64 * w)
+                    .collect::<Color>()
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trilinear() {
+        let grid = VoxelGrid::new([2, 1, 1], vec![0.0, 1.0]);
+        // Voxel centers sit at 1/4 and 3/4; outside them values clamp.
+        assert_eq!(grid.density(&Point3d::new([0.25, 0.5, 0.5])), 0.0);
+        assert!((grid.density(&Point3d::new([0.5, 0.5, 0.5])) - 0.5).abs() < 1e-12);
+        assert_eq!(grid.density(&Point3d::new([1.0, 0.0, 0.0])), 1.0);
+        assert_eq!(grid.absorption(&Point3d::new([0.5, 0.5, 0.5])), 0.0);
+    }
+
+    #[test]
+    fn test_save_load() {
+        let path = std::env::temp_dir().join("voxel_grid_test.vdbl");
+        let path = path.to_str().unwrap();
+        let grid = VoxelGrid {
+            absorption: vec![0.5; 8],
+            emission: (0..8).map(|i| [i as f32, 0.0, 1.0]).collect(),
+            ..VoxelGrid::new([2, 2, 2], (0..8).map(|i| i as f32).collect())
+        };
+        grid.save(path).unwrap();
+        let loaded = VoxelGrid::load(path).unwrap();
+        assert_eq!(loaded.resolution, [2, 2, 2]);
+        assert_eq!(loaded.density, grid.density);
+        assert_eq!(loaded.absorption, grid.absorption);
+        assert_eq!(loaded.emission, grid.emission);
+        assert_eq!(loaded.max_extinction(), 7.5);
+    }
+
+    #[test]
+    fn test_load_rejects_empty_resolution() {
+        let path = std::env::temp_dir().join("voxel_grid_empty.vdbl");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "vdbl 2 0 2 density\n").unwrap();
+        let err = VoxelGrid::load(path).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}